@@ -1,7 +1,5 @@
-use fbx_direct;
 #[macro_use]
 extern crate log;
-use env_logger;
 
 use std::fs::File;
 use std::io::BufReader;
@@ -12,7 +10,7 @@ use fbx_direct::writer::EmitterConfig;
 use fbx_direct::writer::FbxEvent as WriterEvent;
 
 fn indent(size: usize) -> String {
-    const INDENT: &'static str = "    ";
+    const INDENT: &str = "    ";
     (0..size)
         .map(|_| INDENT)
         .fold(String::with_capacity(size * INDENT.len()), |r, s| r + s)
@@ -1,12 +1,10 @@
-use env_logger;
-
 use std::fs::File;
 use std::io::BufReader;
 
 use fbx_direct::reader::{EventReader, FbxEvent};
 
 fn indent(size: usize) -> String {
-    const INDENT: &'static str = "    ";
+    const INDENT: &str = "    ";
     (0..size)
         .map(|_| INDENT)
         .fold(String::with_capacity(size * INDENT.len()), |r, s| r + s)
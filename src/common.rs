@@ -2,9 +2,11 @@
 use base64;
 
 use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
 
 /// Format of FBX data.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FbxFormatType {
     /// Binary FBX, with version (for example, `7400` for FBX 7.4).
     Binary(u32),
@@ -12,6 +14,84 @@ pub enum FbxFormatType {
     Ascii,
 }
 
+/// Non-standard encoding of array properties, which denotes zstd compressed data.
+///
+/// Standard FBX uses only `0` (plain) and `1` (zlib) as encodings of array properties, and this
+/// is used only by some experimental tools. The value is `"zstd"` in ASCII (big endian).
+/// Reading and writing arrays with this encoding requires `zstd` feature of this crate.
+pub const NONSTANDARD_ZSTD_ARRAY_ENCODING: u32 = 0x7a73_7464;
+
+/// Byte size of reserved area in the footer of Binary FBX.
+pub const FOOTER_RESERVED_LEN: usize = 120;
+
+/// Information in the footer of Binary FBX.
+///
+/// The footer consists of 16 bytes of unknown data, padding to 16 bytes alignment, `0u32`, FBX
+/// version, 120 bytes of reserved area, and fixed 16 bytes of end magic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FooterInfo {
+    /// The first 16 bytes of the footer.
+    ///
+    /// The meaning is unknown, but high nibbles seem to be `fbad cdcd 6b7f 81f2 7` and low
+    /// nibbles differ among files.
+    pub head: [u8; 16],
+    /// FBX version written in the footer.
+    pub version: u32,
+    /// Reserved area, which is usually filled with zero but some files have non-zero data.
+    pub reserved: [u8; FOOTER_RESERVED_LEN],
+}
+
+/// Raw bytes of a complete node record of Binary FBX, including its descendants.
+///
+/// This is captured by
+/// [`EventReader::capture_node`](../reader/struct.EventReader.html#method.capture_node), and
+/// written verbatim by
+/// [`EventWriter::write_raw_node`](../writer/struct.EventWriter.html#method.write_raw_node)
+/// for lossless passthrough of nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawNode {
+    /// FBX version of the data which the node is captured from.
+    ///
+    /// The layout of node records depends on the version.
+    pub version: u32,
+    /// Bytes from the beginning of the node record header to the end of the node.
+    ///
+    /// `end_offset` fields of the node and its descendants are offsets in the source data.
+    pub bytes: Vec<u8>,
+}
+
+/// Defines constructors of `OwnedProperty` arrays from iterators.
+macro_rules! impl_owned_property_from_iter {
+    ($($name:ident => $elem:ty, $variant:ident;)*) => {
+        $(
+            #[doc = concat!("Creates `", stringify!($variant), "` by collecting elements convertible into `", stringify!($elem), "`.")]
+            ///
+            /// This allocates a new `Vec`.
+            pub fn $name<I>(iter: I) -> Self
+            where
+                I: IntoIterator,
+                I::Item: Into<$elem>,
+            {
+                OwnedProperty::$variant(iter.into_iter().map(Into::into).collect())
+            }
+        )*
+    };
+}
+
+/// Defines zero-copy constructors of `Property` arrays from slices.
+macro_rules! impl_property_from_slice {
+    ($($name:ident => $elem:ty, $variant:ident;)*) => {
+        $(
+            #[doc = concat!("Creates `", stringify!($variant), "` borrowing the given slice.")]
+            ///
+            /// This does not allocate.
+            pub fn $name(v: &'a [$elem]) -> Self {
+                Property::$variant(v)
+            }
+        )*
+    };
+}
+
 /// A property type of the FBX node.
 #[derive(Debug, Clone, PartialEq)]
 pub enum OwnedProperty {
@@ -43,9 +123,39 @@ pub enum OwnedProperty {
     String(String),
     /// Raw binary data.
     Binary(Vec<u8>),
+    /// String kept as raw bytes.
+    ///
+    /// This is a companion of `String` variant, which is read when
+    /// [`ParserConfig::raw_strings`](../reader/struct.ParserConfig.html#structfield.raw_strings)
+    /// is enabled.
+    /// The bytes may be invalid as UTF-8, and are written as is by the writer, so that
+    /// re-emitted data is byte-identical to the source.
+    RawString(Vec<u8>),
+    /// Array kept as zlib-compressed bytes, without decoding.
+    ///
+    /// This is read instead of `Vec*` variants for compressed arrays when
+    /// [`ParserConfig::raw_compressed_arrays`](../reader/struct.ParserConfig.html#structfield.raw_compressed_arrays)
+    /// is enabled.
+    /// Elements are not available through `get_vec_*` methods, but the bytes are written as is
+    /// by the Binary FBX writer, so that re-emitted data is byte-identical to the source.
+    CompressedArray {
+        /// Type code of the array (`b'f'`, `b'd'`, `b'l'`, `b'i'` or `b'b'`).
+        type_code: u8,
+        /// Number of elements in the array.
+        array_length: u32,
+        /// zlib-compressed elements.
+        data: Vec<u8>,
+    },
 }
 
 impl OwnedProperty {
+    /// Returns the type code of the property in Binary FBX.
+    ///
+    /// See [`Property::fbx_type_code`](enum.Property.html#method.fbx_type_code).
+    pub fn fbx_type_code(&self) -> u8 {
+        self.borrow().fbx_type_code()
+    }
+
     pub fn borrow(&self) -> Property<'_> {
         match *self {
             OwnedProperty::Bool(v) => Property::Bool(v),
@@ -54,13 +164,23 @@ impl OwnedProperty {
             OwnedProperty::I64(v) => Property::I64(v),
             OwnedProperty::F32(v) => Property::F32(v),
             OwnedProperty::F64(v) => Property::F64(v),
-            OwnedProperty::VecBool(ref v) => Property::VecBool(&v),
-            OwnedProperty::VecI32(ref v) => Property::VecI32(&v),
-            OwnedProperty::VecI64(ref v) => Property::VecI64(&v),
-            OwnedProperty::VecF32(ref v) => Property::VecF32(&v),
-            OwnedProperty::VecF64(ref v) => Property::VecF64(&v),
-            OwnedProperty::String(ref v) => Property::String(&v),
-            OwnedProperty::Binary(ref v) => Property::Binary(&v),
+            OwnedProperty::VecBool(ref v) => Property::VecBool(v),
+            OwnedProperty::VecI32(ref v) => Property::VecI32(v),
+            OwnedProperty::VecI64(ref v) => Property::VecI64(v),
+            OwnedProperty::VecF32(ref v) => Property::VecF32(v),
+            OwnedProperty::VecF64(ref v) => Property::VecF64(v),
+            OwnedProperty::String(ref v) => Property::String(v),
+            OwnedProperty::Binary(ref v) => Property::Binary(v),
+            OwnedProperty::RawString(ref v) => Property::RawString(v),
+            OwnedProperty::CompressedArray {
+                type_code,
+                array_length,
+                ref data,
+            } => Property::CompressedArray {
+                type_code,
+                array_length,
+                data,
+            },
         }
     }
 
@@ -95,6 +215,15 @@ impl OwnedProperty {
         }
     }
 
+    /// Checked conversion.
+    ///
+    /// Tries to convert integer property value into `i16`, and returns `None` if the value does
+    /// not fit.
+    /// Unlike `get_i16()`, this succeeds for larger integer types (such as `I64(5)`).
+    pub fn get_i16_checked(&self) -> Option<i16> {
+        self.get_i64().and_then(|v| i16::try_from(v).ok())
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -118,6 +247,15 @@ impl OwnedProperty {
         }
     }
 
+    /// Checked conversion.
+    ///
+    /// Tries to convert integer property value into `i32`, and returns `None` if the value does
+    /// not fit.
+    /// Unlike `get_i32()`, this succeeds for larger integer types (such as `I64(5)`).
+    pub fn get_i32_checked(&self) -> Option<i32> {
+        self.get_i64().and_then(|v| i32::try_from(v).ok())
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -228,7 +366,7 @@ impl OwnedProperty {
             OwnedProperty::VecBool(ref v) => Some(Cow::Owned(
                 v.iter().map(|&v| if v { 1 } else { 0 }).collect(),
             )),
-            OwnedProperty::VecI32(ref v) => Some(Cow::Borrowed(&v)),
+            OwnedProperty::VecI32(ref v) => Some(Cow::Borrowed(v)),
             _ => None,
         }
     }
@@ -255,7 +393,7 @@ impl OwnedProperty {
             OwnedProperty::VecI32(ref v) => {
                 Some(Cow::Owned(v.iter().map(|&v| i64::from(v)).collect()))
             }
-            OwnedProperty::VecI64(ref v) => Some(Cow::Borrowed(&v)),
+            OwnedProperty::VecI64(ref v) => Some(Cow::Borrowed(v)),
             _ => None,
         }
     }
@@ -277,7 +415,7 @@ impl OwnedProperty {
     /// Tries to convert property value into specific type without data loss.
     pub fn get_vec_f32(&self) -> Option<Cow<'_, [f32]>> {
         match *self {
-            OwnedProperty::VecF32(ref v) => Some(Cow::Borrowed(&v)),
+            OwnedProperty::VecF32(ref v) => Some(Cow::Borrowed(v)),
             OwnedProperty::VecF64(ref v) => Some(Cow::Owned(v.iter().map(|&v| v as f32).collect())),
             _ => None,
         }
@@ -294,6 +432,65 @@ impl OwnedProperty {
         }
     }
 
+    /// Checked conversion.
+    ///
+    /// Converts `VecF32` or `VecF64` into `Vec<f32>`, like `into_vec_f32`, but returns
+    /// `ConversionError::OutOfRange` with the first finite element which exceeds the range of
+    /// `f32` (i.e. would be converted into infinity).
+    /// Precision loss of elements in the range is not an error, and non-finite elements are
+    /// converted as is.
+    pub fn try_into_vec_f32_checked(self) -> Result<Vec<f32>, ConversionError> {
+        match self {
+            OwnedProperty::VecF32(v) => Ok(v),
+            OwnedProperty::VecF64(v) => v
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    let converted = value as f32;
+                    if converted.is_infinite() && value.is_finite() {
+                        Err(ConversionError::OutOfRange { index, value })
+                    } else {
+                        Ok(converted)
+                    }
+                })
+                .collect(),
+            v => Err(ConversionError::TypeMismatch(v)),
+        }
+    }
+
+    /// Decodes polygon vertex indices (such as `PolygonVertexIndex` of `Geometry`) in `VecI32`
+    /// into polygons.
+    ///
+    /// In FBX, the last index of each polygon is stored as the bitwise NOT of the index (i.e.
+    /// `-index - 1`), which marks the boundary of polygons. The returned polygons have the real
+    /// indices.
+    /// Returns `ConversionError::UnterminatedPolygon` if the array does not end on a polygon
+    /// boundary, and `ConversionError::UnexpectedType` if the property is not `VecI32`.
+    pub fn decode_polygons(&self) -> Result<Vec<Vec<i32>>, ConversionError> {
+        let indices = match *self {
+            OwnedProperty::VecI32(ref v) => v,
+            ref v => {
+                return Err(ConversionError::UnexpectedType {
+                    type_code: v.fbx_type_code(),
+                })
+            }
+        };
+        let mut polygons = Vec::new();
+        let mut start = 0;
+        for (i, &index) in indices.iter().enumerate() {
+            if index < 0 {
+                let mut polygon = indices[start..i].to_vec();
+                polygon.push(!index);
+                polygons.push(polygon);
+                start = i + 1;
+            }
+        }
+        if start != indices.len() {
+            return Err(ConversionError::UnterminatedPolygon { start });
+        }
+        Ok(polygons)
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -302,7 +499,7 @@ impl OwnedProperty {
             OwnedProperty::VecF32(ref v) => {
                 Some(Cow::Owned(v.iter().map(|&v| f64::from(v)).collect()))
             }
-            OwnedProperty::VecF64(ref v) => Some(Cow::Borrowed(&v)),
+            OwnedProperty::VecF64(ref v) => Some(Cow::Borrowed(v)),
             _ => None,
         }
     }
@@ -321,7 +518,7 @@ impl OwnedProperty {
     /// Get string value if possible.
     pub fn get_string(&self) -> Option<&String> {
         match *self {
-            OwnedProperty::String(ref v) => Some(&v),
+            OwnedProperty::String(ref v) => Some(v),
             _ => None,
         }
     }
@@ -334,6 +531,13 @@ impl OwnedProperty {
         }
     }
 
+    /// Get bytes of string value if possible.
+    ///
+    /// This returns bytes of both `String` and `RawString` variants.
+    pub fn get_string_bytes(&self) -> Option<&[u8]> {
+        self.borrow().get_string_bytes()
+    }
+
     /// Get binary value if possible.
     pub fn get_binary(&self, from_string: bool) -> Option<Cow<'_, [u8]>> {
         match *self {
@@ -356,7 +560,7 @@ impl OwnedProperty {
             OwnedProperty::String(v) => {
                 // In ASCII FBX, binary value is represented as base64-encoded string.
                 if from_string {
-                    base64::decode(&v).or_else(|_| Err(OwnedProperty::String(v)))
+                    base64::decode(&v).map_err(|_| OwnedProperty::String(v))
                 } else {
                     Err(OwnedProperty::String(v))
                 }
@@ -365,6 +569,56 @@ impl OwnedProperty {
             v => Err(v),
         }
     }
+
+    impl_owned_property_from_iter! {
+        from_bool_iter => bool, VecBool;
+        from_i32_iter => i32, VecI32;
+        from_i64_iter => i64, VecI64;
+        from_f32_iter => f32, VecF32;
+        from_f64_iter => f64, VecF64;
+    }
+
+    /// Returns the array of `bool` without conversion, if the property is exactly `VecBool`.
+    ///
+    /// Unlike [`get_vec_bool`](#method.get_vec_bool), this never allocates.
+    pub fn as_vec_bool(&self) -> Option<&[bool]> {
+        self.borrow().as_vec_bool()
+    }
+
+    /// Returns the array of `i32` without conversion, if the property is exactly `VecI32`.
+    ///
+    /// Unlike [`get_vec_i32`](#method.get_vec_i32), this never allocates.
+    pub fn as_vec_i32(&self) -> Option<&[i32]> {
+        self.borrow().as_vec_i32()
+    }
+
+    /// Returns the array of `i64` without conversion, if the property is exactly `VecI64`.
+    ///
+    /// Unlike [`get_vec_i64`](#method.get_vec_i64), this never allocates.
+    pub fn as_vec_i64(&self) -> Option<&[i64]> {
+        self.borrow().as_vec_i64()
+    }
+
+    /// Returns the array of `f32` without conversion, if the property is exactly `VecF32`.
+    ///
+    /// Unlike [`get_vec_f32`](#method.get_vec_f32), this never allocates.
+    pub fn as_vec_f32(&self) -> Option<&[f32]> {
+        self.borrow().as_vec_f32()
+    }
+
+    /// Returns the array of `f64` without conversion, if the property is exactly `VecF64`.
+    ///
+    /// Unlike [`get_vec_f64`](#method.get_vec_f64), this never allocates.
+    pub fn as_vec_f64(&self) -> Option<&[f64]> {
+        self.borrow().as_vec_f64()
+    }
+
+    /// Compares properties, allowing floating point values to differ by `epsilon`.
+    ///
+    /// See [`Property::approx_eq`](enum.Property.html#method.approx_eq).
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.borrow().approx_eq(&other.borrow(), epsilon)
+    }
 }
 
 /// A property type of the FBX node.
@@ -398,9 +652,47 @@ pub enum Property<'a> {
     String(&'a str),
     /// Raw binary data.
     Binary(&'a [u8]),
+    /// String kept as raw bytes.
+    ///
+    /// See [`OwnedProperty::RawString`](enum.OwnedProperty.html#variant.RawString).
+    RawString(&'a [u8]),
+    /// Array kept as zlib-compressed bytes.
+    ///
+    /// See [`OwnedProperty::CompressedArray`](enum.OwnedProperty.html#variant.CompressedArray).
+    CompressedArray {
+        /// Type code of the array (`b'f'`, `b'd'`, `b'l'`, `b'i'` or `b'b'`).
+        type_code: u8,
+        /// Number of elements in the array.
+        array_length: u32,
+        /// zlib-compressed elements.
+        data: &'a [u8],
+    },
 }
 
 impl<'a> Property<'a> {
+    /// Returns the type code of the property in Binary FBX.
+    ///
+    /// `RawString` has the same type code as `String` (`b'S'`), and `CompressedArray` has the
+    /// type code of its elements.
+    pub fn fbx_type_code(&self) -> u8 {
+        match *self {
+            Property::Bool(_) => b'C',
+            Property::I16(_) => b'Y',
+            Property::I32(_) => b'I',
+            Property::I64(_) => b'L',
+            Property::F32(_) => b'F',
+            Property::F64(_) => b'D',
+            Property::VecBool(_) => b'b',
+            Property::VecI32(_) => b'i',
+            Property::VecI64(_) => b'l',
+            Property::VecF32(_) => b'f',
+            Property::VecF64(_) => b'd',
+            Property::String(_) | Property::RawString(_) => b'S',
+            Property::Binary(_) => b'R',
+            Property::CompressedArray { type_code, .. } => type_code,
+        }
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -422,6 +714,15 @@ impl<'a> Property<'a> {
         }
     }
 
+    /// Checked conversion.
+    ///
+    /// Tries to convert integer property value into `i16`, and returns `None` if the value does
+    /// not fit.
+    /// Unlike `get_i16()`, this succeeds for larger integer types (such as `I64(5)`).
+    pub fn get_i16_checked(&self) -> Option<i16> {
+        self.get_i64().and_then(|v| i16::try_from(v).ok())
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -434,6 +735,15 @@ impl<'a> Property<'a> {
         }
     }
 
+    /// Checked conversion.
+    ///
+    /// Tries to convert integer property value into `i32`, and returns `None` if the value does
+    /// not fit.
+    /// Unlike `get_i32()`, this succeeds for larger integer types (such as `I64(5)`).
+    pub fn get_i32_checked(&self) -> Option<i32> {
+        self.get_i64().and_then(|v| i32::try_from(v).ok())
+    }
+
     /// Safe conversion.
     ///
     /// Tries to convert property value into specific type without data loss.
@@ -536,6 +846,17 @@ impl<'a> Property<'a> {
         }
     }
 
+    /// Get bytes of string value if possible.
+    ///
+    /// This returns bytes of both `String` and `RawString` variants.
+    pub fn get_string_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            Property::String(v) => Some(v.as_bytes()),
+            Property::RawString(v) => Some(v),
+            _ => None,
+        }
+    }
+
     /// Get binary value if possible.
     pub fn get_binary(&self, from_string: bool) -> Option<Cow<'_, [u8]>> {
         match *self {
@@ -551,11 +872,461 @@ impl<'a> Property<'a> {
             _ => None,
         }
     }
+
+    impl_property_from_slice! {
+        from_bool_slice => bool, VecBool;
+        from_i32_slice => i32, VecI32;
+        from_i64_slice => i64, VecI64;
+        from_f32_slice => f32, VecF32;
+        from_f64_slice => f64, VecF64;
+    }
+
+    /// Returns the array of `bool` without conversion, if the property is exactly `VecBool`.
+    ///
+    /// Unlike [`get_vec_bool`](#method.get_vec_bool), this never allocates.
+    pub fn as_vec_bool(&self) -> Option<&'a [bool]> {
+        match *self {
+            Property::VecBool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the array of `i32` without conversion, if the property is exactly `VecI32`.
+    ///
+    /// Unlike [`get_vec_i32`](#method.get_vec_i32), this never allocates.
+    pub fn as_vec_i32(&self) -> Option<&'a [i32]> {
+        match *self {
+            Property::VecI32(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the array of `i64` without conversion, if the property is exactly `VecI64`.
+    ///
+    /// Unlike [`get_vec_i64`](#method.get_vec_i64), this never allocates.
+    pub fn as_vec_i64(&self) -> Option<&'a [i64]> {
+        match *self {
+            Property::VecI64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the array of `f32` without conversion, if the property is exactly `VecF32`.
+    ///
+    /// Unlike [`get_vec_f32`](#method.get_vec_f32), this never allocates.
+    pub fn as_vec_f32(&self) -> Option<&'a [f32]> {
+        match *self {
+            Property::VecF32(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the array of `f64` without conversion, if the property is exactly `VecF64`.
+    ///
+    /// Unlike [`get_vec_f64`](#method.get_vec_f64), this never allocates.
+    pub fn as_vec_f64(&self) -> Option<&'a [f64]> {
+        match *self {
+            Property::VecF64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Compares properties, allowing floating point values to differ by `epsilon`.
+    ///
+    /// This is useful to compare data through ASCII FBX, whose decimal representation of
+    /// floating point values may be inexact.
+    /// Floating point scalars (and arrays) are compared by value within the absolute tolerance,
+    /// and `f32` and `f64` values can be compared with each other. Arrays should have the same
+    /// length.
+    /// NaN is considered equal to NaN (unlike `==`), and infinities are equal only to the same
+    /// infinities.
+    /// Other properties (including compressed arrays, which are not decoded) are compared
+    /// strictly, as `==` does.
+    pub fn approx_eq(&self, other: &Property<'_>, epsilon: f64) -> bool {
+        fn scalar_approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+            a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= epsilon
+        }
+        fn slice_approx_eq<A, B>(a: &[A], b: &[B], epsilon: f64) -> bool
+        where
+            A: Copy + Into<f64>,
+            B: Copy + Into<f64>,
+        {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(&a, &b)| scalar_approx_eq(a.into(), b.into(), epsilon))
+        }
+
+        if let (Some(a), Some(b)) = (self.get_f64(), other.get_f64()) {
+            return scalar_approx_eq(a, b, epsilon);
+        }
+        match (self, other) {
+            (Property::VecF32(a), Property::VecF32(b)) => slice_approx_eq(a, b, epsilon),
+            (Property::VecF32(a), Property::VecF64(b)) => slice_approx_eq(a, b, epsilon),
+            (Property::VecF64(a), Property::VecF32(b)) => slice_approx_eq(a, b, epsilon),
+            (Property::VecF64(a), Property::VecF64(b)) => slice_approx_eq(a, b, epsilon),
+            _ => self == other,
+        }
+    }
+}
+
+/// Error of checked conversions of property values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionError {
+    /// The property has a type not convertible into the target type.
+    ///
+    /// The rejected property is returned as is.
+    TypeMismatch(OwnedProperty),
+    /// The borrowed property has a type other than the expected one.
+    UnexpectedType {
+        /// FBX type code of the property.
+        type_code: u8,
+    },
+    /// An array element is not representable in the target type.
+    OutOfRange {
+        /// Index of the first non-representable element.
+        index: usize,
+        /// Value of the element.
+        value: f64,
+    },
+    /// Polygon vertex indices do not end with the last index of a polygon.
+    UnterminatedPolygon {
+        /// Index of the first element of the unterminated polygon.
+        start: usize,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConversionError::TypeMismatch(ref v) => write!(
+                f,
+                "Property of type {:?} is not convertible",
+                v.fbx_type_code() as char
+            ),
+            ConversionError::UnexpectedType { type_code } => write!(
+                f,
+                "Property of type {:?} is not convertible",
+                type_code as char
+            ),
+            ConversionError::OutOfRange { index, value } => write!(
+                f,
+                "Array element #{} ({}) is out of range of the target type",
+                index, value
+            ),
+            ConversionError::UnterminatedPolygon { start } => write!(
+                f,
+                "Polygon starting at the element #{} is not terminated by a negative index",
+                start
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+macro_rules! impl_try_from_owned_property {
+    ($($ty:ty => $into:ident),* $(,)*) => {
+        $(
+            impl TryFrom<OwnedProperty> for $ty {
+                /// The rejected property.
+                type Error = OwnedProperty;
+
+                /// Converts the property in the same manner as
+                #[doc = concat!("[`OwnedProperty::", stringify!($into), "`](enum.OwnedProperty.html#method.", stringify!($into), ").")]
+                fn try_from(v: OwnedProperty) -> Result<Self, Self::Error> {
+                    v.$into()
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_owned_property! {
+    bool => into_bool,
+    i16 => into_i16,
+    i32 => into_i32,
+    i64 => into_i64,
+    f32 => into_f32,
+    f64 => into_f64,
+    Vec<bool> => into_vec_bool,
+    Vec<i32> => into_vec_i32,
+    Vec<i64> => into_vec_i64,
+    Vec<f32> => into_vec_f32,
+    Vec<f64> => into_vec_f64,
+    String => into_string,
+}
+
+impl<'a> PartialEq<Property<'a>> for OwnedProperty {
+    /// Compares variants and values, in the same manner as `PartialEq` for `Property`.
+    fn eq(&self, other: &Property<'a>) -> bool {
+        self.borrow() == *other
+    }
+}
+
+impl<'a> PartialEq<OwnedProperty> for Property<'a> {
+    /// Compares variants and values, in the same manner as `PartialEq` for `Property`.
+    fn eq(&self, other: &OwnedProperty) -> bool {
+        *self == other.borrow()
+    }
+}
+
+macro_rules! impl_property_from {
+    ($($ty:ty => $variant:ident),* $(,)*) => {
+        $(
+            impl<'a> From<$ty> for Property<'a> {
+                fn from(v: $ty) -> Self {
+                    Property::$variant(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_property_from! {
+    bool => Bool,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+    &'a [bool] => VecBool,
+    &'a [i32] => VecI32,
+    &'a [i64] => VecI64,
+    &'a [f32] => VecF32,
+    &'a [f64] => VecF64,
+    &'a str => String,
+    &'a [u8] => Binary,
+}
+
+/// Maximum number of array elements printed by `Display` implementations of properties.
+const DISPLAY_ARRAY_PREVIEW_LEN: usize = 8;
+
+/// Maximum number of bytes printed by `Display` implementations of binary properties.
+const DISPLAY_BINARY_PREVIEW_LEN: usize = 16;
+
+/// Writes a summary of the array, such as `F64[100000]{0.1, 0.2, ...}`.
+fn fmt_array_summary<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    type_name: &str,
+    vec: &[T],
+) -> fmt::Result {
+    write!(f, "{}[{}]{{", type_name, vec.len())?;
+    for (i, v) in vec.iter().take(DISPLAY_ARRAY_PREVIEW_LEN).enumerate() {
+        if i != 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", v)?;
+    }
+    if vec.len() > DISPLAY_ARRAY_PREVIEW_LEN {
+        f.write_str(", ...")?;
+    }
+    f.write_str("}")
+}
+
+impl fmt::Display for OwnedProperty {
+    /// Prints scalar values plainly, and summarizes arrays and binaries.
+    ///
+    /// Use `Debug` format to print whole contents of arrays.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.borrow().fmt(f)
+    }
+}
+
+impl<'a> fmt::Display for Property<'a> {
+    /// Prints scalar values plainly, and summarizes arrays and binaries.
+    ///
+    /// Use `Debug` format to print whole contents of arrays.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Property::Bool(v) => write!(f, "{}", v),
+            Property::I16(v) => write!(f, "{}", v),
+            Property::I32(v) => write!(f, "{}", v),
+            Property::I64(v) => write!(f, "{}", v),
+            Property::F32(v) => write!(f, "{}", v),
+            Property::F64(v) => write!(f, "{}", v),
+            Property::VecBool(v) => fmt_array_summary(f, "Bool", v),
+            Property::VecI32(v) => fmt_array_summary(f, "I32", v),
+            Property::VecI64(v) => fmt_array_summary(f, "I64", v),
+            Property::VecF32(v) => fmt_array_summary(f, "F32", v),
+            Property::VecF64(v) => fmt_array_summary(f, "F64", v),
+            Property::String(v) => write!(f, "{:?}", v),
+            Property::Binary(v) => {
+                write!(f, "Binary[{}]{{", v.len())?;
+                for b in v.iter().take(DISPLAY_BINARY_PREVIEW_LEN) {
+                    write!(f, "{:02x}", b)?;
+                }
+                if v.len() > DISPLAY_BINARY_PREVIEW_LEN {
+                    f.write_str("...")?;
+                }
+                f.write_str("}")
+            }
+            Property::RawString(v) => write!(f, "{:?}", String::from_utf8_lossy(v)),
+            Property::CompressedArray {
+                type_code,
+                array_length,
+                data,
+            } => write!(
+                f,
+                "Compressed{{type={:?}, len={}, bytes={}}}",
+                type_code as char,
+                array_length,
+                data.len()
+            ),
+        }
+    }
+}
+
+/// A value which can be written as a JSON value by `to_debug_json`.
+trait DebugJson {
+    fn write_json(&self, out: &mut String);
+}
+
+impl DebugJson for bool {
+    fn write_json(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+macro_rules! impl_debug_json_for_number {
+    ($($ty:ty),*) => {
+        $(
+            impl DebugJson for $ty {
+                /// Non-finite values are written as strings (such as `"NaN"`), because JSON has
+                /// no representation for them.
+                fn write_json(&self, out: &mut String) {
+                    let s = self.to_string();
+                    if s.parse::<f64>().map_or(false, f64::is_finite) {
+                        out.push_str(&s);
+                    } else {
+                        write_json_string(out, &s);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_debug_json_for_number!(i16, i32, i64, f32, f64);
+
+/// Writes a JSON string with escapes.
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes a JSON array, truncated to `max_elements` elements if specified.
+fn write_json_array<T: DebugJson>(out: &mut String, vec: &[T], max_elements: Option<usize>) {
+    let len = max_elements.map_or(vec.len(), |max| max.min(vec.len()));
+    out.push('[');
+    for (i, v) in vec[..len].iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        v.write_json(out);
+    }
+    if len < vec.len() {
+        if len != 0 {
+            out.push(',');
+        }
+        write_json_string(out, &format!("... ({} more)", vec.len() - len));
+    }
+    out.push(']');
+}
+
+impl OwnedProperty {
+    /// Renders the property as JSON, for debugging.
+    ///
+    /// See [`Property::to_debug_json`](enum.Property.html#method.to_debug_json).
+    pub fn to_debug_json(&self, max_elements: Option<usize>) -> String {
+        self.borrow().to_debug_json(max_elements)
+    }
+}
+
+impl<'a> Property<'a> {
+    /// Renders the property as JSON, for debugging (such as dumping node contents into logs or
+    /// test fixtures).
+    ///
+    /// Scalars are written directly, arrays as JSON arrays, strings as escaped JSON strings, and
+    /// binaries as base64 strings.
+    /// Non-finite floating point values are written as strings (such as `"NaN"`).
+    /// If `max_elements` is specified, arrays are truncated to the number of elements and
+    /// followed by a string such as `"... (42 more)"`. If `None`, whole arrays are written.
+    /// Compressed arrays are not decoded, and written as objects with the type code, the array
+    /// length, and the base64 of the compressed data.
+    pub fn to_debug_json(&self, max_elements: Option<usize>) -> String {
+        let mut out = String::new();
+        match *self {
+            Property::Bool(v) => v.write_json(&mut out),
+            Property::I16(v) => v.write_json(&mut out),
+            Property::I32(v) => v.write_json(&mut out),
+            Property::I64(v) => v.write_json(&mut out),
+            Property::F32(v) => v.write_json(&mut out),
+            Property::F64(v) => v.write_json(&mut out),
+            Property::VecBool(v) => write_json_array(&mut out, v, max_elements),
+            Property::VecI32(v) => write_json_array(&mut out, v, max_elements),
+            Property::VecI64(v) => write_json_array(&mut out, v, max_elements),
+            Property::VecF32(v) => write_json_array(&mut out, v, max_elements),
+            Property::VecF64(v) => write_json_array(&mut out, v, max_elements),
+            Property::String(v) => write_json_string(&mut out, v),
+            Property::RawString(v) => write_json_string(&mut out, &String::from_utf8_lossy(v)),
+            Property::Binary(v) => write_json_string(&mut out, &base64::encode(v)),
+            Property::CompressedArray {
+                type_code,
+                array_length,
+                data,
+            } => {
+                out.push_str("{\"type\":");
+                write_json_string(&mut out, &(type_code as char).to_string());
+                out.push_str(&format!(",\"array_length\":{},\"data\":", array_length));
+                write_json_string(&mut out, &base64::encode(data));
+                out.push('}');
+            }
+        }
+        out
+    }
 }
 
 #[cfg(test)]
 mod property_tests {
-    use super::OwnedProperty;
+    use super::{ConversionError, OwnedProperty, Property};
+
+    #[test]
+    fn constructors_from_slices_and_iterators() {
+        let values = [1_i32, -2, 3];
+        assert_eq!(
+            Property::from_i32_slice(&values),
+            Property::VecI32(&[1, -2, 3])
+        );
+        let values_u16: Vec<u16> = vec![1, 2, 65535];
+        assert_eq!(
+            OwnedProperty::from_i32_iter(values_u16.iter().copied()),
+            OwnedProperty::VecI32(vec![1, 2, 65535])
+        );
+        assert_eq!(
+            OwnedProperty::from_i64_iter(vec![u32::MAX, 0]),
+            OwnedProperty::VecI64(vec![i64::from(u32::MAX), 0])
+        );
+        assert_eq!(
+            OwnedProperty::from_f64_iter((0..3).map(|v| v as f32 * 0.5)),
+            OwnedProperty::VecF64(vec![0.0, 0.5, 1.0])
+        );
+        assert_eq!(
+            OwnedProperty::from_bool_iter([true, false].iter().copied()),
+            OwnedProperty::VecBool(vec![true, false])
+        );
+    }
 
     #[test]
     fn owned_vec_i32_to_vec_i64() {
@@ -577,4 +1348,236 @@ mod property_tests {
         let dst = src.get_vec_i64().unwrap().into_owned();
         assert_eq!(vec_i64, dst);
     }
+
+    #[test]
+    fn display_scalars() {
+        assert_eq!(OwnedProperty::Bool(true).to_string(), "true");
+        assert_eq!(OwnedProperty::I32(-42).to_string(), "-42");
+        assert_eq!(OwnedProperty::F64(0.5).to_string(), "0.5");
+        assert_eq!(
+            OwnedProperty::String("a\"b".to_string()).to_string(),
+            r#""a\"b""#
+        );
+    }
+
+    #[test]
+    fn display_summarizes_arrays() {
+        let short = OwnedProperty::VecI32(vec![1, 2, 3]);
+        assert_eq!(short.to_string(), "I32[3]{1, 2, 3}");
+        let long = OwnedProperty::VecF64((0..100_000).map(f64::from).collect());
+        assert_eq!(long.to_string(), "F64[100000]{0, 1, 2, 3, 4, 5, 6, 7, ...}");
+        let empty = OwnedProperty::VecBool(vec![]);
+        assert_eq!(empty.to_string(), "Bool[0]{}");
+        let binary = OwnedProperty::Binary((0..20).collect());
+        assert_eq!(
+            binary.to_string(),
+            "Binary[20]{000102030405060708090a0b0c0d0e0f...}"
+        );
+    }
+
+    #[test]
+    fn compare_owned_and_borrowed() {
+        let owned = OwnedProperty::VecF64(vec![0.5, 1.5]);
+        assert_eq!(owned, Property::VecF64(&[0.5, 1.5]));
+        assert_eq!(Property::VecF64(&[0.5, 1.5]), owned);
+        assert_ne!(owned, Property::VecF32(&[0.5, 1.5]));
+        assert_eq!(
+            OwnedProperty::String("foo".to_string()),
+            Property::String("foo")
+        );
+        assert_ne!(OwnedProperty::I32(1), Property::I64(1));
+        // Same as derived `PartialEq`, NaN is not equal to NaN.
+        assert_ne!(OwnedProperty::F64(f64::NAN), Property::F64(f64::NAN));
+    }
+
+    #[test]
+    fn try_from_owned_property() {
+        use std::convert::TryFrom;
+
+        assert_eq!(i64::try_from(OwnedProperty::I32(-3)), Ok(-3));
+        assert_eq!(f64::try_from(OwnedProperty::F32(1.5)), Ok(1.5));
+        assert_eq!(
+            String::try_from(OwnedProperty::String("foo".to_string())),
+            Ok("foo".to_string())
+        );
+        assert_eq!(
+            Vec::<i64>::try_from(OwnedProperty::VecI32(vec![1, 2])),
+            Ok(vec![1, 2])
+        );
+        // The rejected property is returned.
+        assert_eq!(
+            i32::try_from(OwnedProperty::F64(0.5)),
+            Err(OwnedProperty::F64(0.5))
+        );
+    }
+
+    #[test]
+    fn as_vec_exact_type() {
+        let vec_i32 = OwnedProperty::VecI32(vec![1, 2, 3]);
+        assert_eq!(vec_i32.as_vec_i32(), Some(&[1, 2, 3][..]));
+        // No conversion, even if it is lossless.
+        assert_eq!(vec_i32.as_vec_i64(), None);
+        assert!(vec_i32.get_vec_i64().is_some());
+
+        let vec_f64 = [0.5, 1.5];
+        assert_eq!(Property::VecF64(&vec_f64).as_vec_f64(), Some(&vec_f64[..]));
+        assert_eq!(Property::VecF64(&vec_f64).as_vec_f32(), None);
+        assert_eq!(Property::F64(0.5).as_vec_f64(), None);
+    }
+
+    #[test]
+    fn fbx_type_code() {
+        let cases: &[(Property<'_>, u8)] = &[
+            (Property::Bool(true), b'C'),
+            (Property::I16(1), b'Y'),
+            (Property::I32(1), b'I'),
+            (Property::I64(1), b'L'),
+            (Property::F32(1.0), b'F'),
+            (Property::F64(1.0), b'D'),
+            (Property::VecBool(&[true]), b'b'),
+            (Property::VecI32(&[1]), b'i'),
+            (Property::VecI64(&[1]), b'l'),
+            (Property::VecF32(&[1.0]), b'f'),
+            (Property::VecF64(&[1.0]), b'd'),
+            (Property::String("a"), b'S'),
+            (Property::RawString(b"a"), b'S'),
+            (Property::Binary(b"a"), b'R'),
+            (
+                Property::CompressedArray {
+                    type_code: b'f',
+                    array_length: 0,
+                    data: &[],
+                },
+                b'f',
+            ),
+        ];
+        for (prop, code) in cases {
+            assert_eq!(prop.fbx_type_code(), *code, "type code of {:?}", prop);
+        }
+        assert_eq!(OwnedProperty::I16(1).fbx_type_code(), b'Y');
+    }
+
+    #[test]
+    fn debug_json() {
+        assert_eq!(Property::Bool(true).to_debug_json(None), "true");
+        assert_eq!(Property::I64(-3).to_debug_json(None), "-3");
+        assert_eq!(Property::F64(0.5).to_debug_json(None), "0.5");
+        assert_eq!(Property::F32(f32::NAN).to_debug_json(None), "\"NaN\"");
+        assert_eq!(
+            Property::F64(f64::NEG_INFINITY).to_debug_json(None),
+            "\"-inf\""
+        );
+        assert_eq!(
+            Property::String("a\"b\\c\n\u{1}").to_debug_json(None),
+            r#""a\"b\\c\n\u0001""#
+        );
+        assert_eq!(Property::Binary(b"abc").to_debug_json(None), "\"YWJj\"");
+        assert_eq!(
+            OwnedProperty::VecI32(vec![1, 2, 3]).to_debug_json(None),
+            "[1,2,3]"
+        );
+        assert_eq!(
+            Property::VecBool(&[true, false, true]).to_debug_json(Some(2)),
+            "[true,false,\"... (1 more)\"]"
+        );
+        assert_eq!(
+            Property::VecF64(&[1.0]).to_debug_json(Some(0)),
+            "[\"... (1 more)\"]"
+        );
+        assert_eq!(Property::VecF64(&[]).to_debug_json(Some(0)), "[]");
+        assert_eq!(
+            Property::CompressedArray {
+                type_code: b'd',
+                array_length: 2,
+                data: b"abc",
+            }
+            .to_debug_json(None),
+            r#"{"type":"d","array_length":2,"data":"YWJj"}"#
+        );
+    }
+
+    #[test]
+    fn checked_integer_conversions() {
+        assert_eq!(OwnedProperty::I64(5).get_i32_checked(), Some(5));
+        assert_eq!(OwnedProperty::I64(5).get_i32(), None);
+        assert_eq!(OwnedProperty::I64(1 << 31).get_i32_checked(), None);
+        assert_eq!(
+            OwnedProperty::I64(-(1 << 31)).get_i32_checked(),
+            Some(i32::MIN)
+        );
+        assert_eq!(OwnedProperty::I32(-5).get_i16_checked(), Some(-5));
+        assert_eq!(OwnedProperty::I32(1 << 15).get_i16_checked(), None);
+        assert_eq!(OwnedProperty::Bool(true).get_i16_checked(), Some(1));
+        assert_eq!(OwnedProperty::F64(1.0).get_i32_checked(), None);
+        assert_eq!(Property::I64(-7).get_i16_checked(), Some(-7));
+        assert_eq!(Property::I64(i64::MAX).get_i32_checked(), None);
+    }
+
+    #[test]
+    fn approx_eq() {
+        let eps = 1e-6;
+        assert!(OwnedProperty::F64(0.1).approx_eq(&OwnedProperty::F64(0.1 + 1e-9), eps));
+        assert!(!OwnedProperty::F64(0.1).approx_eq(&OwnedProperty::F64(0.2), eps));
+        assert!(OwnedProperty::F32(0.1).approx_eq(&OwnedProperty::F64(0.1), eps));
+        assert!(OwnedProperty::F64(f64::NAN).approx_eq(&OwnedProperty::F64(f64::NAN), eps));
+        assert!(!OwnedProperty::F64(f64::NAN).approx_eq(&OwnedProperty::F64(0.0), eps));
+        assert!(
+            OwnedProperty::F64(f64::INFINITY).approx_eq(&OwnedProperty::F32(f32::INFINITY), eps)
+        );
+        assert!(!OwnedProperty::F64(f64::INFINITY).approx_eq(&OwnedProperty::F64(f64::MAX), eps));
+        assert!(OwnedProperty::VecF32(vec![0.1, 0.2])
+            .approx_eq(&OwnedProperty::VecF64(vec![0.1, 0.2]), eps));
+        assert!(!OwnedProperty::VecF64(vec![0.1, 0.2])
+            .approx_eq(&OwnedProperty::VecF64(vec![0.1]), eps));
+        // Others are compared strictly.
+        assert!(!OwnedProperty::I32(1).approx_eq(&OwnedProperty::I32(2), 10.0));
+        assert!(!OwnedProperty::I32(1).approx_eq(&OwnedProperty::I64(1), eps));
+        assert!(!OwnedProperty::I32(1).approx_eq(&OwnedProperty::F64(1.0), eps));
+        assert!(OwnedProperty::String("a".to_string())
+            .approx_eq(&OwnedProperty::String("a".to_string()), eps));
+    }
+
+    #[test]
+    fn try_into_vec_f32_checked() {
+        assert_eq!(
+            OwnedProperty::VecF64(vec![0.5, -1e30, f64::from(f32::MAX), f64::NAN])
+                .try_into_vec_f32_checked()
+                .map(|v| v[..3].to_vec()),
+            Ok(vec![0.5, -1e30, f32::MAX])
+        );
+        assert_eq!(
+            OwnedProperty::VecF64(vec![0.5, f64::INFINITY, -1e39, 1e300])
+                .try_into_vec_f32_checked(),
+            Err(ConversionError::OutOfRange {
+                index: 2,
+                value: -1e39
+            })
+        );
+        // Lossy conversion still converts out-of-range values into infinities.
+        assert_eq!(
+            OwnedProperty::VecF64(vec![1e300]).into_vec_f32(),
+            Ok(vec![f32::INFINITY])
+        );
+        assert_eq!(
+            OwnedProperty::I32(1).try_into_vec_f32_checked(),
+            Err(ConversionError::TypeMismatch(OwnedProperty::I32(1)))
+        );
+    }
+
+    #[test]
+    fn decode_polygons() {
+        assert_eq!(
+            OwnedProperty::VecI32(vec![0, 1, -3, 2, 3, 4, -1, -6]).decode_polygons(),
+            Ok(vec![vec![0, 1, 2], vec![2, 3, 4, 0], vec![5]])
+        );
+        assert_eq!(OwnedProperty::VecI32(vec![]).decode_polygons(), Ok(vec![]));
+        assert_eq!(
+            OwnedProperty::VecI32(vec![0, 1, -3, 2, 3]).decode_polygons(),
+            Err(ConversionError::UnterminatedPolygon { start: 3 })
+        );
+        assert_eq!(
+            OwnedProperty::VecI64(vec![-1]).decode_polygons(),
+            Err(ConversionError::UnexpectedType { type_code: b'l' })
+        );
+    }
 }
@@ -6,8 +6,6 @@
 //! difficult to use directly as COLLADA data.
 //! Compare FBX to COLLADA, this crate is XML reader/writer, not COLLADA importer/exporter.
 
-use byteorder;
-
 pub use crate::reader::EventReader;
 pub use crate::writer::EventWriter;
 
@@ -0,0 +1,463 @@
+//! Contains arena of nodes, which stores whole node tree in a flat `Vec`.
+
+use crate::common::OwnedProperty;
+use crate::reader::error::{Error, ErrorKind, Result};
+use crate::reader::{EventReader, FbxEvent};
+use std::convert::TryFrom;
+use std::io::Read;
+
+/// A node stored in [`Arena`](struct.Arena.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaNode {
+    /// Node name.
+    pub name: String,
+    /// Node properties.
+    pub properties: Vec<OwnedProperty>,
+    /// Index of the parent node.
+    parent: Option<usize>,
+    /// Index of the first child node.
+    first_child: Option<usize>,
+    /// Index of the next sibling node.
+    next_sibling: Option<usize>,
+}
+
+impl ArenaNode {
+    fn new(name: String, properties: Vec<OwnedProperty>, parent: Option<usize>) -> Self {
+        ArenaNode {
+            name,
+            properties,
+            parent,
+            first_child: None,
+            next_sibling: None,
+        }
+    }
+
+    /// Returns index of the parent node.
+    ///
+    /// `None` is returned for the root node.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+}
+
+/// Appends a node as the last child of the innermost open node, and opens it.
+///
+/// `open_stack` has indices of open nodes, and indices of their last children.
+fn push_node(
+    nodes: &mut Vec<ArenaNode>,
+    open_stack: &mut Vec<(usize, Option<usize>)>,
+    name: String,
+    properties: Vec<OwnedProperty>,
+) {
+    let index = nodes.len();
+    let (parent, last_child) = open_stack
+        .last_mut()
+        .expect("Implicit root node should be always open");
+    nodes.push(ArenaNode::new(name, properties, Some(*parent)));
+    match last_child.replace(index) {
+        Some(prev) => nodes[prev].next_sibling = Some(index),
+        None => nodes[*parent].first_child = Some(index),
+    }
+    open_stack.push((index, None));
+}
+
+/// Node tree stored in a single `Vec`, with index-based links among nodes.
+///
+/// Nodes are stored in document order, and the node at index 0 is the implicit root node, which
+/// has an empty name, no properties, and top-level nodes as its children.
+/// Compared to nested `Vec`s of children, this is faster to traverse and to drop, because nodes
+/// are contiguous and dropping does not recurse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arena {
+    nodes: Vec<ArenaNode>,
+}
+
+impl Arena {
+    /// Reads events until `EndFbx` and builds an arena.
+    ///
+    /// Comments are ignored.
+    pub fn read<R: Read>(reader: &mut EventReader<R>) -> Result<Self> {
+        let mut nodes = vec![ArenaNode::new(String::new(), vec![], None)];
+        // Indices of open nodes, and indices of their last children.
+        let mut open_stack: Vec<(usize, Option<usize>)> = vec![(0, None)];
+        loop {
+            match reader.next()? {
+                FbxEvent::StartFbx(_) | FbxEvent::Comment(_) => {}
+                FbxEvent::StartNode { name, properties } => {
+                    push_node(&mut nodes, &mut open_stack, name, properties)
+                }
+                FbxEvent::EndNode => {
+                    if open_stack.len() <= 1 {
+                        return Err(Error::new(
+                            reader.parser.pos(),
+                            ErrorKind::DataError("Unexpected end of node".to_string()),
+                        ));
+                    }
+                    open_stack.pop();
+                }
+                FbxEvent::EndFbx => break,
+            }
+        }
+        Ok(Arena { nodes })
+    }
+
+    /// Reads events until `EndFbx` and builds an arena, skipping broken parts of the data.
+    ///
+    /// This is a best-effort variant of [`read`](#method.read), for salvaging data from broken
+    /// files.
+    /// When an error occurs, this records the error and continues reading after the broken part
+    /// (see [`EventReader::recover`](struct.EventReader.html#method.recover)).
+    /// If the reader cannot recover from an error, this stops reading and closes the open nodes.
+    /// Returns the arena and the errors in the order encountered.
+    ///
+    /// Note that the returned tree may be incomplete even if no errors are returned for some
+    /// nodes: nodes with broken data are omitted, and the rest of the children of a node are
+    /// omitted after an error in them.
+    /// If the errors are empty, the arena is the same as returned by `read`.
+    pub fn read_tree_lenient<R: Read>(reader: &mut EventReader<R>) -> (Self, Vec<Error>) {
+        let mut nodes = vec![ArenaNode::new(String::new(), vec![], None)];
+        let mut open_stack: Vec<(usize, Option<usize>)> = vec![(0, None)];
+        let mut errors = vec![];
+        loop {
+            let event = match reader.next() {
+                Ok(event) => event,
+                Err(err) => {
+                    errors.push(err);
+                    if reader.recover() {
+                        continue;
+                    }
+                    break;
+                }
+            };
+            match event {
+                FbxEvent::StartFbx(_) | FbxEvent::Comment(_) => {}
+                FbxEvent::StartNode { name, properties } => {
+                    push_node(&mut nodes, &mut open_stack, name, properties)
+                }
+                FbxEvent::EndNode => {
+                    if open_stack.len() <= 1 {
+                        errors.push(Error::new(
+                            reader.parser.pos(),
+                            ErrorKind::DataError("Unexpected end of node".to_string()),
+                        ));
+                        break;
+                    }
+                    open_stack.pop();
+                }
+                FbxEvent::EndFbx => break,
+            }
+        }
+        (Arena { nodes }, errors)
+    }
+
+    /// Returns index of the implicit root node.
+    pub fn root(&self) -> usize {
+        0
+    }
+
+    /// Returns the node at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn node(&self, index: usize) -> &ArenaNode {
+        &self.nodes[index]
+    }
+
+    /// Returns an iterator of indices of children of the node at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn children(&self, index: usize) -> Children<'_> {
+        Children {
+            arena: self,
+            next: self.nodes[index].first_child,
+        }
+    }
+
+    /// Returns the number of nodes, including the implicit root node.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the arena has no nodes except for the implicit root node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() <= 1
+    }
+
+    /// Returns index of the first child with the given name of the node at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn child_by_name(&self, index: usize, name: &str) -> Option<usize> {
+        self.children(index)
+            .find(|&child| self.nodes[child].name == name)
+    }
+
+    /// Returns the creation timestamp in `FBXHeaderExtension/CreationTimeStamp`.
+    ///
+    /// `None` is returned if the node is absent, or any of the fields (except for `Millisecond`)
+    /// is missing or is not an integer.
+    pub fn creation_time_stamp(&self) -> Option<CreationTimeStamp> {
+        let header = self.child_by_name(self.root(), "FBXHeaderExtension")?;
+        let stamp = self.child_by_name(header, "CreationTimeStamp")?;
+        let field = |name: &str| -> Option<i32> {
+            let node = &self.nodes[self.child_by_name(stamp, name)?];
+            match node.properties.first()? {
+                OwnedProperty::I16(v) => Some(i32::from(*v)),
+                OwnedProperty::I32(v) => Some(*v),
+                OwnedProperty::I64(v) => i32::try_from(*v).ok(),
+                _ => None,
+            }
+        };
+        Some(CreationTimeStamp {
+            year: field("Year")?,
+            month: field("Month")?,
+            day: field("Day")?,
+            hour: field("Hour")?,
+            minute: field("Minute")?,
+            second: field("Second")?,
+            millisecond: field("Millisecond").unwrap_or(0),
+        })
+    }
+
+    /// Returns the settings in `GlobalSettings/Properties70`.
+    ///
+    /// `None` is returned if `GlobalSettings` node is absent. Fields absent in the data (or
+    /// having values of unexpected types) are set to the defaults (see
+    /// [`GlobalSettings`](struct.GlobalSettings.html)).
+    pub fn global_settings(&self) -> Option<GlobalSettings> {
+        let settings = self.child_by_name(self.root(), "GlobalSettings")?;
+        let value = |name: &str| self.properties70_value(settings, name);
+        let int = |name: &str| value(name).and_then(property_to_i64);
+        let int32 = |name: &str| int(name).and_then(|v| i32::try_from(v).ok());
+        let float = |name: &str| -> Option<f64> {
+            match *value(name)? {
+                OwnedProperty::F32(v) => Some(f64::from(v)),
+                OwnedProperty::F64(v) => Some(v),
+                _ => int(name).map(|v| v as f64),
+            }
+        };
+        let default = GlobalSettings::default();
+        Some(GlobalSettings {
+            up_axis: int32("UpAxis").unwrap_or(default.up_axis),
+            up_axis_sign: int32("UpAxisSign").unwrap_or(default.up_axis_sign),
+            front_axis: int32("FrontAxis").unwrap_or(default.front_axis),
+            front_axis_sign: int32("FrontAxisSign").unwrap_or(default.front_axis_sign),
+            coord_axis: int32("CoordAxis").unwrap_or(default.coord_axis),
+            coord_axis_sign: int32("CoordAxisSign").unwrap_or(default.coord_axis_sign),
+            unit_scale_factor: float("UnitScaleFactor").unwrap_or(default.unit_scale_factor),
+            original_unit_scale_factor: float("OriginalUnitScaleFactor")
+                .unwrap_or(default.original_unit_scale_factor),
+            time_mode: int32("TimeMode").unwrap_or(default.time_mode),
+            time_span_start: int("TimeSpanStart").unwrap_or(default.time_span_start),
+            time_span_stop: int("TimeSpanStop").unwrap_or(default.time_span_stop),
+            custom_frame_rate: float("CustomFrameRate").unwrap_or(default.custom_frame_rate),
+        })
+    }
+
+    /// Returns the names and the time ranges of animation stacks (takes).
+    ///
+    /// Animation stacks are read from `Objects/AnimationStack` nodes of FBX 7.x. If there are
+    /// none, takes are read from `Takes/Take` nodes (which FBX 6.x has instead, and FBX 7.x has
+    /// as well).
+    /// An empty `Vec` is returned if no animation sections exist.
+    pub fn animation_takes(&self) -> Vec<AnimationTake> {
+        let stacks = self
+            .child_by_name(self.root(), "Objects")
+            .into_iter()
+            .flat_map(|objects| self.children(objects))
+            .filter(|&node| self.nodes[node].name == "AnimationStack")
+            .filter_map(|node| {
+                let name = match self.nodes[node].properties.get(1)? {
+                    OwnedProperty::String(name) => object_name(name),
+                    _ => return None,
+                };
+                let time = |start: &str, stop: &str| -> Option<(i64, i64)> {
+                    let value = |name: &str| self.properties70_value(node, name);
+                    Some((
+                        value(start).and_then(property_to_i64)?,
+                        value(stop).and_then(property_to_i64)?,
+                    ))
+                };
+                Some(AnimationTake {
+                    name: name.to_string(),
+                    local_time: time("LocalStart", "LocalStop"),
+                    reference_time: time("ReferenceStart", "ReferenceStop"),
+                })
+            })
+            .collect::<Vec<_>>();
+        if !stacks.is_empty() {
+            return stacks;
+        }
+        self.child_by_name(self.root(), "Takes")
+            .into_iter()
+            .flat_map(|takes| self.children(takes))
+            .filter(|&node| self.nodes[node].name == "Take")
+            .filter_map(|node| {
+                let name = match self.nodes[node].properties.first()? {
+                    OwnedProperty::String(name) => name,
+                    _ => return None,
+                };
+                let time = |name: &str| -> Option<(i64, i64)> {
+                    let props = &self.nodes[self.child_by_name(node, name)?].properties;
+                    Some((
+                        property_to_i64(props.first()?)?,
+                        property_to_i64(props.get(1)?)?,
+                    ))
+                };
+                Some(AnimationTake {
+                    name: name.clone(),
+                    local_time: time("LocalTime"),
+                    reference_time: time("ReferenceTime"),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the value of the property with the given name in `Properties70` child of the
+    /// node at the given index.
+    fn properties70_value(&self, index: usize, name: &str) -> Option<&OwnedProperty> {
+        let props = self.child_by_name(index, "Properties70")?;
+        // A `P` node has the name, the type, the label, the flags, and the value.
+        self.children(props)
+            .map(|p| &self.nodes[p])
+            .find(|p| {
+                p.name == "P"
+                    && matches!(p.properties.first(), Some(OwnedProperty::String(v)) if v == name)
+            })?
+            .properties
+            .get(4)
+    }
+}
+
+/// Returns the value of an integer property as `i64`.
+fn property_to_i64(property: &OwnedProperty) -> Option<i64> {
+    match *property {
+        OwnedProperty::I16(v) => Some(i64::from(v)),
+        OwnedProperty::I32(v) => Some(i64::from(v)),
+        OwnedProperty::I64(v) => Some(v),
+        _ => None,
+    }
+}
+
+/// Returns the object name without the class name.
+///
+/// Binary FBX uses `"name\u{0}\u{1}class"`, and ASCII FBX uses `"class::name"`.
+fn object_name(name: &str) -> &str {
+    match name.find("\u{0}\u{1}") {
+        Some(pos) => &name[..pos],
+        None => name.split_once("::").map_or(name, |(_, name)| name),
+    }
+}
+
+/// Name and time ranges of an animation stack (take).
+///
+/// Times are in FBX time units (1/46186158000 seconds), as
+/// [`GlobalSettings::time_span_start`](struct.GlobalSettings.html#structfield.time_span_start).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationTake {
+    /// Name of the animation stack (without the class name), or the take.
+    pub name: String,
+    /// Start and stop of the local time span (`LocalStart` and `LocalStop` of an animation
+    /// stack, or `LocalTime` of a take).
+    ///
+    /// This is `None` if absent.
+    pub local_time: Option<(i64, i64)>,
+    /// Start and stop of the reference time span (`ReferenceStart` and `ReferenceStop` of an
+    /// animation stack, or `ReferenceTime` of a take).
+    ///
+    /// This is `None` if absent.
+    pub reference_time: Option<(i64, i64)>,
+}
+
+/// Global settings of FBX data, stored in `GlobalSettings/Properties70`.
+///
+/// Values are stored as is, and they are not validated. Axes are `0` (X), `1` (Y), or `2` (Z),
+/// and signs are `1` or `-1`.
+/// The defaults (for absent fields) are the defaults of the FBX SDK, i.e. Y-up with centimeter
+/// units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalSettings {
+    /// Up axis (`UpAxis`). Default is `1`.
+    pub up_axis: i32,
+    /// Sign of the up axis (`UpAxisSign`). Default is `1`.
+    pub up_axis_sign: i32,
+    /// Front axis (`FrontAxis`). Default is `2`.
+    pub front_axis: i32,
+    /// Sign of the front axis (`FrontAxisSign`). Default is `1`.
+    pub front_axis_sign: i32,
+    /// Coordinate axis (`CoordAxis`). Default is `0`.
+    pub coord_axis: i32,
+    /// Sign of the coordinate axis (`CoordAxisSign`). Default is `1`.
+    pub coord_axis_sign: i32,
+    /// Size of a unit in centimeters (`UnitScaleFactor`). Default is `1.0`.
+    pub unit_scale_factor: f64,
+    /// Unit scale factor of the original data (`OriginalUnitScaleFactor`). Default is `1.0`.
+    pub original_unit_scale_factor: f64,
+    /// Time mode (`TimeMode`), such as `6` for 30 frames per second and `14` for a custom frame
+    /// rate. Default is `0` (the default mode of the application).
+    pub time_mode: i32,
+    /// Start of the time span (`TimeSpanStart`), in FBX time units (1/46186158000 seconds).
+    /// Default is `0`.
+    pub time_span_start: i64,
+    /// Stop of the time span (`TimeSpanStop`), in FBX time units. Default is `0`.
+    pub time_span_stop: i64,
+    /// Frame rate for the custom time mode (`CustomFrameRate`). Default is `-1.0`.
+    pub custom_frame_rate: f64,
+}
+
+impl Default for GlobalSettings {
+    fn default() -> Self {
+        GlobalSettings {
+            up_axis: 1,
+            up_axis_sign: 1,
+            front_axis: 2,
+            front_axis_sign: 1,
+            coord_axis: 0,
+            coord_axis_sign: 1,
+            unit_scale_factor: 1.0,
+            original_unit_scale_factor: 1.0,
+            time_mode: 0,
+            time_span_start: 0,
+            time_span_stop: 0,
+            custom_frame_rate: -1.0,
+        }
+    }
+}
+
+/// Creation timestamp of FBX data, stored in `FBXHeaderExtension/CreationTimeStamp`.
+///
+/// Values are stored as is, and they are not validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreationTimeStamp {
+    /// Year.
+    pub year: i32,
+    /// Month (1 to 12).
+    pub month: i32,
+    /// Day of the month (1 to 31).
+    pub day: i32,
+    /// Hour (0 to 23).
+    pub hour: i32,
+    /// Minute (0 to 59).
+    pub minute: i32,
+    /// Second (0 to 59).
+    pub second: i32,
+    /// Millisecond (0 to 999).
+    ///
+    /// This is `0` if the field is absent.
+    pub millisecond: i32,
+}
+
+/// An iterator of indices of child nodes in [`Arena`](struct.Arena.html).
+#[derive(Debug, Clone)]
+pub struct Children<'a> {
+    arena: &'a Arena,
+    next: Option<usize>,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let current = self.next?;
+        self.next = self.arena.nodes[current].next_sibling;
+        Some(current)
+    }
+}
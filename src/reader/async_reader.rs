@@ -0,0 +1,89 @@
+//! Contains an FBX reader for async sources.
+//!
+//! The parser is a synchronous state machine which assumes `std::io::Read`, so the async reader
+//! reads the whole stream into memory first, and then parses it synchronously.
+//! This does not block the executor while waiting for data, but needs memory for the whole
+//! FBX data.
+
+use crate::reader::error::{Error, Result};
+use crate::reader::{EventReader, FbxEvent, ParserConfig};
+use std::io::{self, Cursor};
+use std::mem;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// A wrapper around an `AsyncRead` source which provides pull-based FBX events parsing.
+///
+/// This is available with `async` feature.
+/// The whole stream is read into memory at the first call of [`next`](#method.next), and then
+/// events are parsed from the memory.
+pub struct AsyncEventReader<R> {
+    /// Source stream, which is not read to the end yet.
+    source: Option<R>,
+    /// Data read from the source so far.
+    data: Vec<u8>,
+    /// Configuration of the parser.
+    config: Option<ParserConfig>,
+    /// Reader of the data read from the source.
+    reader: Option<EventReader<Cursor<Vec<u8>>>>,
+    /// Error on reading the source.
+    error: Option<Error>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncEventReader<R> {
+    /// Creates a new reader, consuming the given stream.
+    pub fn new(source: R) -> Self {
+        AsyncEventReader::new_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader with provided configuration, consuming the given stream.
+    pub fn new_with_config(source: R, config: ParserConfig) -> Self {
+        AsyncEventReader {
+            source: Some(source),
+            data: Vec::new(),
+            config: Some(config),
+            reader: None,
+            error: None,
+        }
+    }
+
+    /// Pulls and returns next FBX event from the stream.
+    ///
+    /// This works as [`EventReader::next`](struct.EventReader.html#method.next).
+    ///
+    /// This method is cancel safe.
+    /// Data read from the source is kept in the reader, so if the returned future is dropped
+    /// before completion, the next call continues reading from where it stopped.
+    pub async fn next(&mut self) -> Result<FbxEvent> {
+        if let Some(ref err) = self.error {
+            return Err(err.clone());
+        }
+        while let Some(source) = self.source.as_mut() {
+            match source.read_buf(&mut self.data).await {
+                Ok(0) => {
+                    self.source = None;
+                    let data = mem::take(&mut self.data);
+                    let config = self.config.take().unwrap_or_default();
+                    self.reader = Some(EventReader::new_with_config(Cursor::new(data), config));
+                }
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    let err = Error::new(self.data.len() as u64, err);
+                    self.error = Some(err.clone());
+                    return Err(err);
+                }
+            }
+        }
+        self.reader
+            .as_mut()
+            .expect("Reader should be available after the source is read")
+            .next()
+    }
+
+    /// Returns the number of bytes successfully read and parsed from the source.
+    ///
+    /// See [`EventReader::position`](struct.EventReader.html#method.position).
+    pub fn position(&self) -> u64 {
+        self.reader.as_ref().map_or(0, EventReader::position)
+    }
+}
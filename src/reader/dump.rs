@@ -0,0 +1,51 @@
+//! Contains a dumper which prints FBX data in human-readable form for debugging.
+
+use crate::common::FbxFormatType;
+use crate::reader::error::{Error, Result};
+use crate::reader::{EventReader, FbxEvent};
+use std::io::{Read, Write};
+
+/// Indentation of a nesting level.
+const INDENT: &str = "    ";
+
+/// Reads FBX data and writes an indented outline of it.
+///
+/// Each node is printed as its name and properties, and children are indented.
+/// Large arrays and binaries are summarized as `Display` of
+/// [`Property`](../common/enum.Property.html) does.
+/// This is a diagnostic dump for humans, not a valid FBX (use the ASCII emitter to get one).
+///
+/// Errors on writing to `out` are returned as `ErrorKind::Io` with the position of the reader.
+pub fn dump<R: Read, W: Write>(reader: R, out: &mut W) -> Result<()> {
+    let mut reader = EventReader::new(reader);
+    let mut depth = 0;
+    loop {
+        let event = reader.next()?;
+        let pos = reader.position();
+        let result = match event {
+            FbxEvent::StartFbx(FbxFormatType::Binary(version)) => {
+                writeln!(out, "Binary FBX (version {})", version)
+            }
+            FbxEvent::StartFbx(FbxFormatType::Ascii) => writeln!(out, "ASCII FBX"),
+            FbxEvent::EndFbx => return Ok(()),
+            FbxEvent::StartNode { name, properties } => {
+                depth += 1;
+                write!(out, "{}{}:", INDENT.repeat(depth - 1), name).and_then(|_| {
+                    for (i, prop) in properties.iter().enumerate() {
+                        let sep = if i == 0 { " " } else { ", " };
+                        write!(out, "{}{}", sep, prop)?;
+                    }
+                    writeln!(out)
+                })
+            }
+            FbxEvent::EndNode => {
+                depth -= 1;
+                Ok(())
+            }
+            FbxEvent::Comment(comment) => writeln!(out, "{};{}", INDENT.repeat(depth), comment),
+        };
+        if let Err(err) = result {
+            return Err(Error::new(pos, err));
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::fmt;
 use std::io;
 use std::str;
 use std::string;
+use std::sync::Arc;
 
 /// A specialized `std::result::Result` type for FBX parsing.
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -27,6 +28,41 @@ impl Error {
             kind: kind.into(),
         }
     }
+
+    /// Constructs `Error` with position and an error defined outside of this crate.
+    ///
+    /// This is useful for crates wrapping the FBX reader to report their own failures (such as
+    /// validation failures) with position information.
+    pub fn custom<E>(pos: u64, err: E) -> Self
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        Error::new(pos, ErrorKind::Custom(Arc::from(err.into())))
+    }
+
+    /// Returns the last position of successfully read data when the error detected.
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the error type.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Returns whether the operation can be retried after the error.
+    ///
+    /// I/O errors of kind `Interrupted`, `WouldBlock` and `TimedOut` are retryable.
+    /// See [`EventReader::try_next`](struct.EventReader.html#method.try_next).
+    pub fn is_retryable(&self) -> bool {
+        match self.kind {
+            ErrorKind::Io(ref err) => matches!(
+                err.kind(),
+                io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -42,73 +78,85 @@ impl fmt::Display for Error {
             ),
             ErrorKind::Io(ref err) => write!(f, "I/O error at pos={}: {}", self.pos, err),
             ErrorKind::DataError(ref err) => write!(f, "Invalid data at pos={}: {}", self.pos, err),
+            ErrorKind::Decompression(ref err) => {
+                write!(f, "Decompression error at pos={}: {}", self.pos, err)
+            }
             ErrorKind::UnexpectedValue(ref err) => {
                 write!(f, "Got an unexpected value at pos={}: {}", self.pos, err)
             }
             ErrorKind::UnexpectedEof => write!(f, "Unexpected EOF at pos={}", self.pos),
+            ErrorKind::EmptyInput => write!(f, "Empty input: no data to read as FBX"),
             ErrorKind::Unimplemented(ref err) => write!(f, "Unimplemented feature: {}", err),
+            ErrorKind::EventLimitExceeded(limit) => write!(
+                f,
+                "Number of events exceeded the limit ({}) at pos={}",
+                limit, self.pos
+            ),
+            ErrorKind::NodeLimitExceeded(limit) => write!(
+                f,
+                "Number of nodes exceeded the limit ({}) at pos={}",
+                limit, self.pos
+            ),
+            ErrorKind::Custom(ref err) => write!(f, "Error at pos={}: {}", self.pos, err),
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match self.kind {
-            ErrorKind::Utf8Error(ref err) => err.description(),
-            ErrorKind::InvalidMagic => "Got an invalid magic header",
-            ErrorKind::Io(ref err) => err.description(),
-            ErrorKind::DataError(_) => "Got an invalid data",
-            ErrorKind::UnexpectedValue(_) => "Invalid value in FBX data",
-            ErrorKind::UnexpectedEof => "Unexpected EOF",
-            ErrorKind::Unimplemented(_) => "Attempt to use unimplemented feature",
-        }
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self.kind {
-            ErrorKind::Utf8Error(ref err) => Some(err as &dyn error::Error),
-            ErrorKind::Io(ref err) => Some(err as &dyn error::Error),
+            ErrorKind::Utf8Error(ref err) => Some(err),
+            ErrorKind::Io(ref err) => Some(&**err),
+            ErrorKind::Decompression(ref err) => Some(&**err),
+            ErrorKind::Custom(ref err) => Some(&**err),
             _ => None,
         }
     }
 }
 
 /// Error type.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// Conversion from array of u8 to String failed.
     Utf8Error(str::Utf8Error),
     /// Invalid magic binary detected.
     InvalidMagic,
     /// I/O operation error.
-    Io(io::Error),
+    ///
+    /// `io::Error` is not `Clone`, so it is shared by `Arc` to keep the original error (including
+    /// its message) on cloning.
+    Io(Arc<io::Error>),
     /// Corrupted or inconsistent FBX data detected.
     DataError(String),
+    /// Compressed data is broken (for example, invalid deflate stream or checksum mismatch).
+    ///
+    /// In strict mode, compressed data is decoded to the end to validate the checksum even after
+    /// all the expected elements are read.
+    Decompression(Arc<io::Error>),
     /// Got an unexpected value, and cannot continue parsing.
     ///
     /// This is specialization of [`DataError`](#variant.DataError).
     UnexpectedValue(String),
     /// Reached unexpected EOF.
     UnexpectedEof,
+    /// The input has no data at all.
+    ///
+    /// This is distinguished from other EOF errors, to tell an empty input from a truncated or
+    /// corrupted one.
+    EmptyInput,
     /// Attempted to use unimplemented feature.
     Unimplemented(String),
-}
-
-impl Clone for ErrorKind {
-    fn clone(&self) -> Self {
-        use self::ErrorKind::*;
-        use std::error::Error;
-        match *self {
-            Utf8Error(ref e) => Utf8Error(*e),
-            InvalidMagic => InvalidMagic,
-            // `io::Error` (and an error wrapped by `io::Error`) cannot be cloned.
-            Io(ref e) => Io(io::Error::new(e.kind(), e.description())),
-            DataError(ref e) => DataError(e.clone()),
-            UnexpectedValue(ref e) => UnexpectedValue(e.clone()),
-            UnexpectedEof => UnexpectedEof,
-            Unimplemented(ref e) => Unimplemented(e.clone()),
-        }
-    }
+    /// Number of events exceeded the limit specified by the user.
+    EventLimitExceeded(usize),
+    /// Number of nodes exceeded the limit specified by
+    /// [`ParserConfig::max_nodes`](struct.ParserConfig.html#structfield.max_nodes).
+    NodeLimitExceeded(u64),
+    /// An error defined outside of this crate.
+    ///
+    /// This is not emitted by this crate, but can be used by other crates to report their own
+    /// errors. See [`Error::custom`](struct.Error.html#method.custom).
+    Custom(Arc<dyn error::Error + Send + Sync>),
 }
 
 impl From<string::FromUtf8Error> for ErrorKind {
@@ -119,6 +167,33 @@ impl From<string::FromUtf8Error> for ErrorKind {
 
 impl From<io::Error> for ErrorKind {
     fn from(err: io::Error) -> ErrorKind {
-        ErrorKind::Io(err)
+        ErrorKind::Io(Arc::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorKind};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn custom_error() {
+        let err = Error::custom(42, "vertex count mismatch");
+        assert_eq!(err.pos(), 42);
+        assert!(matches!(*err.kind(), ErrorKind::Custom(_)));
+        assert_eq!(err.to_string(), "Error at pos=42: vertex count mismatch");
+        let source = err.source();
+        assert_eq!(source.unwrap().to_string(), "vertex count mismatch");
+        assert_eq!(err.clone().to_string(), err.to_string());
+    }
+
+    #[test]
+    fn clone_io_error() {
+        use std::io;
+
+        let err = Error::new(3, io::Error::other("disk on fire"));
+        let cloned = err.clone();
+        assert_eq!(cloned.to_string(), "I/O error at pos=3: disk on fire");
+        assert_eq!(cloned.source().unwrap().to_string(), "disk on fire");
     }
 }
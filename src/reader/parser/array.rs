@@ -0,0 +1,822 @@
+//! Contains decoders for property arrays of Binary FBX.
+
+use crate::common::NONSTANDARD_ZSTD_ARRAY_ENCODING;
+use crate::reader::error::{Error, ErrorKind, Result};
+use crate::reader::ParserConfig;
+use flate2;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// A header of a property of array type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayHeader {
+    /// Type code of the property (`b'f'`, `b'd'`, `b'l'`, `b'i'` or `b'b'`).
+    pub type_code: u8,
+    /// Number of values in the array, *NOT byte size*.
+    pub array_length: u32,
+    /// Denotes whether data in stream is plain, or what algorithm it is compressed by.
+    ///
+    /// Standard FBX uses only `0` (plain) and `1` (zlib).
+    /// [`NONSTANDARD_ZSTD_ARRAY_ENCODING`](../common/constant.NONSTANDARD_ZSTD_ARRAY_ENCODING.html)
+    /// is also supported if `zstd` feature is enabled.
+    pub encoding: u32,
+    /// Byte size of the compressed array value in the stream.
+    pub compressed_length: u32,
+}
+
+impl ArrayHeader {
+    /// Reads a type code and an array header of a property from the given stream.
+    ///
+    /// Positions of returned errors are relative to the beginning of the property.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut pos = 0;
+        let type_code = try_read_le_u8!(pos, reader);
+        match type_code {
+            b'f' | b'd' | b'l' | b'i' | b'b' => {
+                Self::read_after_type_code(reader, &mut pos, type_code)
+            }
+            _ => Err(Error::new(
+                0,
+                ErrorKind::UnexpectedValue(format!(
+                    "Expected type code of array property but got {:#x}",
+                    type_code
+                )),
+            )),
+        }
+    }
+
+    /// Reads an array header of a property whose type code is already read.
+    pub(crate) fn read_after_type_code<R: Read>(
+        reader: &mut R,
+        pos: &mut u64,
+        type_code: u8,
+    ) -> Result<Self> {
+        let array_length = try_read_le_u32!(*pos, reader);
+        let encoding = try_read_le_u32!(*pos, reader);
+        let compressed_length = try_read_le_u32!(*pos, reader);
+        Ok(ArrayHeader {
+            type_code,
+            array_length,
+            encoding,
+            compressed_length,
+        })
+    }
+}
+
+/// An element type of properties of array type.
+pub trait ArrayElement: Sized {
+    /// Type code of array properties with elements of this type.
+    const TYPE_CODE: u8;
+    /// Byte size of an element in plain (uncompressed) stream.
+    const BYTE_SIZE: u64;
+
+    /// Decodes elements from little endian bytes and appends them to `out`.
+    ///
+    /// The length of `bytes` should be a multiple of `BYTE_SIZE`.
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>);
+}
+
+impl ArrayElement for bool {
+    const TYPE_CODE: u8 = b'b';
+    const BYTE_SIZE: u64 = 1;
+
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>) {
+        decode_bool_array(bytes, out)
+    }
+}
+
+impl ArrayElement for i32 {
+    const TYPE_CODE: u8 = b'i';
+    const BYTE_SIZE: u64 = 4;
+
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>) {
+        decode_i32_array(bytes, out)
+    }
+}
+
+impl ArrayElement for i64 {
+    const TYPE_CODE: u8 = b'l';
+    const BYTE_SIZE: u64 = 8;
+
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>) {
+        decode_i64_array(bytes, out)
+    }
+}
+
+impl ArrayElement for f32 {
+    const TYPE_CODE: u8 = b'f';
+    const BYTE_SIZE: u64 = 4;
+
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>) {
+        decode_f32_array(bytes, out)
+    }
+}
+
+impl ArrayElement for f64 {
+    const TYPE_CODE: u8 = b'd';
+    const BYTE_SIZE: u64 = 8;
+
+    fn decode_le_bytes(bytes: &[u8], out: &mut Vec<Self>) {
+        decode_f64_array(bytes, out)
+    }
+}
+
+/// Decodes an array of 1 byte booleans (LSB represents the value).
+fn decode_bool_array(bytes: &[u8], out: &mut Vec<bool>) {
+    // Check LSB.
+    out.extend(bytes.iter().map(|&v| v & 1 == 1));
+}
+
+/// Decodes an array of little endian 4 byte signed integers.
+fn decode_i32_array(bytes: &[u8], out: &mut Vec<i32>) {
+    out.extend(
+        bytes
+            .chunks_exact(4)
+            .map(|v| i32::from_le_bytes([v[0], v[1], v[2], v[3]])),
+    );
+}
+
+/// Decodes an array of little endian 8 byte signed integers.
+fn decode_i64_array(bytes: &[u8], out: &mut Vec<i64>) {
+    out.extend(
+        bytes
+            .chunks_exact(8)
+            .map(|v| i64::from_le_bytes([v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]])),
+    );
+}
+
+/// Decodes an array of little endian 4 byte single-precision IEEE 754 numbers.
+fn decode_f32_array(bytes: &[u8], out: &mut Vec<f32>) {
+    out.extend(
+        bytes
+            .chunks_exact(4)
+            .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]])),
+    );
+}
+
+/// Decodes an array of little endian 8 byte double-precision IEEE 754 numbers.
+fn decode_f64_array(bytes: &[u8], out: &mut Vec<f64>) {
+    out.extend(
+        bytes
+            .chunks_exact(8)
+            .map(|v| f64::from_le_bytes([v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]])),
+    );
+}
+
+/// Reads elements of an array property into the given buffer.
+///
+/// Decoded elements are appended to `out`, so a caller can reuse one buffer across many arrays
+/// instead of allocating a new `Vec` for each array.
+/// `reader` should be positioned right after the array header.
+///
+/// Returns `ErrorKind::UnexpectedValue` if `T` does not match the type code of `header`.
+/// Positions of returned errors are relative to the end of the array header.
+pub fn read_array_into<R: Read, T: ArrayElement>(
+    reader: &mut R,
+    header: &ArrayHeader,
+    out: &mut Vec<T>,
+) -> Result<()> {
+    if header.type_code != T::TYPE_CODE {
+        return Err(Error::new(
+            0,
+            ErrorKind::UnexpectedValue(format!(
+                "Array element type (type code {:?}) does not match the array (type code {:?})",
+                T::TYPE_CODE as char,
+                header.type_code as char
+            )),
+        ));
+    }
+    read_array_contents_into(
+        reader,
+        0,
+        header,
+        out,
+        &ParserConfig::new(),
+        &mut ArrayScratch::new(),
+    )?;
+    Ok(())
+}
+
+/// Size of the buffer of compressed input for `ArrayScratch`.
+const COMPRESSED_CHUNK_SIZE: usize = 32 * 1024;
+
+/// Reusable state for decoding compressed arrays.
+///
+/// Creating a zlib decoder allocates its internal state and an input buffer, which is costly
+/// for data with many small arrays. A parser keeps this across arrays, and the decompressor is
+/// reset before each use.
+/// The state is boxed and allocated on the first use, because it is large and parsers are moved
+/// around.
+#[derive(Default)]
+pub(crate) struct ArrayScratch {
+    state: Option<Box<ScratchState>>,
+}
+
+/// Contents of `ArrayScratch`.
+struct ScratchState {
+    /// zlib decompressor.
+    decompress: flate2::Decompress,
+    /// Buffer of compressed input.
+    input: Vec<u8>,
+}
+
+impl ArrayScratch {
+    /// Creates an empty scratch, which allocates nothing until used.
+    pub fn new() -> Self {
+        ArrayScratch::default()
+    }
+
+    /// Returns a zlib decoder reading compressed data from `reader`, using the reusable state.
+    fn zlib_decoder<R: Read>(&mut self, reader: R) -> ZlibReader<'_, R> {
+        let state = match self.state {
+            Some(ref mut state) => {
+                state.decompress.reset(true);
+                state
+            }
+            None => self.state.get_or_insert_with(|| {
+                Box::new(ScratchState {
+                    decompress: flate2::Decompress::new(true),
+                    input: vec![0; COMPRESSED_CHUNK_SIZE],
+                })
+            }),
+        };
+        ZlibReader {
+            reader,
+            decompress: &mut state.decompress,
+            input: &mut state.input,
+            start: 0,
+            end: 0,
+        }
+    }
+}
+
+impl Clone for ArrayScratch {
+    /// Creates an empty scratch, because the state is not meaningful outside of decoding.
+    fn clone(&self) -> Self {
+        ArrayScratch::new()
+    }
+}
+
+impl fmt::Debug for ArrayScratch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArrayScratch")
+            .field("allocated", &self.state.is_some())
+            .finish()
+    }
+}
+
+/// zlib decoder with borrowed state and input buffer.
+///
+/// This behaves the same as `flate2::read::ZlibDecoder`.
+struct ZlibReader<'a, R> {
+    reader: R,
+    decompress: &'a mut flate2::Decompress,
+    input: &'a mut [u8],
+    /// Start of the unconsumed input in `input`.
+    start: usize,
+    /// End of the unconsumed input in `input`.
+    end: usize,
+}
+
+impl<'a, R: Read> Read for ZlibReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.start == self.end {
+                self.start = 0;
+                self.end = fill_buffer(&mut self.reader, self.input)?;
+            }
+            let eof = self.start == self.end;
+            let flush = if eof {
+                flate2::FlushDecompress::Finish
+            } else {
+                flate2::FlushDecompress::None
+            };
+            let before_in = self.decompress.total_in();
+            let before_out = self.decompress.total_out();
+            let status = self
+                .decompress
+                .decompress(&self.input[self.start..self.end], buf, flush);
+            self.start += (self.decompress.total_in() - before_in) as usize;
+            let read = (self.decompress.total_out() - before_out) as usize;
+            match status {
+                Ok(flate2::Status::Ok) | Ok(flate2::Status::BufError)
+                    if read == 0 && !buf.is_empty() =>
+                {
+                    if eof {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "incomplete deflate stream",
+                        ));
+                    }
+                }
+                Ok(_) => return Ok(read),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidInput, err)),
+            }
+        }
+    }
+}
+
+/// Reads elements of an array property, and returns byte size read from the stream.
+///
+/// `pos` is a position of the beginning of the array contents, used for error reporting.
+/// If `config.strict` is `true`, compressed data is decoded to the end to validate its checksum.
+pub(crate) fn read_array_contents_into<R: Read, T: ArrayElement>(
+    reader: &mut R,
+    pos: u64,
+    header: &ArrayHeader,
+    out: &mut Vec<T>,
+    config: &ParserConfig,
+    scratch: &mut ArrayScratch,
+) -> Result<u64> {
+    match header.encoding {
+        // 0; raw
+        0 => {
+            let byte_size = u64::from(header.array_length) * T::BYTE_SIZE;
+            if byte_size != u64::from(header.compressed_length) {
+                return Err(Error::new(
+                    pos,
+                    ErrorKind::DataError(format!(
+                        "Raw array of {} elements should be {} bytes, but got {} bytes",
+                        header.array_length, byte_size, header.compressed_length
+                    )),
+                ));
+            }
+            let decoded =
+                decode_plain_stream_into(reader, pos, header.array_length, out, config, byte_size)?;
+            if decoded != header.array_length {
+                return Err(Error::new(pos, ErrorKind::UnexpectedEof));
+            }
+            Ok(byte_size)
+        }
+        // 1: zlib compressed data, or non-standard zstd compressed data.
+        encoding if encoding == 1 || is_zstd_encoding(encoding) => {
+            // An empty array may have no compressed data at all, which is not a valid zlib
+            // stream.
+            if header.array_length == 0 && header.compressed_length == 0 {
+                return Ok(0);
+            }
+            let mut compressed = reader.by_ref().take(u64::from(header.compressed_length));
+            let reserve_bytes =
+                u64::from(header.compressed_length).saturating_mul(MAX_COMPRESSION_RATIO);
+            let decoded = if encoding == 1 {
+                let mut decoder = scratch.zlib_decoder(compressed.by_ref());
+                decode_compressed_stream_into(
+                    &mut decoder,
+                    pos,
+                    header.array_length,
+                    out,
+                    config,
+                    reserve_bytes,
+                )
+            } else {
+                decode_zstd_stream_into(
+                    compressed.by_ref(),
+                    pos,
+                    header.array_length,
+                    out,
+                    config,
+                    reserve_bytes,
+                )
+            };
+            let decoded = match decoded {
+                Ok(decoded) => decoded,
+                // If whole `compressed_length` bytes are consumed, the stream is not
+                // truncated but the compressed data itself is broken.
+                Err(err) => match *err.kind() {
+                    ErrorKind::Io(ref io_err) if compressed.limit() == 0 => {
+                        return Err(Error::new(pos, ErrorKind::Decompression(io_err.clone())));
+                    }
+                    _ => return Err(err),
+                },
+            };
+            if decoded != header.array_length {
+                // Distinguish corrupt compressed data from truncated file.
+                return Err(Error::new(
+                    pos,
+                    ErrorKind::DataError(format!(
+                        "Compressed array decoded to {} elements, expected {}",
+                        decoded, header.array_length
+                    )),
+                ));
+            }
+            // Skip the rest of the compressed data (if any) to keep the stream position
+            // consistent.
+            try_with_pos!(pos, io::copy(&mut compressed, &mut io::sink()));
+            Ok(u64::from(header.compressed_length))
+        }
+        // Unknown.
+        e => Err(Error::new(
+            pos,
+            ErrorKind::UnexpectedValue(format!(
+                "Unsupported property array encoding, got {:#x}",
+                e
+            )),
+        )),
+    }
+}
+
+/// Returns whether the encoding is the non-standard zstd encoding and it is supported.
+fn is_zstd_encoding(encoding: u32) -> bool {
+    cfg!(feature = "zstd") && encoding == NONSTANDARD_ZSTD_ARRAY_ENCODING
+}
+
+/// Ratio of decompressed size to compressed size assumed when preallocating array elements.
+///
+/// This is the maximum compression ratio of deflate. The array may still grow beyond the
+/// preallocated size (for example, with zstd), but an untrusted header alone cannot make the
+/// reader allocate much more than the input can actually produce.
+const MAX_COMPRESSION_RATIO: u64 = 1032;
+
+/// Reads elements of an array from the decompressed stream.
+///
+/// If `config.strict` is `true`, the decoder is drained to validate the rest of the stream.
+fn decode_compressed_stream_into<R: Read, T: ArrayElement>(
+    decoder: &mut R,
+    pos: u64,
+    num_elements: u32,
+    out: &mut Vec<T>,
+    config: &ParserConfig,
+    reserve_bytes: u64,
+) -> Result<u32> {
+    match decode_plain_stream_into(decoder, pos, num_elements, out, config, reserve_bytes) {
+        Ok(decoded) if config.strict && decoded == num_elements => {
+            drain_decoder(decoder, pos, num_elements).map(|_| decoded)
+        }
+        result => result,
+    }
+}
+
+/// Reads elements of an array from zstd compressed stream.
+#[cfg(feature = "zstd")]
+fn decode_zstd_stream_into<R: Read, T: ArrayElement>(
+    compressed: R,
+    pos: u64,
+    num_elements: u32,
+    out: &mut Vec<T>,
+    config: &ParserConfig,
+    reserve_bytes: u64,
+) -> Result<u32> {
+    let mut decoder = try_with_pos!(pos, zstd::stream::read::Decoder::new(compressed));
+    decode_compressed_stream_into(&mut decoder, pos, num_elements, out, config, reserve_bytes)
+}
+
+/// Reads elements of an array from zstd compressed stream.
+///
+/// This is never called because zstd support is disabled.
+#[cfg(not(feature = "zstd"))]
+fn decode_zstd_stream_into<R: Read, T: ArrayElement>(
+    _compressed: R,
+    pos: u64,
+    _num_elements: u32,
+    _out: &mut Vec<T>,
+    _config: &ParserConfig,
+    _reserve_bytes: u64,
+) -> Result<u32> {
+    Err(Error::new(
+        pos,
+        ErrorKind::Unimplemented("zstd compressed array (`zstd` feature is disabled)".to_string()),
+    ))
+}
+
+/// Byte size of a buffer to read plain (uncompressed) array at once.
+///
+/// This should be a multiple of byte size of any array element type.
+const DECODE_CHUNK_SIZE: usize = 8192;
+
+/// Reads elements of an array from plain (uncompressed) stream.
+///
+/// Data is read by chunks and decoded by element type specific decoder.
+/// Returns the number of decoded elements, which is less than `num_elements` if the stream
+/// reached EOF.
+/// Representations of booleans are checked according to `config.unexpected_bool`.
+/// At most `reserve_bytes` bytes worth of elements are preallocated.
+fn decode_plain_stream_into<R: Read, T: ArrayElement>(
+    reader: &mut R,
+    pos: u64,
+    num_elements: u32,
+    out: &mut Vec<T>,
+    config: &ParserConfig,
+    reserve_bytes: u64,
+) -> Result<u32> {
+    // `num_elements` comes from the file and is not trusted: reserve only as much as the
+    // input can hold.
+    out.reserve(u64::from(num_elements).min(reserve_bytes / T::BYTE_SIZE) as usize);
+    let mut rest_bytes = u64::from(num_elements) * T::BYTE_SIZE;
+    let mut chunk = [0; DECODE_CHUNK_SIZE];
+    while rest_bytes > 0 {
+        let len = rest_bytes.min(DECODE_CHUNK_SIZE as u64) as usize;
+        let filled = try_with_pos!(pos, fill_buffer(reader, &mut chunk[..len]));
+        if T::TYPE_CODE == b'b' {
+            // Elements are `0` or `1` in files written by this crate, and `'T'` or `'Y'` in
+            // some others (as single boolean values).
+            if let Some(&val) = chunk[..filled]
+                .iter()
+                .find(|&&v| !matches!(v, 0 | 1 | b'T' | b'Y'))
+            {
+                config.unexpected_bool.check(pos, || {
+                    format!(
+                        "Expected 0, 1, 'T' or 'Y' for element of boolean array, but got {:#x}",
+                        val
+                    )
+                })?;
+            }
+        }
+        T::decode_le_bytes(&chunk[..filled], out);
+        rest_bytes -= filled as u64;
+        if filled < len {
+            // Reached EOF.
+            break;
+        }
+    }
+    let rest_elements = rest_bytes.div_ceil(T::BYTE_SIZE);
+    Ok(num_elements - rest_elements as u32)
+}
+
+/// Reads the rest of the decompressed stream to let the decoder validate the checksum.
+///
+/// Returns `ErrorKind::Decompression` if the decoder fails, and `ErrorKind::DataError` if the
+/// stream has extra data after `num_elements` elements.
+fn drain_decoder<R: Read>(decoder: &mut R, pos: u64, num_elements: u32) -> Result<()> {
+    match io::copy(decoder, &mut io::sink()) {
+        Ok(0) => Ok(()),
+        Ok(_) => Err(Error::new(
+            pos,
+            ErrorKind::DataError(format!(
+                "Compressed array decoded to more than {} elements",
+                num_elements
+            )),
+        )),
+        Err(err) => Err(Error::new(pos, ErrorKind::Decompression(Arc::new(err)))),
+    }
+}
+
+/// Reads data into the buffer until it is filled or the stream reaches EOF.
+///
+/// Returns the number of bytes read.
+fn fill_buffer<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(len) => filled += len,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_array_contents_into, read_array_into, ArrayHeader, ArrayScratch};
+    use crate::reader::error::ErrorKind;
+    use crate::reader::ParserConfig;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use flate2;
+    use std::io::{Cursor, Write};
+
+    fn plain_i32_array(values: &[i32]) -> Vec<u8> {
+        let mut bytes = vec![b'i'];
+        bytes
+            .write_u32::<LittleEndian>(values.len() as u32)
+            .unwrap();
+        bytes.write_u32::<LittleEndian>(0).unwrap();
+        bytes
+            .write_u32::<LittleEndian>(values.len() as u32 * 4)
+            .unwrap();
+        for &v in values {
+            bytes.write_i32::<LittleEndian>(v).unwrap();
+        }
+        bytes
+    }
+
+    fn compressed_i32_array(values: &[i32]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        for &v in values {
+            encoder.write_i32::<LittleEndian>(v).unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+        let mut bytes = vec![b'i'];
+        bytes
+            .write_u32::<LittleEndian>(values.len() as u32)
+            .unwrap();
+        bytes.write_u32::<LittleEndian>(1).unwrap();
+        bytes
+            .write_u32::<LittleEndian>(compressed.len() as u32)
+            .unwrap();
+        bytes.write_all(&compressed).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reuse_buffer_across_arrays() {
+        let arrays: [&[i32]; 3] = [&[1, 2, 3], &[-4, 5], &[6, 7, 8, 9]];
+        let mut data = Vec::new();
+        data.extend(plain_i32_array(arrays[0]));
+        data.extend(compressed_i32_array(arrays[1]));
+        data.extend(plain_i32_array(arrays[2]));
+        let mut reader = Cursor::new(data);
+
+        let mut buffer = Vec::<i32>::new();
+        for expected in &arrays {
+            buffer.clear();
+            let header = ArrayHeader::read(&mut reader).unwrap();
+            read_array_into(&mut reader, &header, &mut buffer).unwrap();
+            assert_eq!(&buffer[..], *expected);
+        }
+        assert_eq!(reader.position() as usize, reader.get_ref().len());
+    }
+
+    #[test]
+    fn reject_mismatched_element_type() {
+        let mut reader = Cursor::new(plain_i32_array(&[1, 2, 3]));
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let mut buffer = Vec::<f64>::new();
+        assert!(read_array_into(&mut reader, &header, &mut buffer).is_err());
+        assert!(buffer.is_empty());
+    }
+
+    /// Checks decoders against bytes in little endian explicitly.
+    ///
+    /// Expected values are written as literals, so this fails if decoders wrongly depend on the
+    /// native endianness (for example, on big endian targets).
+    #[test]
+    fn decode_each_type() {
+        let mut bools = Vec::new();
+        super::decode_bool_array(&[0x00, 0x01, b'T', b'Y'], &mut bools);
+        assert_eq!(bools, [false, true, false, true]);
+
+        let mut i32s = Vec::new();
+        super::decode_i32_array(&[0x01, 0x02, 0x03, 0x04, 0xff, 0xff, 0xff, 0xff], &mut i32s);
+        assert_eq!(i32s, [0x0403_0201, -1]);
+
+        let mut i64s = Vec::new();
+        super::decode_i64_array(
+            &[
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff, 0xff,
+            ],
+            &mut i64s,
+        );
+        assert_eq!(i64s, [0x0807_0605_0403_0201, -2]);
+
+        let mut f32s = Vec::new();
+        super::decode_f32_array(&[0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xc0], &mut f32s);
+        assert_eq!(f32s, [1.0, -2.0]);
+
+        let mut f64s = Vec::new();
+        super::decode_f64_array(
+            &[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x04, 0xc0,
+            ],
+            &mut f64s,
+        );
+        assert_eq!(f64s, [1.0, -2.5]);
+    }
+
+    #[test]
+    fn decode_across_chunks() {
+        let values = (0..super::DECODE_CHUNK_SIZE as i32).collect::<Vec<i32>>();
+        let mut reader = Cursor::new(plain_i32_array(&values));
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let mut buffer = Vec::<i32>::new();
+        read_array_into(&mut reader, &header, &mut buffer).unwrap();
+        assert_eq!(buffer, values);
+    }
+
+    #[test]
+    fn compressed_array_decoded_short() {
+        let mut data = compressed_i32_array(&[1, 2]);
+        // Claim 4 elements.
+        data[1..5].copy_from_slice(&4u32.to_le_bytes());
+        let mut reader = Cursor::new(data);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let err = read_array_into(&mut reader, &header, &mut Vec::<i32>::new()).unwrap_err();
+        match *err.kind() {
+            ErrorKind::DataError(ref msg) => {
+                assert_eq!(msg, "Compressed array decoded to 2 elements, expected 4")
+            }
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+
+        // Compressed data truncated by `compressed_length` cannot yield enough elements.
+        let mut data = compressed_i32_array(&(0..256).collect::<Vec<_>>());
+        let compressed_len = (data.len() - 13) as u32;
+        data[9..13].copy_from_slice(&(compressed_len / 2).to_le_bytes());
+        let mut reader = Cursor::new(data);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let err = read_array_into(&mut reader, &header, &mut Vec::<i32>::new()).unwrap_err();
+        assert!(
+            matches!(*err.kind(), ErrorKind::Decompression(_)),
+            "unexpected error: {:?}",
+            err
+        );
+
+        // Truncated plain array is reported as EOF.
+        let data = plain_i32_array(&[1, 2, 3, 4]);
+        let mut reader = Cursor::new(&data[..data.len() - 6]);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let err = read_array_into(&mut reader, &header, &mut Vec::<i32>::new()).unwrap_err();
+        assert!(matches!(*err.kind(), ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn compressed_array_checksum_mismatch() {
+        let mut data = compressed_i32_array(&[1, 2, 3, 4]);
+        // Break Adler-32 checksum at the end of zlib stream.
+        *data.last_mut().unwrap() ^= 0xff;
+        let mut reader = Cursor::new(data);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let err = read_array_contents_into(
+            &mut reader,
+            0,
+            &header,
+            &mut Vec::<i32>::new(),
+            &ParserConfig::new().strict(true),
+            &mut ArrayScratch::new(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(*err.kind(), ErrorKind::Decompression(_)),
+            "unexpected error: {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn compressed_array_extra_data() {
+        let mut data = compressed_i32_array(&[1, 2, 3, 4, 5]);
+        // Claim 4 elements.
+        data[1..5].copy_from_slice(&4u32.to_le_bytes());
+
+        // Extra data is ignored in non-strict mode.
+        let mut reader = Cursor::new(&data[..]);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let mut values = Vec::<i32>::new();
+        read_array_contents_into(
+            &mut reader,
+            0,
+            &header,
+            &mut values,
+            &ParserConfig::new(),
+            &mut ArrayScratch::new(),
+        )
+        .unwrap();
+        assert_eq!(values, [1, 2, 3, 4]);
+
+        let mut reader = Cursor::new(&data[..]);
+        let header = ArrayHeader::read(&mut reader).unwrap();
+        let err = read_array_contents_into(
+            &mut reader,
+            0,
+            &header,
+            &mut Vec::<i32>::new(),
+            &ParserConfig::new().strict(true),
+            &mut ArrayScratch::new(),
+        )
+        .unwrap_err();
+        match *err.kind() {
+            ErrorKind::DataError(ref msg) => {
+                assert_eq!(msg, "Compressed array decoded to more than 4 elements")
+            }
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn reuse_scratch() {
+        let mut scratch = ArrayScratch::new();
+        let mut broken = compressed_i32_array(&[1, 2, 3, 4]);
+        *broken.last_mut().unwrap() ^= 0xff;
+        let large = (0..100_000).collect::<Vec<i32>>();
+        // The state is reset after both successful and failed decoding.
+        for &(ref data, expected) in &[
+            (compressed_i32_array(&[1, 2, 3]), Some(&[1, 2, 3][..])),
+            (broken, None),
+            (compressed_i32_array(&large), Some(&large[..])),
+            (compressed_i32_array(&[5, 6]), Some(&[5, 6][..])),
+        ] {
+            let mut reader = Cursor::new(&data[..]);
+            let header = ArrayHeader::read(&mut reader).unwrap();
+            let mut values = Vec::<i32>::new();
+            let result = read_array_contents_into(
+                &mut reader,
+                0,
+                &header,
+                &mut values,
+                &ParserConfig::new().strict(true),
+                &mut scratch,
+            );
+            match expected {
+                Some(expected) => {
+                    assert_eq!(result.unwrap(), u64::from(header.compressed_length));
+                    assert_eq!(values, expected);
+                }
+                None => assert!(result.is_err()),
+            }
+        }
+    }
+}
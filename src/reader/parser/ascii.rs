@@ -1,32 +1,566 @@
 //! Contains implementation of ASCII FBX parser.
+//!
+//! ASCII FBX consists of lines of nodes such as `Name: prop1, prop2 {`, which contain children
+//! until the closing `}` line.
+//! Properties continue on the next line if the line ends with `,` or the next line starts with
+//! `,`.
+//! Property values are parsed as below:
+//!
+//! * `T` and `Y` are `false` and `true` respectively.
+//! * Integers are `I32` if they fit, or `I64`.
+//! * Other numbers are `F64`.
+//! * Quoted strings are `String`, with `&quot;`, `&lf;` and `&cr;` unescaped.
+//! * `*N { a: v1,v2,... }` are arrays of `N` elements.
+//!   Elements can span multiple lines, with a comma at the end of a line or at the beginning
+//!   of the next line (as Autodesk FBX SDK writes long arrays).
+//!   Elements are parsed in the same way as the other values, so the array is `VecBool` if all
+//!   elements are `T` or `Y`, `VecI32` or `VecI64` if all elements are integers (including empty
+//!   arrays), and `VecF64` otherwise.
+//!
+//! Note that ASCII FBX has less type information than Binary FBX, so `I16`, `F32`, `VecF32` and
+//! `Binary` values written as ASCII are read as other types.
 
 use super::CommonState;
+use crate::common::OwnedProperty;
 use crate::reader::error::{Error, ErrorKind, Result};
-use crate::reader::FbxEvent;
-use std::io::Read;
+use crate::reader::{FbxEvent, ParserConfig};
+use log::warn;
+use std::collections::VecDeque;
+use std::io::{BufRead, Read};
+
+/// Maximum byte length of a line.
+///
+/// Lines of real ASCII FBX are much shorter (long arrays and strings are split into lines), so
+/// this only bounds memory usage on broken or malicious data.
+const MAX_LINE_LEN: usize = 16 * 1024 * 1024;
+
+/// Maximum number of characters of a line shown in error messages.
+const MAX_LINE_CHARS_IN_ERROR: usize = 64;
+
+/// A token of ASCII FBX.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Name followed by `:` (node name, or `a` of array elements).
+    Name(String),
+    /// Quoted string (already unescaped).
+    String(String),
+    /// Unquoted value, such as numbers and booleans.
+    Word(String),
+    /// Array length (`*N`).
+    ArrayLength(String),
+    /// `,`.
+    Comma,
+    /// `{`.
+    OpenBrace,
+    /// `}`.
+    CloseBrace,
+    /// End of a line (or end of the stream).
+    EndOfLine,
+}
 
 /// A parser for ASCII FBX.
 #[derive(Debug, Clone)]
 pub struct AsciiParser {
+    /// Data which is already read from the stream but not parsed yet.
     buffer: String,
+    /// Position of the token to be parsed next in the buffer.
+    cursor: usize,
+    /// FBX version detected from the header comment.
+    version: Option<u32>,
+    /// Number of open nodes.
+    depth: usize,
+    /// Position of the node line being parsed.
+    line_pos: u64,
+    /// Events which are already parsed but not emitted yet.
+    pending_events: VecDeque<FbxEvent>,
+}
+
+/// State of `AsciiParser` to be restored when reading an event fails with a retryable error.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AsciiCheckpoint {
+    /// Number of bytes read from the stream but not parsed yet.
+    buffered_len: u64,
+    /// Number of open nodes.
+    depth: usize,
+    /// Number of events parsed but not emitted yet.
+    num_pending_events: usize,
 }
 
 impl AsciiParser {
     /// Constructs ASCII FBX parser with initial state of internal buffer.
+    ///
+    /// The initial buffer should be the first line of the FBX data.
     pub(crate) fn new(buffer: String) -> Self {
-        AsciiParser { buffer }
+        let version = parse_header_comment(&buffer);
+        AsciiParser {
+            buffer,
+            cursor: 0,
+            version,
+            depth: 0,
+            line_pos: 0,
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    /// Returns FBX version written in the header comment (such as `; FBX 7.4.0 project file`).
+    pub(crate) fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Returns the byte length of data which is already read from the stream but not parsed yet.
+    ///
+    /// The parser reads a line ahead (for example, to merge comment lines), so this should be
+    /// subtracted from the number of read bytes to get the position of the parsed data.
+    pub(crate) fn buffered_len(&self) -> u64 {
+        (self.buffer.len() - self.cursor) as u64
+    }
+
+    /// Returns the state to be restored if reading the next event fails.
+    pub(crate) fn checkpoint(&self) -> AsciiCheckpoint {
+        AsciiCheckpoint {
+            buffered_len: self.buffered_len(),
+            depth: self.depth,
+            num_pending_events: self.pending_events.len(),
+        }
+    }
+
+    /// Restores the state at the checkpoint.
+    ///
+    /// `common` should be already restored. Data in the buffer is discarded and `common.pos` is
+    /// rewound to the beginning of it, so that the data is read from the stream again.
+    pub(crate) fn restore(&mut self, checkpoint: AsciiCheckpoint, common: &mut CommonState) {
+        common.pos -= checkpoint.buffered_len;
+        self.buffer.clear();
+        self.cursor = 0;
+        self.depth = checkpoint.depth;
+        self.pending_events.truncate(checkpoint.num_pending_events);
     }
 
-    pub(crate) fn next<R: Read>(
+    /// Returns whether the node started by the last event has child nodes, if known.
+    ///
+    /// Nodes without braces have no children. For nodes with braces, the following lines should
+    /// be read to know whether they have children, so `None` is returned.
+    pub(crate) fn current_node_has_children(&self) -> Option<bool> {
+        match self.pending_events.front() {
+            Some(FbxEvent::EndNode) => Some(false),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn next<R: BufRead>(
         &mut self,
-        _reader: &mut R,
+        reader: &mut R,
         common: &mut CommonState,
+        config: &ParserConfig,
     ) -> Result<FbxEvent> {
-        Err(Error::new(
-            common.pos,
-            ErrorKind::Unimplemented(
-                "Parser for ASCII FBX format is not implemented yet".to_string(),
-            ),
-        ))
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            if self.buffer.trim().is_empty() {
+                // Skip empty lines.
+                self.buffer.clear();
+                if !self.read_line(reader, common)? {
+                    if self.depth > 0 {
+                        return Err(Error::new(common.pos, ErrorKind::UnexpectedEof));
+                    }
+                    return Ok(FbxEvent::EndFbx);
+                }
+                continue;
+            }
+            if let Some(mut comment) = self.take_comment_line() {
+                if config.merge_comments {
+                    // Append following comment lines.
+                    while self.buffer.is_empty() && self.read_line(reader, common)? {
+                        if let Some(line) = self.take_comment_line() {
+                            comment.push('\n');
+                            comment.push_str(&line);
+                        }
+                    }
+                }
+                return Ok(FbxEvent::Comment(comment));
+            }
+            self.line_pos = common.pos - self.buffered_len();
+            let result = self.parse_line(reader, common, config);
+            // Drop the parsed lines.
+            self.buffer.drain(..self.cursor);
+            self.cursor = 0;
+            result?;
+            if let Some(event) = self.pending_events.pop_front() {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Parses a node line (and lines of its properties), or a line which closes a node.
+    ///
+    /// Parsed events are pushed to `pending_events`.
+    fn parse_line<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        config: &ParserConfig,
+    ) -> Result<()> {
+        let mut comments = Vec::new();
+        match self.next_token(&mut comments)? {
+            Token::CloseBrace => {
+                self.expect_end_of_line(&mut comments)?;
+                if self.depth == 0 {
+                    return Err(self.syntax_error("Unexpected `}`"));
+                }
+                self.depth -= 1;
+                self.pending_events.push_back(FbxEvent::EndNode);
+            }
+            Token::Name(name) => {
+                if let Some(max_depth) = config.max_depth {
+                    if self.depth >= max_depth {
+                        return Err(self.syntax_error(&format!(
+                            "Nesting of nodes is deeper than the limit ({})",
+                            max_depth
+                        )));
+                    }
+                }
+                let mut properties = Vec::new();
+                let mut token = self.next_token(&mut comments)?;
+                if token == Token::Comma {
+                    // Autodesk writes a leading comma for some nodes, such as
+                    // `Content: , "(base64)"`.
+                    token = self.next_token_across_lines(reader, common, &mut comments)?;
+                }
+                let has_children = loop {
+                    let property = match token {
+                        Token::EndOfLine if properties.is_empty() => break false,
+                        Token::OpenBrace if properties.is_empty() => break true,
+                        Token::String(value) => OwnedProperty::String(value),
+                        Token::Word(ref word) => self.parse_value(word, config)?,
+                        Token::ArrayLength(ref len) => {
+                            self.parse_array(reader, common, len, &mut comments)?
+                        }
+                        _ => return Err(self.syntax_error("Expected property value")),
+                    };
+                    properties.push(property);
+                    match self.next_token(&mut comments)? {
+                        Token::Comma => {
+                            token = self.next_token_across_lines(reader, common, &mut comments)?;
+                        }
+                        Token::EndOfLine => {
+                            // Properties continue on the next line if it starts with `,`.
+                            if !self.next_line_starts_with_comma(reader, common)? {
+                                break false;
+                            }
+                            self.next_token(&mut comments)?;
+                            token = self.next_token_across_lines(reader, common, &mut comments)?;
+                        }
+                        Token::OpenBrace => break true,
+                        _ => return Err(self.syntax_error("Expected `,` between properties")),
+                    }
+                };
+                self.pending_events
+                    .push_back(FbxEvent::StartNode { name, properties });
+                if has_children {
+                    self.expect_end_of_line(&mut comments)?;
+                    self.depth += 1;
+                } else {
+                    self.pending_events.push_back(FbxEvent::EndNode);
+                }
+            }
+            _ => return Err(self.syntax_error("Expected node name")),
+        }
+        self.pending_events
+            .extend(comments.into_iter().map(FbxEvent::Comment));
+        Ok(())
+    }
+
+    /// Parses an array property (`*N { a: v1,v2,... }`) after the array length.
+    fn parse_array<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        len: &str,
+        comments: &mut Vec<String>,
+    ) -> Result<OwnedProperty> {
+        let len = len
+            .parse::<usize>()
+            .map_err(|_| self.syntax_error("Invalid array length"))?;
+        if self.next_token(comments)? != Token::OpenBrace {
+            return Err(self.syntax_error("Expected `{` after array length"));
+        }
+        let mut elements = Vec::new();
+        let mut token = self.next_token_across_lines(reader, common, comments)?;
+        if let Token::Name(ref name) = token {
+            if name != "a" {
+                return Err(self.syntax_error("Expected `a:` before array elements"));
+            }
+            token = self.next_token_across_lines(reader, common, comments)?;
+        }
+        let mut expect_element = true;
+        loop {
+            match token {
+                Token::CloseBrace => break,
+                Token::Word(word) if expect_element => {
+                    elements.push(word);
+                    expect_element = false;
+                }
+                Token::Comma if !expect_element => expect_element = true,
+                _ => return Err(self.syntax_error("Unexpected token in array elements")),
+            }
+            token = self.next_token_across_lines(reader, common, comments)?;
+        }
+        if elements.len() != len {
+            return Err(self.syntax_error(&format!(
+                "Array length is {} but got {} elements",
+                len,
+                elements.len()
+            )));
+        }
+
+        if !elements.is_empty() && elements.iter().all(|v| v == "T" || v == "Y") {
+            return Ok(OwnedProperty::VecBool(
+                elements.iter().map(|v| v == "Y").collect(),
+            ));
+        }
+        if let Ok(vec) = elements
+            .iter()
+            .map(|v| v.parse::<i64>())
+            .collect::<::std::result::Result<Vec<_>, _>>()
+        {
+            if vec.iter().all(|&v| v as i32 as i64 == v) {
+                return Ok(OwnedProperty::VecI32(
+                    vec.into_iter().map(|v| v as i32).collect(),
+                ));
+            }
+            return Ok(OwnedProperty::VecI64(vec));
+        }
+        elements
+            .iter()
+            .map(|v| v.parse::<f64>())
+            .collect::<::std::result::Result<Vec<_>, _>>()
+            .map(OwnedProperty::VecF64)
+            .map_err(|_| self.syntax_error("Invalid array element"))
+    }
+
+    /// Parses an unquoted property value.
+    fn parse_value(&self, word: &str, config: &ParserConfig) -> Result<OwnedProperty> {
+        match word {
+            "T" => return Ok(OwnedProperty::Bool(false)),
+            "Y" => return Ok(OwnedProperty::Bool(true)),
+            _ => {}
+        }
+        if let Ok(v) = word.parse::<i64>() {
+            if v as i32 as i64 == v {
+                return Ok(OwnedProperty::I32(v as i32));
+            }
+            return Ok(OwnedProperty::I64(v));
+        }
+        if let Ok(v) = word.parse::<f64>() {
+            return Ok(OwnedProperty::F64(v));
+        }
+        if config.strict {
+            return Err(self.syntax_error(&format!("Invalid property value {:?}", word)));
+        }
+        warn!("Unknown unquoted property value {:?}, read as string", word);
+        Ok(OwnedProperty::String(word.to_string()))
+    }
+
+    /// Checks that the rest of the line is empty (except comments).
+    fn expect_end_of_line(&mut self, comments: &mut Vec<String>) -> Result<()> {
+        if self.next_token(comments)? != Token::EndOfLine {
+            return Err(self.syntax_error("Expected end of line"));
+        }
+        Ok(())
+    }
+
+    /// Reads the next line if the current line is consumed, and checks whether it starts with `,`.
+    ///
+    /// The read line is kept in the buffer even if it does not start with `,`.
+    fn next_line_starts_with_comma<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+    ) -> Result<bool> {
+        if self.cursor == self.buffer.len() && !self.read_line(reader, common)? {
+            return Ok(false);
+        }
+        Ok(self.buffer[self.cursor..].trim_start().starts_with(','))
+    }
+
+    /// Reads the next token, reading following lines if the current line is consumed.
+    fn next_token_across_lines<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        comments: &mut Vec<String>,
+    ) -> Result<Token> {
+        loop {
+            match self.next_token(comments)? {
+                Token::EndOfLine => {
+                    if self.cursor == self.buffer.len() && !self.read_line(reader, common)? {
+                        return Err(Error::new(common.pos, ErrorKind::UnexpectedEof));
+                    }
+                }
+                token => return Ok(token),
+            }
+        }
+    }
+
+    /// Reads the next token in the current line.
+    ///
+    /// Comments are skipped and pushed to `comments`.
+    fn next_token(&mut self, comments: &mut Vec<String>) -> Result<Token> {
+        let rest = &self.buffer[self.cursor..];
+        let trimmed = rest.trim_start_matches([' ', '\t', '\r']);
+        self.cursor += rest.len() - trimmed.len();
+        let (token, len) = match trimmed.chars().next() {
+            None => return Ok(Token::EndOfLine),
+            Some('\n') => (Token::EndOfLine, 1),
+            Some(';') => {
+                let len = trimmed.find('\n').unwrap_or(trimmed.len());
+                comments.push(trimmed[1..len].trim_end_matches('\r').to_string());
+                self.cursor += len;
+                return self.next_token(comments);
+            }
+            Some(',') => (Token::Comma, 1),
+            Some('{') => (Token::OpenBrace, 1),
+            Some('}') => (Token::CloseBrace, 1),
+            Some('"') => {
+                let body = &trimmed[1..];
+                let len = body
+                    .find(['"', '\n'])
+                    .filter(|&i| body[i..].starts_with('"'))
+                    .ok_or_else(|| self.syntax_error("Unterminated string"))?;
+                let value = body[..len]
+                    .replace("&quot;", "\"")
+                    .replace("&lf;", "\n")
+                    .replace("&cr;", "\r");
+                (Token::String(value), len + 2)
+            }
+            Some('*') => {
+                let len = word_len(&trimmed[1..]);
+                (Token::ArrayLength(trimmed[1..len + 1].to_string()), len + 1)
+            }
+            Some(_) => {
+                let len = word_len(trimmed);
+                if len == 0 {
+                    return Err(self.syntax_error("Unexpected character"));
+                }
+                let word = trimmed[..len].to_string();
+                if trimmed[len..].starts_with(':') {
+                    (Token::Name(word), len + 1)
+                } else {
+                    (Token::Word(word), len)
+                }
+            }
+        };
+        self.cursor += len;
+        Ok(token)
+    }
+
+    /// Creates a syntax error at the current line.
+    ///
+    /// The line which cannot be parsed is shown, to help users to find what tripped it.
+    fn syntax_error(&self, message: &str) -> Error {
+        let line = self.buffer.lines().next().unwrap_or("").trim();
+        let line = match line.char_indices().nth(MAX_LINE_CHARS_IN_ERROR) {
+            Some((end, _)) => format!("{}...", &line[..end]),
+            None => line.to_string(),
+        };
+        Error::new(
+            self.line_pos,
+            ErrorKind::DataError(format!("{} (got line {:?})", message, line)),
+        )
+    }
+
+    /// Takes a comment from the buffer if the buffer is a comment line.
+    ///
+    /// Returned comment does not contain leading `;` and trailing newline.
+    fn take_comment_line(&mut self) -> Option<String> {
+        let comment = self
+            .buffer
+            .trim_start()
+            .strip_prefix(';')?
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+        self.buffer.clear();
+        Some(comment)
+    }
+
+    /// Reads a line (including newline character) from the stream and appends it to the buffer.
+    ///
+    /// Returns `false` if the stream reached EOF and no data is read.
+    fn read_line<R: BufRead>(&mut self, reader: &mut R, common: &mut CommonState) -> Result<bool> {
+        let mut line = Vec::new();
+        let result = reader
+            .by_ref()
+            .take(MAX_LINE_LEN as u64 + 1)
+            .read_until(b'\n', &mut line);
+        common.pos += line.len() as u64;
+        try_with_pos!(common.pos, result);
+        if line.len() > MAX_LINE_LEN {
+            return Err(Error::new(
+                common.pos - line.len() as u64,
+                ErrorKind::DataError(format!("Line is longer than {} bytes", MAX_LINE_LEN)),
+            ));
+        }
+        if line.is_empty() {
+            return Ok(false);
+        }
+        // Control characters never appear in ASCII FBX, but are common in binary data.
+        if line
+            .iter()
+            .any(|&b| b.is_ascii_control() && !b"\t\r\n".contains(&b))
+        {
+            return Err(Error::new(
+                common.pos - line.len() as u64,
+                ErrorKind::DataError(
+                    "Control characters in a line (the data may be Binary FBX or corrupt)"
+                        .to_string(),
+                ),
+            ));
+        }
+        self.buffer
+            .push_str(&try_with_pos!(common.pos, String::from_utf8(line)));
+        Ok(true)
+    }
+}
+
+/// Returns the byte length of the unquoted word at the beginning of the given string.
+fn word_len(s: &str) -> usize {
+    s.find(|c: char| c.is_whitespace() || ",{}\";:*".contains(c))
+        .unwrap_or(s.len())
+}
+
+/// Parses the header comment (such as `; FBX 7.4.0 project file`) and returns the FBX version.
+///
+/// For example, `7400` is returned for `; FBX 7.4.0 project file`.
+fn parse_header_comment(line: &str) -> Option<u32> {
+    let mut words = line.trim_start().strip_prefix(';')?.split_whitespace();
+    if words.next()? != "FBX" {
+        return None;
+    }
+    let mut numbers = words.next()?.split('.').map(str::parse::<u32>);
+    let major = numbers.next()?.ok()?;
+    let minor = numbers.next()?.ok()?;
+    let revision = numbers.next().unwrap_or(Ok(0)).ok()?;
+    if minor >= 10 || revision >= 100 {
+        return None;
+    }
+    Some(major * 1000 + minor * 100 + revision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_header_comment;
+
+    #[test]
+    fn header_comment() {
+        assert_eq!(
+            parse_header_comment("; FBX 7.4.0 project file\n"),
+            Some(7400)
+        );
+        assert_eq!(parse_header_comment(";FBX 6.1.0 project file"), Some(6100));
+        assert_eq!(parse_header_comment("; FBX 7.5 project file"), Some(7500));
+        assert_eq!(parse_header_comment("; FBX project file"), None);
+        assert_eq!(parse_header_comment("; Created by Blender"), None);
+        assert_eq!(parse_header_comment("FBXHeaderExtension:  {"), None);
     }
 }
@@ -1,51 +1,312 @@
 //! Contains implementation of Binary FBX parser.
 
-use flate2;
-
-use super::CommonState;
-use crate::common::OwnedProperty;
+use super::array::{read_array_contents_into, ArrayElement, ArrayHeader, ArrayScratch};
+use super::{read_byte, CommonState};
+use crate::common::{FooterInfo, OwnedProperty, FOOTER_RESERVED_LEN};
 use crate::reader::error::{Error, ErrorKind, Result};
-use crate::reader::FbxEvent;
+use crate::reader::{FbxEvent, NodeFilter, NodeHeaderInfo, ParserConfig};
 use log::warn;
-use std::io::Read;
+use std::io::{self, Read};
 
 /// A parser for Binary FBX.
 #[derive(Debug, Clone)]
 pub struct BinaryParser {
     version: u32,
+    /// Position of the beginning of the document (i.e. the magic binary) in the stream.
+    ///
+    /// End offsets of nodes are relative to this.
+    doc_start: u64,
     end_offset_stack: Vec<u64>,
+    /// Names of the currently open nodes, from the outermost one.
+    name_stack: Vec<String>,
+    /// Information of the footer, if read.
+    footer_info: Option<FooterInfo>,
+    /// Information of the node record header of the last started node.
+    last_node_header: Option<NodeHeaderInfo>,
+    /// Type codes of the properties of the last started node.
+    ///
+    /// This is recorded only when `ParserConfig::keep_type_codes` is enabled.
+    last_type_codes: Vec<u8>,
+    /// Headers of the array properties of the last started node.
+    ///
+    /// This is recorded only when `ParserConfig::array_headers_only` is enabled.
+    last_array_headers: Vec<ArrayHeader>,
+    /// Reusable state for decoding compressed arrays.
+    array_scratch: ArrayScratch,
+    /// Whether the node at the top of `end_offset_stack` is being started, and its `StartNode`
+    /// is not emitted yet.
+    start_pending: bool,
+}
+
+/// State of `BinaryParser` to be restored when reading an event fails with a retryable error.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BinaryCheckpoint {
+    /// Number of open nodes.
+    depth: usize,
+    last_node_header: Option<NodeHeaderInfo>,
+    start_pending: bool,
 }
 
 impl BinaryParser {
     /// Constructs Binary FBX parser with FBX version (which is placed after magic binary).
-    pub(crate) fn new(version: u32) -> Self {
+    ///
+    /// `doc_start` is the position of the magic binary in the stream.
+    pub(crate) fn new(version: u32, doc_start: u64) -> Self {
         BinaryParser {
             version,
+            doc_start,
             end_offset_stack: vec![],
+            name_stack: vec![],
+            footer_info: None,
+            last_node_header: None,
+            last_type_codes: vec![],
+            last_array_headers: vec![],
+            array_scratch: ArrayScratch::new(),
+            start_pending: false,
         }
     }
 
+    /// Constructs Binary FBX parser to start reading in the middle of the data.
+    ///
+    /// `end_offset_stack` is end offsets of the ancestor nodes, from the outermost one.
+    /// Names of the ancestors are unknown, so they are treated as empty in error messages.
+    pub(crate) fn with_ancestors(version: u32, end_offset_stack: Vec<u64>) -> Self {
+        let name_stack = vec![String::new(); end_offset_stack.len()];
+        BinaryParser {
+            version,
+            doc_start: 0,
+            end_offset_stack,
+            name_stack,
+            footer_info: None,
+            last_node_header: None,
+            last_type_codes: vec![],
+            last_array_headers: vec![],
+            array_scratch: ArrayScratch::new(),
+            start_pending: false,
+        }
+    }
+
+    /// Returns FBX version.
+    pub(crate) fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns information of the footer, if already read.
+    pub(crate) fn footer_info(&self) -> Option<&FooterInfo> {
+        self.footer_info.as_ref()
+    }
+
+    /// Returns information of the node record header of the last started node.
+    pub(crate) fn last_node_header(&self) -> Option<NodeHeaderInfo> {
+        self.last_node_header
+    }
+
+    /// Returns type codes of the properties of the last started node.
+    pub(crate) fn last_type_codes(&self) -> &[u8] {
+        &self.last_type_codes
+    }
+
+    /// Returns headers of the array properties of the last started node.
+    pub(crate) fn last_array_headers(&self) -> &[ArrayHeader] {
+        &self.last_array_headers
+    }
+
+    /// Returns whether node record headers consist of 64 bit integers.
+    ///
+    /// FBX 7.5 and later use 64 bit integers for `end_offset`, `num_properties` and
+    /// `property_list_len` of node record headers.
+    /// Earlier versions (including FBX 6.x) use 32 bit integers, and there are no other
+    /// differences in the layout of node records.
+    fn has_64bit_node_header(&self) -> bool {
+        self.version >= 7500
+    }
+
+    /// Returns byte size of a null record (node record header filled with zero).
+    fn null_record_len(&self) -> u64 {
+        if self.has_64bit_node_header() {
+            // 8+8+8+1.
+            25
+        } else {
+            // 4+4+4+1.
+            13
+        }
+    }
+
+    /// Returns path of the current node, such as `Objects/Geometry/Vertices`.
+    fn node_path(&self) -> String {
+        self.name_stack.join("/")
+    }
+
+    /// Returns the state to be restored if reading the next event fails.
+    pub(crate) fn checkpoint(&self) -> BinaryCheckpoint {
+        BinaryCheckpoint {
+            depth: self.end_offset_stack.len(),
+            last_node_header: self.last_node_header,
+            start_pending: self.start_pending,
+        }
+    }
+
+    /// Restores the state at the checkpoint.
+    ///
+    /// Nodes open at the checkpoint are closed only by events which never fail after that, so
+    /// dropping the nodes opened since the checkpoint is enough.
+    pub(crate) fn restore(&mut self, checkpoint: BinaryCheckpoint) {
+        self.end_offset_stack.truncate(checkpoint.depth);
+        self.name_stack.truncate(checkpoint.depth);
+        self.last_node_header = checkpoint.last_node_header;
+        self.start_pending = checkpoint.start_pending;
+    }
+
+    /// Skips the rest of the last started node, including its children.
+    pub(crate) fn skip_node<R: Read>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+    ) -> Result<()> {
+        let end_offset = self
+            .end_offset_stack
+            .pop()
+            .expect("A node should be open to be skipped");
+        self.name_stack.pop();
+        let len = end_offset - common.pos;
+        skip_bytes(reader, &mut common.pos, len)
+    }
+
+    /// Skips the broken part of the data after an error, so that parsing can continue.
+    ///
+    /// `stream_pos` is the position where the reader actually is, which may be ahead of
+    /// `common.pos` after an error.
+    /// If the error occurred while starting a node, the whole node is skipped and no events are
+    /// emitted for it. Otherwise, the rest of the innermost open node is skipped, and `EndNode`
+    /// of it will be emitted next.
+    pub(crate) fn recover<R: Read>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        stream_pos: u64,
+    ) -> Result<()> {
+        let end_offset = if self.start_pending {
+            self.start_pending = false;
+            // The name is not pushed if the error occurred while reading it.
+            if self.name_stack.len() == self.end_offset_stack.len() {
+                self.name_stack.pop();
+            }
+            self.end_offset_stack.pop()
+        } else {
+            self.end_offset_stack.last().cloned()
+        };
+        let end_offset = match end_offset {
+            Some(end_offset) if end_offset >= stream_pos => end_offset,
+            _ => {
+                return Err(Error::new(
+                    stream_pos,
+                    ErrorKind::DataError("No node to recover parsing from".to_string()),
+                ))
+            }
+        };
+        let mut pos = stream_pos;
+        skip_bytes(reader, &mut pos, end_offset - stream_pos)?;
+        common.pos = pos;
+        Ok(())
+    }
+
+    /// Reads the footer after the end of the implicit root node, if necessary.
+    ///
+    /// `rewind` is the number of bytes of the footer already consumed, which `reader` should
+    /// yield again.
+    fn read_root_footer<R: Read>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        config: &ParserConfig,
+        rewind: usize,
+    ) -> Result<()> {
+        if config.allow_multiple_documents {
+            common.pos -= rewind as u64;
+            self.footer_info = read_footer_before_next_document(reader, common, config)?;
+        } else if config.strict || config.read_to_end {
+            common.pos -= rewind as u64;
+            self.footer_info = read_footer(reader, &mut common.pos, config)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn next<R: Read>(
         &mut self,
         reader: &mut R,
         common: &mut CommonState,
+        config: &ParserConfig,
+        node_filter: Option<&NodeFilter>,
     ) -> Result<FbxEvent> {
+        loop {
+            if let Some(event) = self.next_unfiltered(reader, common, config, node_filter)? {
+                self.start_pending = false;
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Reads the next event, or skips a node rejected by `node_filter`.
+    ///
+    /// Returns `Ok(None)` if a node is skipped.
+    fn next_unfiltered<R: Read>(
+        &mut self,
+        reader: &mut R,
+        common: &mut CommonState,
+        config: &ParserConfig,
+        node_filter: Option<&NodeFilter>,
+    ) -> Result<Option<FbxEvent>> {
+        self.start_pending = false;
         // Check if the previously read node ends here.
         if let Some(&end_pos_top) = self.end_offset_stack.last() {
-            if end_pos_top as u64 == common.pos {
+            if end_pos_top == common.pos {
                 // Reached the end of previously read node.
                 self.end_offset_stack.pop();
-                return Ok(FbxEvent::EndNode);
+                self.name_stack.pop();
+                return Ok(Some(FbxEvent::EndNode));
             }
         }
 
         // Read a node record header.
-        let node_record_header = NodeRecordHeader::read(reader, &mut common.pos, self)?;
+        let start_offset = common.pos;
+        let node_record_header = match NodeRecordHeader::read(reader, &mut common.pos, self) {
+            // The stream ended at the top level, where at least the null record of the implicit
+            // root node should follow.
+            Err(ref err) if self.end_offset_stack.is_empty() && is_eof(err) => {
+                return Err(Error::new(
+                    start_offset,
+                    ErrorKind::DataError("Truncated before root null record".to_string()),
+                ));
+            }
+            result => result?,
+        };
+        // Text in place of the first node record header is likely to be ASCII FBX with the magic
+        // binary of Binary FBX, which would cause confusing errors later.
+        if self.last_node_header.is_none()
+            && self.end_offset_stack.is_empty()
+            && node_record_header.looks_like_text(self)
+        {
+            return Err(Error::new(
+                start_offset,
+                ErrorKind::DataError(
+                    "Node record header looks like text (the data may be ASCII FBX or corrupt)"
+                        .to_string(),
+                ),
+            ));
+        }
+        if self.end_offset_stack.is_empty() && node_record_header.looks_like_footer_head(self) {
+            // The implicit root node is terminated without null record, and the footer follows
+            // the last top-level node directly.
+            let head = node_record_header.raw_bytes(self);
+            self.read_root_footer(&mut (&head[..]).chain(reader), common, config, head.len())?;
+            return Ok(Some(FbxEvent::EndFbx));
+        }
         if node_record_header.is_null_record() {
             // End of a node.
             return if let Some(expected_pos) = self.end_offset_stack.pop() {
-                if common.pos == expected_pos as u64 {
-                    Ok(FbxEvent::EndNode)
+                self.name_stack.pop();
+                if common.pos == expected_pos {
+                    Ok(Some(FbxEvent::EndNode))
                 } else {
                     // Data is collapsed (the node doesn't end at expected position).
                     Err(Error::new(
@@ -68,40 +329,167 @@ impl BinaryParser {
                 // NOTE: There is the only thing known, the last 16 bytes of the data always seem
                 //       to be `[0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c,
                 //       0xe3, 0x75, 0x8f, 0x29, 0x0b]`.
-                Ok(FbxEvent::EndFbx)
+                self.read_root_footer(reader, common, config, 0)?;
+                Ok(Some(FbxEvent::EndFbx))
             };
         } else {
             // Start of a node.
-            self.end_offset_stack.push(node_record_header.end_offset);
+            // The node should contain its name and properties, and should be contained by the
+            // parent node.
+            // Without this check, broken or malicious data can make nesting nonsensical.
+            let min_end = common
+                .pos
+                .checked_add(u64::from(node_record_header.name_len))
+                .and_then(|pos| pos.checked_add(node_record_header.property_list_len))
+                .ok_or_else(|| {
+                    Error::new(
+                        common.pos,
+                        ErrorKind::DataError(format!(
+                            "Property list length of the node is too large ({} bytes)",
+                            node_record_header.property_list_len
+                        )),
+                    )
+                })?;
+            let max_end = self.end_offset_stack.last().cloned().unwrap_or(u64::MAX);
+            let end_offset = node_record_header.end_offset.saturating_add(self.doc_start);
+            if end_offset <= common.pos || end_offset < min_end || end_offset > max_end {
+                return Err(Error::new(
+                    common.pos,
+                    ErrorKind::DataError(format!(
+                        "Invalid end offset of the node (expected {}..={}, but got {})",
+                        min_end.max(common.pos + 1),
+                        max_end,
+                        end_offset
+                    )),
+                ));
+            }
+            // A node without a name is suspicious if it has any contents.
+            // (Data between the properties and the end of the node, other than a null record,
+            // should be children.)
+            let children_start = min_end.checked_add(self.null_record_len()).ok_or_else(|| {
+                Error::new(
+                    common.pos,
+                    ErrorKind::DataError(format!(
+                        "Invalid end offset of the node (got {})",
+                        end_offset
+                    )),
+                )
+            })?;
+            let has_children = end_offset > children_start;
+            if node_record_header.name_len == 0
+                && (node_record_header.num_properties > 0 || has_children)
+            {
+                let msg = "Node with properties or children has an empty name";
+                if config.strict {
+                    return Err(Error::new(
+                        common.pos,
+                        ErrorKind::DataError(msg.to_string()),
+                    ));
+                }
+                warn!("{} (pos={})", msg, common.pos);
+            }
+            if let Some(max_depth) = config.max_depth {
+                if self.end_offset_stack.len() >= max_depth {
+                    return Err(Error::new(
+                        common.pos,
+                        ErrorKind::DataError(format!(
+                            "Nesting of nodes is deeper than the limit ({})",
+                            max_depth
+                        )),
+                    ));
+                }
+            }
+            self.end_offset_stack.push(end_offset);
+            self.start_pending = true;
+            self.last_node_header = Some(NodeHeaderInfo {
+                start_offset,
+                num_properties: node_record_header.num_properties,
+                property_list_len: node_record_header.property_list_len,
+                end_offset,
+                has_children,
+            });
         }
 
         // Read a node name.
-        let name = try_read_fixstr!(common.pos, reader, node_record_header.name_len);
+        let name = try_read_fixstr!(
+            common.pos,
+            reader,
+            node_record_header.name_len,
+            config.utf8_lossy
+        );
+        self.name_stack.push(name.clone());
+
+        if let Some(filter) = node_filter {
+            if !filter(&name, self.end_offset_stack.len() - 1) {
+                // Skip the whole node.
+                let end_offset = self.end_offset_stack.pop().unwrap();
+                self.name_stack.pop();
+                self.start_pending = false;
+                let len = end_offset - common.pos;
+                skip_bytes(reader, &mut common.pos, len)?;
+                return Ok(None);
+            }
+        }
+
+        self.last_array_headers.clear();
+        if config.headers_only {
+            // Skip properties.
+            skip_bytes(
+                reader,
+                &mut common.pos,
+                node_record_header.property_list_len,
+            )?;
+            return Ok(Some(FbxEvent::StartNode {
+                name,
+                properties: vec![],
+            }));
+        }
 
         // Read properties.
         let mut properties =
             Vec::<OwnedProperty>::with_capacity(node_record_header.num_properties as usize);
+        let props_end = common
+            .pos
+            .checked_add(node_record_header.property_list_len)
+            .ok_or_else(|| {
+                Error::new(
+                    common.pos,
+                    ErrorKind::DataError(format!(
+                        "Property list length of the node is too large ({} bytes)",
+                        node_record_header.property_list_len
+                    )),
+                )
+            })?;
+        self.last_type_codes.clear();
         for _ in 0..node_record_header.num_properties {
-            let prop = self.read_property(reader, common)?;
+            let prop = self.read_property(reader, common, config, props_end)?;
             properties.push(prop);
         }
 
-        Ok(FbxEvent::StartNode { name, properties })
+        Ok(Some(FbxEvent::StartNode { name, properties }))
     }
 
     /// Read a node property value.
+    ///
+    /// `props_end` is the end position of the property list of the node.
     fn read_property<R: Read>(
         &mut self,
         reader: &mut R,
         common: &mut CommonState,
+        config: &ParserConfig,
+        props_end: u64,
     ) -> Result<OwnedProperty> {
         let type_code = try_read_le_u8!(common.pos, reader);
+        if config.keep_type_codes {
+            self.last_type_codes.push(type_code);
+        }
         // type code must be ASCII.
         let type_code = if type_code > 0x80 {
             return Err(Error::new(
                 common.pos - 1,
                 ErrorKind::DataError(format!(
-                    "Expected property type code (ASCII) but got {:#x}",
+                    "Expected property type code (ASCII) but got {:#x} \
+                     (the stream may be corrupt, or may not be Binary FBX)",
                     type_code
                 )),
             ));
@@ -111,12 +499,17 @@ impl BinaryParser {
         let value = match type_code {
             // 1 bit boolean (1: true, 0: false) encoded as the LSB of a 1 byte value.
             'C' => {
+                let val_pos = common.pos;
                 let val = try_read_le_u8!(common.pos, reader);
-                // It seems 'T' (0x54) is used as `false`, 'T' (0x59) is used as `true`.
+                // It seems 'T' (0x54) is used as `false`, 'Y' (0x59) is used as `true`.
                 if (val != b'T') && (val != b'Y') {
-                    // Should this treated as error?
-                    // (I don't know whether other characters than 'T' and 'Y' are allowed...)
-                    warn!("Expected 'T' or 'Y' for representaton of boolean property value, but got {:#x}", val);
+                    config.unexpected_bool.check(val_pos, || {
+                        format!(
+                            "Expected 'T' or 'Y' for representation of boolean property value, \
+                             but got {:#x}",
+                            val
+                        )
+                    })?;
                 }
                 // Check LSB.
                 OwnedProperty::Bool(val & 1 == 1)
@@ -133,24 +526,78 @@ impl BinaryParser {
             'L' => OwnedProperty::I64(try_read_le_i64!(common.pos, reader)),
             // Array types
             'f' | 'd' | 'l' | 'i' | 'b' => {
-                let array_header = PropertyArrayHeader::read(reader, &mut common.pos)?;
-                self.read_property_value_array(reader, common, type_code, &array_header)?
+                let array_header =
+                    ArrayHeader::read_after_type_code(reader, &mut common.pos, type_code as u8)?;
+                check_data_len(
+                    common.pos,
+                    array_header.compressed_length,
+                    props_end,
+                    "array",
+                )?;
+                if config.array_headers_only {
+                    skip_bytes(
+                        reader,
+                        &mut common.pos,
+                        u64::from(array_header.compressed_length),
+                    )?;
+                    self.last_array_headers.push(array_header);
+                    // Keep the type of the property, without elements.
+                    match array_header.type_code {
+                        b'b' => OwnedProperty::VecBool(vec![]),
+                        b'i' => OwnedProperty::VecI32(vec![]),
+                        b'l' => OwnedProperty::VecI64(vec![]),
+                        b'f' => OwnedProperty::VecF32(vec![]),
+                        _ => OwnedProperty::VecF64(vec![]),
+                    }
+                } else if config.raw_compressed_arrays && array_header.encoding == 1 {
+                    OwnedProperty::CompressedArray {
+                        type_code: array_header.type_code,
+                        array_length: array_header.array_length,
+                        data: try_read_exact!(common.pos, reader, array_header.compressed_length),
+                    }
+                } else {
+                    self.read_property_value_array(reader, common, config, &array_header)?
+                }
             }
             // String
             'S' => {
                 let length = try_read_le_u32!(common.pos, reader);
-                OwnedProperty::String(try_read_fixstr!(common.pos, reader, length))
+                check_data_len(common.pos, length, props_end, "string")?;
+                if config.raw_strings {
+                    OwnedProperty::RawString(try_read_exact!(common.pos, reader, length))
+                } else {
+                    OwnedProperty::String(try_read_fixstr!(
+                        common.pos,
+                        reader,
+                        length,
+                        config.utf8_lossy
+                    ))
+                }
             }
             // Raw binary data
             'R' => {
                 let length = try_read_le_u32!(common.pos, reader);
+                check_data_len(common.pos, length, props_end, "binary")?;
                 OwnedProperty::Binary(try_read_exact!(common.pos, reader, length))
             }
+            // There is no `'B'` type code in FBX.
+            // Boolean is `'C'` and array of booleans is `'b'`.
+            'B' => {
+                return Err(Error::new(
+                    common.pos,
+                    ErrorKind::UnexpectedValue(
+                        "Unsupported type code appears in node property: type_code=B(0x42) \
+                         (boolean is 'C' and boolean array is 'b'; the stream may be corrupt)"
+                            .to_string(),
+                    ),
+                ));
+            }
             _ => {
                 return Err(Error::new(
                     common.pos,
                     ErrorKind::UnexpectedValue(format!(
-                        "Unsupported type code appears in node property: type_code={}({:#x})",
+                        "Unsupported type code appears in node property: type_code={}({:#x}) \
+                         (the stream may be corrupt)",
                         type_code, type_code as u8
                     )),
                 ));
@@ -164,108 +611,260 @@ impl BinaryParser {
         &mut self,
         reader: &mut R,
         common: &mut CommonState,
-        type_code: char,
-        array_header: &PropertyArrayHeader,
+        config: &ParserConfig,
+        array_header: &ArrayHeader,
     ) -> Result<OwnedProperty> {
-        match array_header.encoding {
-            // 0; raw
-            0 => {
-                let (val, byte_size) = self.read_property_value_array_from_plain_stream(
-                    reader,
-                    common.pos,
-                    type_code,
-                    array_header.array_length,
-                )?;
-                common.pos += byte_size;
-                Ok(val)
-            }
-            // 1: zlib compressed data
-            1 => {
-                let mut decoded_stream = flate2::read::ZlibDecoder::new(
-                    reader
-                        .by_ref()
-                        .take(u64::from(array_header.compressed_length)),
-                );
-                let (val, _) = self.read_property_value_array_from_plain_stream(
-                    &mut decoded_stream,
-                    common.pos,
-                    type_code,
-                    array_header.array_length,
-                )?;
-                common.pos += u64::from(array_header.compressed_length);
-                Ok(val)
-            }
-            // Unknown.
-            e => Err(Error::new(
-                common.pos,
-                ErrorKind::UnexpectedValue(format!(
-                    "Unsupported property array encoding, got {:#x}",
-                    e
-                )),
-            )),
+        fn read_vec<R: Read, T: ArrayElement>(
+            reader: &mut R,
+            pos: &mut u64,
+            array_header: &ArrayHeader,
+            config: &ParserConfig,
+            scratch: &mut ArrayScratch,
+        ) -> Result<Vec<T>> {
+            let mut data = Vec::new();
+            *pos +=
+                read_array_contents_into(reader, *pos, array_header, &mut data, config, scratch)?;
+            Ok(data)
         }
-    }
 
-    /// Read a property value of array type from plain (uncompressed) stream.
-    fn read_property_value_array_from_plain_stream<R: Read>(
-        &mut self,
-        reader: &mut R,
-        abs_pos: u64,
-        type_code: char,
-        num_elements: u32,
-    ) -> Result<(OwnedProperty, u64)> {
-        use byteorder::{LittleEndian, ReadBytesExt};
-        Ok(match type_code {
+        let pos = &mut common.pos;
+        let scratch = &mut self.array_scratch;
+        let result = match array_header.type_code {
             // Array of 4 byte single-precision IEEE 754 floating-point number.
-            'f' => {
-                let mut data = Vec::<f32>::with_capacity(num_elements as usize);
-                for _ in 0..num_elements {
-                    data.push(try_with_pos!(abs_pos, reader.read_f32::<LittleEndian>()));
-                }
-                (OwnedProperty::VecF32(data), u64::from(num_elements) * 4)
-            }
+            b'f' => read_vec(reader, pos, array_header, config, scratch).map(OwnedProperty::VecF32),
             // Array of 8 byte double-precision IEEE 754 floating-point number.
-            'd' => {
-                let mut data = Vec::<f64>::with_capacity(num_elements as usize);
-                for _ in 0..num_elements {
-                    data.push(try_with_pos!(abs_pos, reader.read_f64::<LittleEndian>()));
-                }
-                (OwnedProperty::VecF64(data), u64::from(num_elements) * 8)
-            }
+            b'd' => read_vec(reader, pos, array_header, config, scratch).map(OwnedProperty::VecF64),
             // Array of 8 byte signed integer.
-            'l' => {
-                let mut data = Vec::<i64>::with_capacity(num_elements as usize);
-                for _ in 0..num_elements {
-                    data.push(try_with_pos!(abs_pos, reader.read_i64::<LittleEndian>()));
-                }
-                (OwnedProperty::VecI64(data), u64::from(num_elements) * 8)
-            }
+            b'l' => read_vec(reader, pos, array_header, config, scratch).map(OwnedProperty::VecI64),
             // Array of 4 byte signed integer.
-            'i' => {
-                let mut data = Vec::<i32>::with_capacity(num_elements as usize);
-                for _ in 0..num_elements {
-                    data.push(try_with_pos!(abs_pos, reader.read_i32::<LittleEndian>()));
-                }
-                (OwnedProperty::VecI32(data), u64::from(num_elements) * 4)
-            }
+            b'i' => read_vec(reader, pos, array_header, config, scratch).map(OwnedProperty::VecI32),
             // Array of 1 byte booleans (always 0 or 1?).
-            'b' => {
-                let mut data = Vec::<bool>::with_capacity(num_elements as usize);
-                for _ in 0..num_elements {
-                    // Check LSB.
-                    data.push(try_with_pos!(abs_pos, reader.read_u8()) & 1 == 1);
-                }
-                (OwnedProperty::VecBool(data), u64::from(num_elements))
+            b'b' => {
+                read_vec(reader, pos, array_header, config, scratch).map(OwnedProperty::VecBool)
             }
             _ => {
                 // Unreachable because `read_property()` gives only 'f' , 'd', 'l', 'i', or 'b' to
                 // `read_property_value_array()`.
                 unreachable!();
             }
+        };
+        result.map_err(|err| match *err.kind() {
+            ErrorKind::DataError(ref msg) => Error::new(
+                err.pos(),
+                ErrorKind::DataError(format!("{} (in node `{}`)", msg, self.node_path())),
+            ),
+            _ => err,
         })
     }
 }
 
+/// Reads and discards the given length of data.
+fn skip_bytes<R: Read>(reader: &mut R, pos: &mut u64, len: u64) -> Result<()> {
+    let skipped = try_with_pos!(
+        *pos,
+        io::copy(&mut reader.by_ref().take(len), &mut io::sink())
+    );
+    *pos += skipped;
+    if skipped != len {
+        return Err(Error::new(*pos, ErrorKind::UnexpectedEof));
+    }
+    Ok(())
+}
+
+/// Checks that data of the given length starting at `pos` fits in the property list.
+///
+/// This rejects bogus lengths before allocating buffers for them.
+fn check_data_len(pos: u64, len: u32, props_end: u64, what: &str) -> Result<()> {
+    let end = pos.checked_add(u64::from(len));
+    if end.filter(|&end| end <= props_end).is_none() {
+        return Err(Error::new(
+            pos,
+            ErrorKind::DataError(format!(
+                "Length of {} property ({} bytes) exceeds the end of the property list \
+                 (at pos={})",
+                what, len, props_end
+            )),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks whether the error is caused by unexpected end of the stream.
+fn is_eof(err: &Error) -> bool {
+    match *err.kind() {
+        ErrorKind::UnexpectedEof => true,
+        ErrorKind::Io(ref err) => err.kind() == io::ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
+
+/// High nibbles of the first 16 bytes of footers.
+///
+/// Low nibbles differ among files.
+const FOOTER_HEAD_HIGH_NIBBLES: [u8; 16] = [
+    0xf, 0xb, 0xa, 0x0, 0xd, 0xc, 0xd, 0x6, 0xb, 0x7, 0xf, 0x8, 0x1, 0xf, 0x2, 0x7,
+];
+
+/// The last 16 bytes of footers.
+const FOOTER_END_MAGIC: [u8; 16] = [
+    0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c, 0xe3, 0x75, 0x8f, 0x29, 0x0b,
+];
+
+/// Length of the last part of footers: `0u32`, version, reserved area, and end magic.
+const FOOTER_TAIL_LEN: usize = 4 + 4 + FOOTER_RESERVED_LEN + FOOTER_END_MAGIC.len();
+
+/// Reads the footer following the null record of the implicit root node.
+///
+/// In strict mode, checks that the data looks like a footer, and anything else is considered as
+/// unexpected trailing data.
+/// No data (i.e. EOF) is allowed because some exporters might not write footers.
+///
+/// If `config.read_to_end` is `true`, reads to the end of the stream and checks the end magic
+/// loosely (only warns in non-strict mode), and returns information of the footer if available.
+/// Otherwise, reads only the first 16 bytes of the footer (only in strict mode).
+fn read_footer<R: Read>(
+    reader: &mut R,
+    pos: &mut u64,
+    config: &ParserConfig,
+) -> Result<Option<FooterInfo>> {
+    let start_pos = *pos;
+    let mut head = Vec::with_capacity(16);
+    let len = try_with_pos!(*pos, reader.by_ref().take(16).read_to_end(&mut head)) as u64;
+    *pos += len;
+    if head.is_empty() {
+        return Ok(None);
+    }
+    let is_footer_head = is_footer_head(&head);
+    if config.strict && !is_footer_head {
+        return Err(Error::new(
+            start_pos,
+            ErrorKind::DataError(
+                "Unexpected trailing data after the end of the document (not a footer)".to_string(),
+            ),
+        ));
+    }
+    if !config.read_to_end {
+        return Ok(None);
+    }
+
+    // Read to the end, remembering the last bytes: `0u32`, version, reserved area, and end magic.
+    let mut tail = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        let len = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(*pos, err)),
+        };
+        *pos += len as u64;
+        tail.extend_from_slice(&chunk[..len]);
+        let excess = tail.len().saturating_sub(FOOTER_TAIL_LEN);
+        tail.drain(..excess);
+    }
+    if tail.len() < FOOTER_TAIL_LEN || !tail.ends_with(&FOOTER_END_MAGIC) {
+        if config.strict {
+            return Err(Error::new(
+                *pos,
+                ErrorKind::DataError(
+                    "Unexpected trailing data after the footer (footer end magic not found)"
+                        .to_string(),
+                ),
+            ));
+        }
+        warn!("Footer end magic not found, there might be trailing data after the footer");
+        return Ok(None);
+    }
+    if !is_footer_head {
+        return Ok(None);
+    }
+    Ok(Some(footer_info(&head, &tail)))
+}
+
+/// Reads the footer of a document which may be followed by another document.
+///
+/// Unlike `read_footer`, this reads only to the end magic of the footer, regardless of
+/// `config.read_to_end`.
+/// If the data does not start as a footer, it is treated as the beginning of the next document
+/// and the first byte is left to `common.lookahead`.
+fn read_footer_before_next_document<R: Read>(
+    reader: &mut R,
+    common: &mut CommonState,
+    config: &ParserConfig,
+) -> Result<Option<FooterInfo>> {
+    let start_pos = common.pos;
+    let first = match read_byte(reader, &mut common.pos)? {
+        Some(first) => first,
+        None => return Ok(None),
+    };
+    if first >> 4 != FOOTER_HEAD_HIGH_NIBBLES[0] {
+        // Not a footer (probably the magic of the next document).
+        common.pos = start_pos;
+        common.lookahead = Some(first);
+        return Ok(None);
+    }
+
+    // Read to the end magic, remembering the first 16 bytes and the last bytes.
+    let mut head = vec![first];
+    let mut tail = vec![first];
+    while tail.len() < FOOTER_TAIL_LEN || !tail.ends_with(&FOOTER_END_MAGIC) {
+        let byte = match read_byte(reader, &mut common.pos)? {
+            Some(byte) => byte,
+            None => {
+                let msg = "Unexpected end of the footer (footer end magic not found)";
+                if config.strict {
+                    return Err(Error::new(
+                        common.pos,
+                        ErrorKind::DataError(msg.to_string()),
+                    ));
+                }
+                warn!("{} (pos={})", msg, common.pos);
+                return Ok(None);
+            }
+        };
+        if head.len() < 16 {
+            head.push(byte);
+        }
+        tail.push(byte);
+        if tail.len() > FOOTER_TAIL_LEN {
+            tail.remove(0);
+        }
+    }
+    if !is_footer_head(&head) {
+        let msg = "Unexpected trailing data after the end of the document (not a footer)";
+        if config.strict {
+            return Err(Error::new(start_pos, ErrorKind::DataError(msg.to_string())));
+        }
+        warn!("{} (pos={})", msg, start_pos);
+        return Ok(None);
+    }
+    Ok(Some(footer_info(&head, &tail)))
+}
+
+/// Checks whether the bytes look like the first 16 bytes of a footer.
+fn is_footer_head(head: &[u8]) -> bool {
+    head.len() == FOOTER_HEAD_HIGH_NIBBLES.len()
+        && head
+            .iter()
+            .zip(FOOTER_HEAD_HIGH_NIBBLES.iter())
+            .all(|(&byte, &nibble)| byte >> 4 == nibble)
+}
+
+/// Creates footer information from the first 16 bytes and the last `FOOTER_TAIL_LEN` bytes of
+/// the footer.
+fn footer_info(head: &[u8], tail: &[u8]) -> FooterInfo {
+    let mut footer = FooterInfo {
+        head: [0; 16],
+        version: u32::from_le_bytes([tail[4], tail[5], tail[6], tail[7]]),
+        reserved: [0; FOOTER_RESERVED_LEN],
+    };
+    footer.head.copy_from_slice(head);
+    footer
+        .reserved
+        .copy_from_slice(&tail[8..8 + FOOTER_RESERVED_LEN]);
+    footer
+}
+
 /// A header of a node.
 #[derive(Debug, Copy, Clone)]
 struct NodeRecordHeader {
@@ -282,20 +881,20 @@ struct NodeRecordHeader {
 impl NodeRecordHeader {
     /// Constructs `NodeRecordHeader` from the given stream.
     pub fn read<R: Read>(reader: &mut R, pos: &mut u64, context: &BinaryParser) -> Result<Self> {
-        let end_offset = if context.version < 7500 {
-            u64::from(try_read_le_u32!(*pos, reader))
-        } else {
+        let end_offset = if context.has_64bit_node_header() {
             try_read_le_u64!(*pos, reader)
-        };
-        let num_properties = if context.version < 7500 {
-            u64::from(try_read_le_u32!(*pos, reader))
         } else {
-            try_read_le_u64!(*pos, reader)
-        };
-        let property_list_len = if context.version < 7500 {
             u64::from(try_read_le_u32!(*pos, reader))
+        };
+        let num_properties = if context.has_64bit_node_header() {
+            try_read_le_u64!(*pos, reader)
         } else {
+            u64::from(try_read_le_u32!(*pos, reader))
+        };
+        let property_list_len = if context.has_64bit_node_header() {
             try_read_le_u64!(*pos, reader)
+        } else {
+            u64::from(try_read_le_u32!(*pos, reader))
         };
         let name_len = try_read_le_u8!(*pos, reader);
         Ok(NodeRecordHeader {
@@ -313,29 +912,47 @@ impl NodeRecordHeader {
             && self.property_list_len == 0
             && self.name_len == 0
     }
-}
 
-/// A header of a property of array type.
-#[derive(Debug, Copy, Clone)]
-struct PropertyArrayHeader {
-    /// Number of values in the array, *NOT byte size*.
-    array_length: u32,
-    /// Denotes whether data in stream is plain, or what algorithm it is compressed by.
-    encoding: u32,
-    /// Byte size of the compressed array value in the stream.
-    compressed_length: u32,
-}
+    /// Returns the bytes of the header as stored in the data.
+    fn raw_bytes(&self, context: &BinaryParser) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(25);
+        for &v in &[self.end_offset, self.num_properties, self.property_list_len] {
+            if context.has_64bit_node_header() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&(v as u32).to_le_bytes());
+            }
+        }
+        bytes.push(self.name_len);
+        bytes
+    }
+
+    /// Checks whether the header is actually the beginning of a footer.
+    ///
+    /// This happens when the null record of the implicit root node is omitted.
+    /// Valid headers practically never match, because the number of properties (or the end
+    /// offset, for 64-bit headers) would be more than 0x6000_0000.
+    fn looks_like_footer_head(&self, context: &BinaryParser) -> bool {
+        self.raw_bytes(context)
+            .iter()
+            .zip(FOOTER_HEAD_HIGH_NIBBLES.iter())
+            .all(|(&byte, &nibble)| byte >> 4 == nibble)
+    }
 
-impl PropertyArrayHeader {
-    /// Constructs `PropertyArrayHeader` from the given stream.
-    pub fn read<R: Read>(reader: &mut R, pos: &mut u64) -> Result<Self> {
-        let array_length = try_read_le_u32!(*pos, reader);
-        let encoding = try_read_le_u32!(*pos, reader);
-        let compressed_length = try_read_le_u32!(*pos, reader);
-        Ok(PropertyArrayHeader {
-            array_length,
-            encoding,
-            compressed_length,
+    /// Checks whether the end offset and the number of properties consist of text bytes.
+    ///
+    /// This is practically impossible for valid headers, because the number of properties
+    /// would be more than 0x2020_2020.
+    fn looks_like_text(&self, context: &BinaryParser) -> bool {
+        let len = if context.has_64bit_node_header() {
+            8
+        } else {
+            4
+        };
+        [self.end_offset, self.num_properties].iter().all(|v| {
+            v.to_le_bytes()[..len]
+                .iter()
+                .all(|&b| b.is_ascii_graphic() || b" \t\r\n".contains(&b))
         })
     }
 }
@@ -23,7 +23,7 @@ macro_rules! try_with_pos {
 
 macro_rules! try_read_le_u8 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_u8());
         $pos += 1;
         val
@@ -32,8 +32,8 @@ macro_rules! try_read_le_u8 {
 
 macro_rules! try_read_le_u32 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_u32::<byteorder::LittleEndian>());
         $pos += 4;
         val
@@ -42,8 +42,8 @@ macro_rules! try_read_le_u32 {
 
 macro_rules! try_read_le_u64 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_u64::<byteorder::LittleEndian>());
         $pos += 8;
         val
@@ -52,8 +52,8 @@ macro_rules! try_read_le_u64 {
 
 macro_rules! try_read_le_i16 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_i16::<byteorder::LittleEndian>());
         $pos += 2;
         val
@@ -62,8 +62,8 @@ macro_rules! try_read_le_i16 {
 
 macro_rules! try_read_le_i32 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_i32::<byteorder::LittleEndian>());
         $pos += 4;
         val
@@ -72,8 +72,8 @@ macro_rules! try_read_le_i32 {
 
 macro_rules! try_read_le_i64 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_i64::<byteorder::LittleEndian>());
         $pos += 8;
         val
@@ -82,8 +82,8 @@ macro_rules! try_read_le_i64 {
 
 macro_rules! try_read_le_f32 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_f32::<byteorder::LittleEndian>());
         $pos += 4;
         val
@@ -92,29 +92,30 @@ macro_rules! try_read_le_f32 {
 
 macro_rules! try_read_le_f64 {
     ($pos:expr, $reader:expr) => {{
-        use $crate::byteorder;
-        use $crate::byteorder::ReadBytesExt;
+        use ::byteorder;
+        use ::byteorder::ReadBytesExt;
         let val = try_with_pos!($pos, $reader.read_f64::<byteorder::LittleEndian>());
         $pos += 8;
         val
     }};
 }
 
+/// Reads a string of the given length.
+///
+/// If `$lossy` is true, invalid UTF-8 sequences are replaced with `U+FFFD`.
+/// Otherwise, invalid UTF-8 sequences are reported as `ErrorKind::Utf8Error`.
 macro_rules! try_read_fixstr {
-    ($pos:expr, $reader:expr, $len:expr) => {{
-        let mut buffer = String::with_capacity($len as usize);
-        let len = try_with_pos!(
-            $pos,
-            $reader
-                .by_ref()
-                .take(u64::from($len))
-                .read_to_string(&mut buffer)
-        ) as u64;
-        if len != (u64::from($len)) {
-            return Err(Error::new($pos, ErrorKind::UnexpectedEof));
+    ($pos:expr, $reader:expr, $len:expr, $lossy:expr) => {{
+        let start_pos = $pos;
+        let bytes = try_read_exact!($pos, $reader, $len);
+        if $lossy {
+            match String::from_utf8(bytes) {
+                Ok(s) => s,
+                Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+            }
+        } else {
+            try_with_pos!(start_pos, String::from_utf8(bytes))
         }
-        $pos += len;
-        buffer
     }};
 }
 
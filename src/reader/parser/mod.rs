@@ -1,15 +1,16 @@
 //! Contains implementations of FBX parsers.
 
-use self::ascii::AsciiParser;
-use self::binary::BinaryParser;
-use crate::common::FbxFormatType;
+use self::ascii::{AsciiCheckpoint, AsciiParser};
+use self::binary::{BinaryCheckpoint, BinaryParser};
+use crate::common::{FbxFormatType, FooterInfo};
 use crate::reader::error::{Error, ErrorKind, Result};
-use crate::reader::{FbxEvent, ParserConfig};
+use crate::reader::{ArrayHeader, FbxEvent, NodeFilter, NodeHeaderInfo, ParserConfig};
 use log::{debug, warn};
-use std::io::Read;
+use std::io::{self, BufRead, Read};
 
 mod macros;
 
+pub(crate) mod array;
 mod ascii;
 mod binary;
 
@@ -19,24 +20,65 @@ enum ParserState {
     /// Reading magic binary (i.e. the first line).
     Magic,
     /// Reading binary FBX.
-    Binary(BinaryParser),
+    Binary(Box<BinaryParser>),
     /// Reading ASCII FBX.
     Ascii(AsciiParser),
 }
 
+/// Sub parser state to be restored when reading an event fails with a retryable error.
+#[derive(Debug, Clone, Copy)]
+enum StateCheckpoint {
+    Magic,
+    Binary(BinaryCheckpoint),
+    Ascii(AsciiCheckpoint),
+}
+
+/// Parser state to be restored when reading an event fails with a retryable error.
+///
+/// This is taken for every `try_next` call, so it keeps only positions and counters which an
+/// event can change before it fails, instead of a clone of the whole state.
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    common: CommonState,
+    state: StateCheckpoint,
+    num_nodes: u64,
+    after_start_node: bool,
+    document_ended: bool,
+}
+
 /// Common state among all sub parsers.
 #[derive(Debug, Clone)]
 pub(crate) struct CommonState {
     /// Position of last successfully read byte.
     pos: u64,
     final_result: Option<Result<FbxEvent>>,
+    /// A byte read from the stream but not parsed yet, which is the first byte of the next
+    /// document.
+    ///
+    /// This is not counted in `pos`.
+    lookahead: Option<u8>,
 }
 
 /// A simple wrapper around magic, binary and ascii FBX parser.
 pub struct Parser {
     config: ParserConfig,
+    /// A predicate to select nodes to read in Binary FBX.
+    node_filter: Option<NodeFilter>,
     common: CommonState,
     state: ParserState,
+    /// Number of nodes read so far.
+    num_nodes: u64,
+    /// Whether the last event is `StartNode`.
+    after_start_node: bool,
+    /// Whether `EndFbx` of a document is emitted and the next document is not started yet.
+    ///
+    /// This is used only when `allow_multiple_documents` is enabled.
+    document_ended: bool,
+    /// Number of bytes actually consumed from the stream, including bytes of the event being
+    /// read when an error occurred and the lookahead byte.
+    ///
+    /// This is used to recover from errors.
+    stream_pos: u64,
 }
 
 impl Parser {
@@ -44,27 +86,214 @@ impl Parser {
     pub fn new(config: ParserConfig) -> Self {
         Parser {
             config,
+            node_filter: None,
             common: CommonState {
                 pos: 0,
                 final_result: None,
+                lookahead: None,
             },
             state: ParserState::Magic,
+            num_nodes: 0,
+            after_start_node: false,
+            document_ended: false,
+            stream_pos: 0,
+        }
+    }
+
+    /// Constructs a parser which starts reading Binary FBX at the given position.
+    ///
+    /// See [`EventReader::new_binary_at`](../struct.EventReader.html#method.new_binary_at).
+    pub fn new_binary_at(
+        config: ParserConfig,
+        version: u32,
+        pos: u64,
+        ancestor_end_offsets: Vec<u64>,
+    ) -> Self {
+        Parser {
+            config,
+            node_filter: None,
+            common: CommonState {
+                pos,
+                final_result: None,
+                lookahead: None,
+            },
+            state: ParserState::Binary(Box::new(BinaryParser::with_ancestors(
+                version,
+                ancestor_end_offsets,
+            ))),
+            num_nodes: 0,
+            after_start_node: false,
+            document_ended: false,
+            stream_pos: pos,
+        }
+    }
+
+    /// Returns the position of the last successfully read byte.
+    ///
+    /// For ASCII FBX, data read ahead but not parsed yet is not counted.
+    pub fn pos(&self) -> u64 {
+        match self.state {
+            ParserState::Ascii(ref parser) => self.common.pos - parser.buffered_len(),
+            _ => self.common.pos,
+        }
+    }
+
+    /// Returns FBX version if already known.
+    pub fn fbx_version(&self) -> Option<u32> {
+        match self.state {
+            ParserState::Magic => None,
+            ParserState::Binary(ref parser) => Some(parser.version()),
+            ParserState::Ascii(ref parser) => parser.version(),
+        }
+    }
+
+    /// Returns information of the footer of Binary FBX, if already read.
+    pub fn footer_info(&self) -> Option<&FooterInfo> {
+        match self.state {
+            ParserState::Binary(ref parser) => parser.footer_info(),
+            _ => None,
+        }
+    }
+
+    /// Returns information of the node record header of the last started node, if available.
+    pub fn last_node_header(&self) -> Option<NodeHeaderInfo> {
+        match self.state {
+            ParserState::Binary(ref parser) => parser.last_node_header(),
+            _ => None,
+        }
+    }
+
+    /// Returns type codes of the properties of the last started node, if recorded.
+    pub fn last_type_codes(&self) -> Option<&[u8]> {
+        match self.state {
+            ParserState::Binary(ref parser)
+                if self.config.keep_type_codes && parser.last_node_header().is_some() =>
+            {
+                Some(parser.last_type_codes())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns headers of the array properties of the last started node, if recorded.
+    pub fn last_array_headers(&self) -> Option<&[ArrayHeader]> {
+        match self.state {
+            ParserState::Binary(ref parser)
+                if self.config.array_headers_only && parser.last_node_header().is_some() =>
+            {
+                Some(parser.last_array_headers())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether the node started by the last event has child nodes, if known.
+    ///
+    /// See [`EventReader::current_node_has_children`](../struct.EventReader.html#method.current_node_has_children).
+    pub fn current_node_has_children(&self) -> Option<bool> {
+        if !self.after_start_node {
+            return None;
+        }
+        match self.state {
+            ParserState::Magic => None,
+            ParserState::Binary(ref parser) => parser.last_node_header().map(|h| h.has_children),
+            ParserState::Ascii(ref parser) => parser.current_node_has_children(),
         }
     }
 
+    /// Sets a predicate to select nodes to read in Binary FBX.
+    pub fn set_node_filter(&mut self, filter: Option<NodeFilter>) {
+        self.node_filter = filter;
+    }
+
+    /// Returns whether the last event is `StartNode`.
+    pub fn is_after_start_node(&self) -> bool {
+        self.after_start_node
+    }
+
     /// Get next `FbxEvent`.
-    pub fn next<R: Read>(&mut self, reader: &mut R) -> Result<FbxEvent> {
+    pub fn next<R: BufRead>(&mut self, reader: &mut R) -> Result<FbxEvent> {
         // If parsing has been finished, return the last result.
         if let Some(ref result) = self.common.final_result {
             return result.clone();
         }
+        let mut reader = CountingReader::new(reader);
+        let result = self.next_event(&mut reader);
+        self.stream_pos += reader.count;
+        self.latch_final_result(&result);
+        result
+    }
+
+    /// Get next `FbxEvent`, and restores the parser state if a retryable error occurs.
+    pub fn try_next<R: BufRead>(&mut self, reader: &mut R) -> Result<FbxEvent> {
+        if let Some(ref result) = self.common.final_result {
+            return result.clone();
+        }
+        let checkpoint = self.checkpoint();
+        let mut reader = CountingReader::new(reader);
+        let result = self.next_event(&mut reader);
+        match result {
+            Err(ref err) if err.is_retryable() => self.restore(checkpoint),
+            _ => {
+                self.stream_pos += reader.count;
+                self.latch_final_result(&result);
+            }
+        }
+        result
+    }
+
+    /// Returns the state to be restored if reading the next event fails.
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            common: self.common.clone(),
+            state: match self.state {
+                ParserState::Magic => StateCheckpoint::Magic,
+                ParserState::Binary(ref parser) => StateCheckpoint::Binary(parser.checkpoint()),
+                ParserState::Ascii(ref parser) => StateCheckpoint::Ascii(parser.checkpoint()),
+            },
+            num_nodes: self.num_nodes,
+            after_start_node: self.after_start_node,
+            document_ended: self.document_ended,
+        }
+    }
+
+    /// Restores the state at the checkpoint.
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.common = checkpoint.common;
+        self.num_nodes = checkpoint.num_nodes;
+        self.after_start_node = checkpoint.after_start_node;
+        self.document_ended = checkpoint.document_ended;
+        match (&mut self.state, checkpoint.state) {
+            (ParserState::Binary(ref mut parser), StateCheckpoint::Binary(checkpoint)) => {
+                parser.restore(checkpoint)
+            }
+            (ParserState::Ascii(ref mut parser), StateCheckpoint::Ascii(checkpoint)) => {
+                parser.restore(checkpoint, &mut self.common)
+            }
+            // The sub parser is replaced only when an event is read successfully.
+            _ => {}
+        }
+    }
+
+    /// Get next `FbxEvent` from sub parsers.
+    fn next_event<R: BufRead>(&mut self, reader: &mut R) -> Result<FbxEvent> {
+        if self.document_ended {
+            return self.next_document(reader);
+        }
         let result;
         loop {
             // Parsing is not finished, call sub parser.
             let r = match self.state {
                 ParserState::Magic => self.magic_next(reader),
-                ParserState::Binary(ref mut parser) => parser.next(reader, &mut self.common),
-                ParserState::Ascii(ref mut parser) => parser.next(reader, &mut self.common),
+                ParserState::Binary(ref mut parser) => parser.next(
+                    reader,
+                    &mut self.common,
+                    &self.config,
+                    self.node_filter.as_ref(),
+                ),
+                ParserState::Ascii(ref mut parser) => {
+                    parser.next(reader, &mut self.common, &self.config)
+                }
             };
             // Break only when `ignore_comments` option is disabled or got non-comment event.
             if self.config.ignore_comments {
@@ -80,69 +309,360 @@ impl Parser {
                 break;
             }
         }
-        // If parsing is finished, set `final_result`.
+        self.after_start_node = matches!(result, Ok(FbxEvent::StartNode { .. }));
+        if let Ok(FbxEvent::StartNode { .. }) = result {
+            self.num_nodes += 1;
+            if let Some(max_nodes) = self.config.max_nodes {
+                if self.num_nodes > max_nodes {
+                    return Err(Error::new(
+                        self.common.pos,
+                        ErrorKind::NodeLimitExceeded(max_nodes),
+                    ));
+                }
+            }
+        }
+        result
+    }
+
+    /// Skips the rest of the node started by the last event, including its children, as if its
+    /// `EndNode` is read.
+    ///
+    /// For Binary FBX, children are skipped without parsing.
+    pub fn skip_node<R: BufRead>(&mut self, reader: &mut R) -> Result<()> {
+        if let ParserState::Binary(_) = self.state {
+            return self.skip_binary_node(reader);
+        }
+        debug_assert!(
+            self.after_start_node,
+            "should be called right after `StartNode`"
+        );
+        if let Some(Err(ref err)) = self.common.final_result {
+            return Err(err.clone());
+        }
+        self.after_start_node = false;
+        let mut depth = 0;
+        loop {
+            match self.next(reader)? {
+                FbxEvent::StartNode { .. } => depth += 1,
+                FbxEvent::EndNode if depth == 0 => return Ok(()),
+                FbxEvent::EndNode => depth -= 1,
+                FbxEvent::EndFbx => return Ok(()),
+                FbxEvent::StartFbx(_) | FbxEvent::Comment(_) => {}
+            }
+        }
+    }
+
+    /// Skips the node started by the last event of Binary FBX, without parsing its children.
+    ///
+    /// This needs only `Read`, so that the skipped bytes can be captured by the caller.
+    pub fn skip_binary_node<R: Read>(&mut self, reader: &mut R) -> Result<()> {
+        debug_assert!(
+            self.after_start_node,
+            "should be called right after `StartNode`"
+        );
+        if let Some(Err(ref err)) = self.common.final_result {
+            return Err(err.clone());
+        }
+        self.after_start_node = false;
+        let parser = match self.state {
+            ParserState::Binary(ref mut parser) => parser,
+            _ => panic!("should be called for Binary FBX"),
+        };
+        let mut reader = CountingReader::new(reader);
+        let result = parser.skip_node(&mut reader, &mut self.common);
+        self.stream_pos += reader.count;
+        if let Err(ref err) = result {
+            self.common.final_result = Some(Err(err.clone()));
+        }
+        result
+    }
+
+    /// Skips the broken part of the data after an error, so that parsing can continue.
+    ///
+    /// See [`EventReader::recover`](../struct.EventReader.html#method.recover).
+    pub fn recover<R: Read>(&mut self, reader: &mut R) -> bool {
+        match self.common.final_result {
+            Some(Err(ref err)) if is_recoverable(err) => {}
+            _ => return false,
+        }
+        if self.common.lookahead.is_some() {
+            return false;
+        }
+        let parser = match self.state {
+            ParserState::Binary(ref mut parser) => parser,
+            _ => return false,
+        };
+        let mut reader = CountingReader::new(reader);
+        let result = parser.recover(&mut reader, &mut self.common, self.stream_pos);
+        self.stream_pos += reader.count;
         match result {
+            Ok(()) => {
+                debug!(
+                    "recovered from the error, resuming at pos={}",
+                    self.common.pos
+                );
+                self.common.final_result = None;
+                self.after_start_node = false;
+                true
+            }
+            Err(err) => {
+                debug!("failed to recover from the error: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Returns whether parsing is finished and the reader emits no more new events.
+    pub fn is_finished(&self) -> bool {
+        self.common.final_result.is_some()
+    }
+
+    /// Sets `final_result` if parsing is finished.
+    fn latch_final_result(&mut self, result: &Result<FbxEvent>) {
+        match *result {
+            Ok(FbxEvent::EndFbx)
+                if self.config.allow_multiple_documents && !self.document_ended =>
+            {
+                self.document_ended = true;
+            }
             Ok(FbxEvent::EndFbx) | Err(_) => {
                 self.common.final_result = Some(result.clone());
             }
             _ => {}
         }
-        result
+    }
+
+    /// Starts the next document after `EndFbx`, or returns `EndFbx` again at the end of the
+    /// stream.
+    fn next_document<R: BufRead>(&mut self, reader: &mut R) -> Result<FbxEvent> {
+        if self.common.lookahead.is_none() {
+            match read_byte(reader, &mut self.common.pos)? {
+                Some(byte) => {
+                    self.common.pos -= 1;
+                    self.common.lookahead = Some(byte);
+                }
+                None => return Ok(FbxEvent::EndFbx),
+            }
+        }
+        let event = self.magic_next(reader)?;
+        self.document_ended = false;
+        Ok(event)
     }
 
     /// Read magic binary and update parser state if success.
     fn magic_next<R: Read>(&mut self, reader: &mut R) -> Result<FbxEvent> {
-        // 20 is the length of `b"Kaydara FBX Binary  "`.
-        let mut first_line_bytes = Vec::with_capacity(20);
-        // First, read the first line.
-        // Read the first line manually.
-        let magic_end_byte;
-        loop {
-            let c = try_read_le_u8!(self.common.pos, reader);
-            if (c == 0) || (c == (b'\n')) {
-                magic_end_byte = c;
-                break;
+        let doc_start = self.common.pos;
+        let magic = match self.common.lookahead.take() {
+            Some(byte) => {
+                let head = [byte];
+                read_magic(&mut (&head[..]).chain(reader), &mut self.common.pos, None)?
             }
-            first_line_bytes.push(c);
-        }
-        // In Binary FBX, magic binary is `"Kaydara FBX Binary  "`,
-        // and in ASCII FBX, there is no magic and it should be treated as normal line.
-        if magic_end_byte == 0 {
-            // Binary FBX?
-            if first_line_bytes == b"Kaydara FBX Binary  " {
-                // Binary FBX!
-                // "unknown but all observed files show these bytes",
-                // see https://code.blender.org/2013/08/fbx-binary-file-format-specification/ .
-                {
-                    let bytes = try_read_exact!(self.common.pos, reader, 2u64);
-                    if bytes != vec![0x1A, 0x00] {
-                        warn!(
-                            "expected [0x1A, 0x00] right after magic, but got {:?}",
-                            bytes
-                        );
-                    }
-                }
-                // Read FBX version.
-                let version = try_read_le_u32!(self.common.pos, reader);
-                debug!("magic binary read, Binary FBX (version={})", version);
-                self.state = ParserState::Binary(BinaryParser::new(version));
+            None => read_magic(reader, &mut self.common.pos, None)?,
+        };
+        match magic {
+            Magic::Binary(version) => {
+                self.state = ParserState::Binary(Box::new(BinaryParser::new(version, doc_start)));
                 Ok(FbxEvent::StartFbx(FbxFormatType::Binary(version)))
-            } else {
-                Err(Error::new(self.common.pos, ErrorKind::InvalidMagic))
             }
-        } else {
-            assert_eq!(magic_end_byte, (b'\n'));
-            // Maybe ASCII FBX
-            let mut buffer;
-            if first_line_bytes[0] != (b';') {
-                // The line is not comment, so the parser should remember it to use next time.
-                buffer = try_with_pos!(self.common.pos, String::from_utf8(first_line_bytes));
-                buffer.push('\n');
+            Magic::Ascii(first_line) => {
+                // The line should be parsed by the ASCII FBX parser, so the parser should
+                // remember it.
+                self.state = ParserState::Ascii(AsciiParser::new(first_line));
+                Ok(FbxEvent::StartFbx(FbxFormatType::Ascii))
+            }
+        }
+    }
+}
+
+/// Returns whether the error is caused by broken data, which can be skipped by
+/// [`Parser::recover`](struct.Parser.html#method.recover).
+///
+/// Errors of the stream (such as I/O errors and unexpected EOF) and exceeded limits are not
+/// recoverable.
+fn is_recoverable(err: &Error) -> bool {
+    matches!(
+        *err.kind(),
+        ErrorKind::Utf8Error(_)
+            | ErrorKind::DataError(_)
+            | ErrorKind::Decompression(_)
+            | ErrorKind::UnexpectedValue(_)
+            | ErrorKind::Unimplemented(_)
+    )
+}
+
+/// A reader which counts the bytes read from the inner reader.
+struct CountingReader<'a, R> {
+    inner: &'a mut R,
+    /// Number of bytes read.
+    count: u64,
+}
+
+impl<'a, R: Read> CountingReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        CountingReader { inner, count: 0 }
+    }
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Reads a byte from the stream.
+///
+/// Returns `Ok(None)` at the end of the stream.
+fn read_byte<R: Read>(reader: &mut R, pos: &mut u64) -> Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {
+                *pos += 1;
+                return Ok(Some(byte[0]));
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::new(*pos, err)),
+        }
+    }
+}
+
+/// Result of reading magic.
+enum Magic {
+    /// Binary FBX with the version.
+    Binary(u32),
+    /// Maybe ASCII FBX, with the first line (including the trailing newline).
+    Ascii(String),
+}
+
+/// Reads magic binary of Binary FBX, or the first line of ASCII FBX.
+///
+/// If `max_line_len` is given, `ErrorKind::InvalidMagic` is returned when that many bytes are
+/// read without finding the end of the first line.
+fn read_magic<R: Read>(
+    reader: &mut R,
+    pos: &mut u64,
+    max_line_len: Option<usize>,
+) -> Result<Magic> {
+    // 20 is the length of `b"Kaydara FBX Binary  "`.
+    let mut first_line_bytes = Vec::with_capacity(20);
+    // First, read the first line.
+    // Read the first line manually.
+    let magic_end_byte;
+    loop {
+        let c = {
+            use byteorder::ReadBytesExt;
+            match reader.read_u8() {
+                Ok(c) => c,
+                // Distinguish empty input from truncated data.
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof && *pos == 0 => {
+                    return Err(Error::new(0, ErrorKind::EmptyInput));
+                }
+                Err(err) => return Err(Error::new(*pos, err)),
+            }
+        };
+        *pos += 1;
+        if (c == 0) || (c == (b'\n')) {
+            magic_end_byte = c;
+            break;
+        }
+        first_line_bytes.push(c);
+        if let Some(max_line_len) = max_line_len {
+            if first_line_bytes.len() >= max_line_len {
+                return Err(Error::new(*pos, ErrorKind::InvalidMagic));
+            }
+        }
+    }
+    // In Binary FBX, magic binary is `"Kaydara FBX Binary  "`,
+    // and in ASCII FBX, there is no magic and it should be treated as normal line.
+    if magic_end_byte == 0 {
+        // Binary FBX?
+        if first_line_bytes == b"Kaydara FBX Binary  " {
+            // Binary FBX!
+            // "unknown but all observed files show these bytes",
+            // see https://code.blender.org/2013/08/fbx-binary-file-format-specification/ .
+            // Some exporters omit these bytes and write FBX version right after the magic.
+            let bytes = try_read_exact!(*pos, reader, 2u64);
+            let version = if bytes == [0x1A, 0x00] {
+                try_read_le_u32!(*pos, reader)
             } else {
-                buffer = String::new();
+                let rest = try_read_exact!(*pos, reader, 2u64);
+                let version = u32::from_le_bytes([bytes[0], bytes[1], rest[0], rest[1]]);
+                if (6000..8000).contains(&version) {
+                    debug!("[0x1A, 0x00] right after magic is omitted");
+                    version
+                } else {
+                    warn!(
+                        "expected [0x1A, 0x00] right after magic, but got {:?}",
+                        bytes
+                    );
+                    let upper = try_read_exact!(*pos, reader, 2u64);
+                    u32::from_le_bytes([rest[0], rest[1], upper[0], upper[1]])
+                }
+            };
+            debug!("magic binary read, Binary FBX (version={})", version);
+            // FBX 6.x and 7.x are known to be readable.
+            if !(6000..8000).contains(&version) {
+                warn!(
+                    "Binary FBX of unknown version ({}), parsing may fail",
+                    version
+                );
             }
-            self.state = ParserState::Ascii(AsciiParser::new(buffer));
-            Ok(FbxEvent::StartFbx(FbxFormatType::Ascii))
+            Ok(Magic::Binary(version))
+        } else {
+            Err(Error::new(*pos, ErrorKind::InvalidMagic))
         }
+    } else {
+        assert_eq!(magic_end_byte, (b'\n'));
+        // Maybe ASCII FBX
+        let mut buffer = try_with_pos!(*pos, String::from_utf8(first_line_bytes));
+        buffer.push('\n');
+        Ok(Magic::Ascii(buffer))
+    }
+}
+
+/// Maximum length of the first line read by `detect_format`.
+///
+/// The binary magic (23 bytes) and usual header comments of ASCII FBX fit in this.
+const MAX_DETECT_LINE_LEN: usize = 256;
+
+/// Detects the format of FBX data, reading only the magic binary or the first line.
+///
+/// Returns `Ok(None)` if the data is not FBX, or is too short to be FBX (including an empty
+/// input).
+/// The format is detected in the same way as [`EventReader`](../struct.EventReader.html), so
+/// any data whose first line is valid UTF-8 text is detected as ASCII FBX.
+///
+/// For Binary FBX, this consumes 27 bytes (magic binary and FBX version), or 25 bytes if
+/// `[0x1A, 0x00]` after the magic is omitted.
+/// Otherwise, this consumes bytes until the first `\n` or `\0` (inclusive).
+/// At most 256 bytes are read for the first line, and data without `\n` nor `\0` in them is
+/// not detected as FBX.
+pub fn detect_format<R: Read>(reader: &mut R) -> Result<Option<FbxFormatType>> {
+    let mut pos = 0;
+    match read_magic(reader, &mut pos, Some(MAX_DETECT_LINE_LEN)) {
+        Ok(Magic::Binary(version)) => Ok(Some(FbxFormatType::Binary(version))),
+        Ok(Magic::Ascii(_)) => Ok(Some(FbxFormatType::Ascii)),
+        Err(err) => match *err.kind() {
+            ErrorKind::InvalidMagic
+            | ErrorKind::EmptyInput
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::Utf8Error(_) => Ok(None),
+            ErrorKind::Io(ref io_err) if io_err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            _ => Err(err),
+        },
     }
 }
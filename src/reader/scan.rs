@@ -0,0 +1,64 @@
+//! Contains a scanner which collects statistics of FBX data in one pass.
+
+use crate::common::FbxFormatType;
+use crate::reader::error::Result;
+use crate::reader::{EventReader, FbxEvent};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// Statistics of FBX data, collected by [`scan`](fn.scan.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Format of the FBX data.
+    pub format: Option<FbxFormatType>,
+    /// Total number of nodes (excluding the implicit root node).
+    pub num_nodes: u64,
+    /// Maximum depth of nodes.
+    ///
+    /// Top-level nodes are at depth 1, and this is `0` if there are no nodes.
+    pub max_depth: usize,
+    /// Total number of properties of all nodes.
+    pub num_properties: u64,
+    /// Number of properties for each type code (such as `b'I'` and `b'd'`).
+    ///
+    /// See [`Property::fbx_type_code`](../common/enum.Property.html#method.fbx_type_code).
+    pub property_types: BTreeMap<u8, u64>,
+}
+
+impl ScanReport {
+    /// Reads events until `EndFbx` and collects statistics.
+    ///
+    /// Events already read from the reader are not counted.
+    pub fn read<R: Read>(reader: &mut EventReader<R>) -> Result<Self> {
+        let mut report = ScanReport::default();
+        let mut depth = 0;
+        loop {
+            match reader.next()? {
+                FbxEvent::StartFbx(format) => report.format = Some(format),
+                FbxEvent::EndFbx => return Ok(report),
+                FbxEvent::StartNode { properties, .. } => {
+                    depth += 1;
+                    report.num_nodes += 1;
+                    report.max_depth = report.max_depth.max(depth);
+                    report.num_properties += properties.len() as u64;
+                    for prop in &properties {
+                        *report
+                            .property_types
+                            .entry(prop.fbx_type_code())
+                            .or_insert(0) += 1;
+                    }
+                }
+                FbxEvent::EndNode => depth -= 1,
+                FbxEvent::Comment(_) => {}
+            }
+        }
+    }
+}
+
+/// Reads FBX data sequentially and returns its statistics.
+///
+/// This needs only `Read` (not `Seek`), and does not build a node tree.
+/// To read with custom parser config, use [`ScanReport::read`](struct.ScanReport.html#method.read).
+pub fn scan<R: Read>(reader: R) -> Result<ScanReport> {
+    ScanReport::read(&mut EventReader::new(reader))
+}
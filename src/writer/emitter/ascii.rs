@@ -1,31 +1,74 @@
 //! Contains implementation of ASCII FBX emitter.
-use crate::common::Property;
+use crate::common::{OwnedProperty, Property};
+use crate::reader::{read_array_into, ArrayElement, ArrayHeader};
 use crate::writer::error::{Error, Result};
+use crate::writer::{EmitterConfig, LineEnding};
 use base64;
-use log::{error, warn};
+use log::error;
 use std::io::Write;
 
-fn indent<W: Write>(sink: &mut W, depth: usize) -> Result<()> {
-    for _ in 0..depth {
-        sink.write_all(b"\t")?;
+/// Layout options of ASCII FBX output.
+#[derive(Debug, Clone)]
+struct Style {
+    /// Indentation string of a nesting level.
+    indent: &'static str,
+    /// Line ending.
+    newline: &'static [u8],
+}
+
+impl Style {
+    /// Creates a style from the emitter config.
+    fn new(config: &EmitterConfig) -> Self {
+        Style {
+            indent: config.ascii_indent,
+            newline: match config.line_ending {
+                LineEnding::Lf => b"\n",
+                LineEnding::CrLf => b"\r\n",
+            },
+        }
+    }
+
+    /// Checks whether the output with this style is parsable.
+    fn validate(&self) -> Result<()> {
+        if !self.indent.chars().all(|c| c == ' ' || c == '\t') {
+            return Err(Error::InvalidOption(format!(
+                "ASCII indentation should consist of spaces and tabs, but got {:?}",
+                self.indent
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes a line ending.
+    fn newline<W: Write>(&self, sink: &mut W) -> Result<()> {
+        sink.write_all(self.newline)?;
+        Ok(())
+    }
+
+    /// Writes indentation for the given depth.
+    fn indent<W: Write>(&self, sink: &mut W, depth: usize) -> Result<()> {
+        for _ in 0..depth {
+            sink.write_all(self.indent.as_bytes())?;
+        }
+        Ok(())
     }
-    Ok(())
 }
 
 fn print_property<W: Write>(
     sink: &mut W,
     property: &Property<'_>,
     prop_depth: usize,
+    style: &Style,
 ) -> Result<()> {
     assert!(prop_depth > 0);
 
-    // TODO: I've never seen vector of booleans (in binary or ascii FBX)... How should it be?
     // TODO: How will it be when other properties follows a property of array value?
     // TODO: Implement folding of large array.
     macro_rules! generic_vec_print {
         ($vec:ident) => {{
-            sink.write_fmt(format_args!("*{} {{\n", $vec.len()))?;
-            indent(sink, prop_depth)?;
+            sink.write_fmt(format_args!("*{} {{", $vec.len()))?;
+            style.newline(sink)?;
+            style.indent(sink, prop_depth)?;
             sink.write_all(b"a: ")?;
             let mut iter = $vec.iter();
             if let Some(&v) = iter.next() {
@@ -34,8 +77,8 @@ fn print_property<W: Write>(
             for &v in iter {
                 sink.write_fmt(format_args!(",{}", v))?;
             }
-            sink.write_all(b"\n")?;
-            indent(sink, prop_depth - 1)?;
+            style.newline(sink)?;
+            style.indent(sink, prop_depth - 1)?;
             sink.write_all(b"}")?;
         }};
     }
@@ -64,9 +107,12 @@ fn print_property<W: Write>(
             sink.write_fmt(format_args!("{}", v))?;
         }
         Property::VecBool(vec) => {
-            warn!("ASCII representation of vector of boolean values may be wrong.");
-            sink.write_fmt(format_args!("*{} {{\n", vec.len()))?;
-            indent(sink, prop_depth)?;
+            // Vectors of booleans are rarely seen in real FBX data.
+            // They are written with the same tokens as boolean values (such as `a: Y,T,Y`), and
+            // the parser reads arrays consisting only of `Y` and `T` as vectors of booleans.
+            sink.write_fmt(format_args!("*{} {{", vec.len()))?;
+            style.newline(sink)?;
+            style.indent(sink, prop_depth)?;
             sink.write_all(b"a: ")?;
             let mut iter = vec.iter();
             if let Some(&v) = iter.next() {
@@ -75,8 +121,8 @@ fn print_property<W: Write>(
             for &v in iter {
                 sink.write_all(if v { b",Y" } else { b",T" })?;
             }
-            sink.write_all(b"\n")?;
-            indent(sink, prop_depth - 1)?;
+            style.newline(sink)?;
+            style.indent(sink, prop_depth - 1)?;
             sink.write_all(b"}")?;
         }
         Property::VecI32(vec) => {
@@ -92,24 +138,20 @@ fn print_property<W: Write>(
             generic_vec_print!(vec);
         }
         Property::String(v) => {
-            sink.write_all(b"\"")?;
-            for c in v.chars() {
-                match c {
-                    '"' => {
-                        sink.write_all(b"&quot;")?;
-                    }
-                    '\n' => {
-                        sink.write_all(b"&lf;")?;
-                    }
-                    '\r' => {
-                        sink.write_all(b"&cr;")?;
-                    }
-                    _ => {
-                        sink.write_fmt(format_args!("{}", c))?;
-                    }
-                }
-            }
-            sink.write_all(b"\"")?;
+            emit_string(sink, v)?;
+        }
+        Property::RawString(v) => {
+            // ASCII FBX is a text format, so invalid UTF-8 sequences cannot be kept.
+            emit_string(sink, &String::from_utf8_lossy(v))?;
+        }
+        Property::CompressedArray {
+            type_code,
+            array_length,
+            data,
+        } => {
+            // ASCII FBX has no compressed representation, so decode the elements.
+            let decoded = decode_compressed_array(type_code, array_length, data)?;
+            print_property(sink, &decoded.borrow(), prop_depth, style)?;
         }
         Property::Binary(v) => {
             // TODO: Implement folding of long line.
@@ -120,39 +162,99 @@ fn print_property<W: Write>(
     Ok(())
 }
 
+/// Decodes elements of a compressed array.
+fn decode_compressed_array(type_code: u8, array_length: u32, data: &[u8]) -> Result<OwnedProperty> {
+    fn decode<T: ArrayElement>(header: &ArrayHeader, data: &[u8]) -> Result<Vec<T>> {
+        let mut vec = Vec::new();
+        read_array_into(&mut &data[..], header, &mut vec)?;
+        Ok(vec)
+    }
+
+    let header = ArrayHeader {
+        type_code,
+        array_length,
+        encoding: 1,
+        compressed_length: data.len() as u32,
+    };
+    Ok(match type_code {
+        b'b' => OwnedProperty::VecBool(decode(&header, data)?),
+        b'i' => OwnedProperty::VecI32(decode(&header, data)?),
+        b'l' => OwnedProperty::VecI64(decode(&header, data)?),
+        b'f' => OwnedProperty::VecF32(decode(&header, data)?),
+        b'd' => OwnedProperty::VecF64(decode(&header, data)?),
+        _ => return Err(Error::UnwritableEvent),
+    })
+}
+
+/// Writes a quoted string property value with escapes.
+fn emit_string<W: Write>(sink: &mut W, v: &str) -> Result<()> {
+    sink.write_all(b"\"")?;
+    for c in v.chars() {
+        match c {
+            '"' => {
+                sink.write_all(b"&quot;")?;
+            }
+            '\n' => {
+                sink.write_all(b"&lf;")?;
+            }
+            '\r' => {
+                sink.write_all(b"&cr;")?;
+            }
+            _ => {
+                sink.write_fmt(format_args!("{}", c))?;
+            }
+        }
+    }
+    sink.write_all(b"\"")?;
+    Ok(())
+}
+
 /// A writer for ASCII FBX.
 #[derive(Debug, Clone)]
 pub struct AsciiEmitter {
+    style: Style,
     prop_child_existence: Vec<(bool, bool)>,
+    /// Inline comments to be written at the end of the line of the last started node.
+    ///
+    /// The line is not ended until the next child node or the end of the node, because an
+    /// opening brace might follow the properties.
+    pending_inline_comment: String,
 }
 
 impl AsciiEmitter {
     /// Constructs ASCII FBX writer.
-    pub fn new() -> Self {
+    pub fn new(config: &EmitterConfig) -> Self {
         AsciiEmitter {
+            style: Style::new(config),
             prop_child_existence: vec![],
+            pending_inline_comment: String::new(),
         }
     }
 
     pub fn emit_start_fbx<W: Write>(&mut self, sink: &mut W, ver: u32) -> Result<()> {
-        if (ver < 7000) || (ver >= 8000) {
+        if !(7000..8000).contains(&ver) {
             error!("Unsupported version: {}", ver);
             return Err(Error::UnsupportedFbxVersion(ver));
         }
+        self.style.validate()?;
         {
             let (major, minor) = (ver / 1000, ver % 1000);
             let (minor, revision) = (minor / 100, minor % 100);
             // Write magic for ASCII FBX.
             sink.write_fmt(format_args!(
-                "; FBX {}.{}.{} project file\n",
+                "; FBX {}.{}.{} project file",
                 major, minor, revision
             ))?;
+            self.style.newline(sink)?;
         }
 
         Ok(())
     }
 
     pub fn emit_end_fbx<W: Write>(&mut self, _sink: &mut W) -> Result<()> {
+        if !self.prop_child_existence.is_empty() {
+            return Err(Error::UnclosedNodes(self.prop_child_existence.len()));
+        }
         Ok(())
     }
 
@@ -166,11 +268,12 @@ impl AsciiEmitter {
             // Print brace for *parent node*, if the current node is the first child.
             // (i.e. `child_exist` of parent is `false`.)
             if !child_exist {
-                sink.write_all(b" {\n")?;
+                sink.write_all(b" {")?;
+                self.end_line(sink)?;
             }
             self.prop_child_existence.push((prop_exist, true));
         }
-        indent(sink, self.prop_child_existence.len())?;
+        self.style.indent(sink, self.prop_child_existence.len())?;
         self.prop_child_existence
             .push((!properties.is_empty(), false));
         sink.write_fmt(format_args!("{}: ", name))?;
@@ -178,36 +281,64 @@ impl AsciiEmitter {
         let prop_depth = self.prop_child_existence.len();
         let mut prop_iter = properties.iter();
         if let Some(prop) = prop_iter.next() {
-            print_property(sink, prop, prop_depth)?;
+            print_property(sink, prop, prop_depth, &self.style)?;
         }
         for prop in prop_iter {
             sink.write_all(b", ")?;
-            print_property(sink, prop, prop_depth)?;
+            print_property(sink, prop, prop_depth, &self.style)?;
         }
 
         Ok(())
     }
 
     pub fn emit_end_node<W: Write>(&mut self, sink: &mut W) -> Result<()> {
-        let (prop_exist, child_exist) = self.prop_child_existence.pop().unwrap();
+        let (prop_exist, child_exist) =
+            self.prop_child_existence.pop().ok_or(Error::ExtraEndNode)?;
         if !prop_exist || child_exist {
             if !prop_exist && !child_exist {
-                sink.write_all(b" {\n")?;
+                sink.write_all(b" {")?;
+                self.end_line(sink)?;
             }
-            indent(sink, self.prop_child_existence.len())?;
-            sink.write_all(b"}\n")?;
+            self.style.indent(sink, self.prop_child_existence.len())?;
+            sink.write_all(b"}")?;
+            self.style.newline(sink)?;
         } else {
-            sink.write_all(b"\n")?;
+            self.end_line(sink)?;
         }
 
         Ok(())
     }
 
+    /// Ends the line of the last started node, with pending inline comments.
+    fn end_line<W: Write>(&mut self, sink: &mut W) -> Result<()> {
+        sink.write_all(self.pending_inline_comment.as_bytes())?;
+        self.pending_inline_comment.clear();
+        self.style.newline(sink)?;
+        Ok(())
+    }
+
+    pub fn emit_inline_comment<W: Write>(&mut self, _sink: &mut W, comment: &str) -> Result<()> {
+        match self.prop_child_existence.last() {
+            Some(&(_, false)) => {}
+            _ => {
+                error!("Inline comment should be written right after the start of a node");
+                return Err(Error::UnwritableEvent);
+            }
+        }
+        if comment.contains(['\r', '\n']) {
+            error!("Inline comment cannot contain newlines");
+            return Err(Error::UnwritableEvent);
+        }
+        self.pending_inline_comment.push_str(" ;");
+        self.pending_inline_comment.push_str(comment);
+        Ok(())
+    }
+
     pub fn emit_comment<W: Write>(&mut self, sink: &mut W, comment: &str) -> Result<()> {
         for line in comment.lines() {
-            indent(sink, self.prop_child_existence.len())?;
+            self.style.indent(sink, self.prop_child_existence.len())?;
             sink.write_all(line.as_bytes())?;
-            sink.write_all(b"\n")?;
+            self.style.newline(sink)?;
         }
 
         Ok(())
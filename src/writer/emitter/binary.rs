@@ -3,19 +3,191 @@
 use byteorder;
 use flate2;
 
-use self::byteorder::{LittleEndian, WriteBytesExt};
-use crate::common::Property;
+use self::byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use crate::common::{Property, RawNode, FOOTER_RESERVED_LEN, NONSTANDARD_ZSTD_ARRAY_ENCODING};
 use crate::writer::error::{Error, Result};
+use crate::writer::{EmitterConfig, NodeWrittenCallback};
 use log::error;
+use std::fmt;
 use std::io::{Seek, SeekFrom, Write};
 
+/// Checks that the value fits in a 32 bit field, and returns it as `u32`.
+///
+/// Array lengths, array byte sizes, and string and binary lengths of properties are 32 bit
+/// integers in all FBX versions (including FBX 7.5 and later, which use 64 bit integers for node
+/// record headers).
+fn check_u32_field(value: u64, what: &str) -> Result<u32> {
+    if value > u64::from(u32::MAX) {
+        return Err(Error::DataTooLarge(format!(
+            "{} ({}) exceeds the limit ({})",
+            what,
+            value,
+            u32::MAX
+        )));
+    }
+    Ok(value as u32)
+}
+
+/// Returns the error for `nonstandard_zstd_arrays` enabled without zstd support.
+#[cfg(not(feature = "zstd"))]
+fn zstd_disabled() -> Error {
+    Error::InvalidOption("`nonstandard_zstd_arrays` requires `zstd` feature".to_string())
+}
+
+/// Returns the encoding of array properties to write.
+fn array_encoding(config: &EmitterConfig) -> Result<u32> {
+    if !config.compress_arrays {
+        return Ok(0);
+    }
+    if config.nonstandard_zstd_arrays {
+        #[cfg(feature = "zstd")]
+        return Ok(NONSTANDARD_ZSTD_ARRAY_ENCODING);
+        #[cfg(not(feature = "zstd"))]
+        return Err(zstd_disabled());
+    }
+    Ok(1)
+}
+
+/// Writes array elements with zstd compression.
+#[cfg(feature = "zstd")]
+macro_rules! write_zstd_array {
+    ($sink:ident, |$writer:ident, $v:ident| $write:expr, $vec:ident) => {{
+        let mut $writer = zstd::stream::write::Encoder::new($sink.by_ref(), 0)?;
+        for &$v in $vec {
+            $write?;
+        }
+        $writer.finish()?;
+    }};
+}
+
+/// Fails because zstd support is disabled.
+#[cfg(not(feature = "zstd"))]
+macro_rules! write_zstd_array {
+    ($($tt:tt)*) => {
+        return Err(zstd_disabled())
+    };
+}
+
+/// Size of compressed data buffered before it is written to the sink, for streamed arrays.
+const ARRAY_CHUNK_LEN: usize = 64 * 1024;
+
+/// Encoder of an array property streamed by `push_array_element`.
+enum ArrayEncoder {
+    /// Elements are written to the sink as is.
+    Plain,
+    /// Elements are compressed by zlib, and the compressed data is buffered.
+    Zlib(flate2::write::ZlibEncoder<Vec<u8>>),
+    /// Elements are compressed by zstd, and the compressed data is buffered.
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl ArrayEncoder {
+    /// Creates an encoder for the given array encoding.
+    fn new(encoding: u32) -> Result<Self> {
+        Ok(match encoding {
+            0 => ArrayEncoder::Plain,
+            #[cfg(feature = "zstd")]
+            NONSTANDARD_ZSTD_ARRAY_ENCODING => {
+                ArrayEncoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), 0)?)
+            }
+            _ => ArrayEncoder::Zlib(flate2::write::ZlibEncoder::new(
+                Vec::new(),
+                flate2::Compression::fast(),
+            )),
+        })
+    }
+
+    /// Encodes the bytes of an element, and writes the encoded data to the sink if enough data
+    /// is buffered.
+    fn write<W: Write>(&mut self, sink: &mut W, bytes: &[u8]) -> Result<()> {
+        let buffer = match *self {
+            ArrayEncoder::Plain => {
+                sink.write_all(bytes)?;
+                return Ok(());
+            }
+            ArrayEncoder::Zlib(ref mut encoder) => {
+                encoder.write_all(bytes)?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "zstd")]
+            ArrayEncoder::Zstd(ref mut encoder) => {
+                encoder.write_all(bytes)?;
+                encoder.get_mut()
+            }
+        };
+        if buffer.len() >= ARRAY_CHUNK_LEN {
+            sink.write_all(buffer)?;
+            buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// Finishes encoding and writes the rest of the encoded data to the sink.
+    fn finish<W: Write>(self, sink: &mut W) -> Result<()> {
+        let buffer = match self {
+            ArrayEncoder::Plain => return Ok(()),
+            ArrayEncoder::Zlib(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            ArrayEncoder::Zstd(encoder) => encoder.finish()?,
+        };
+        sink.write_all(&buffer)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ArrayEncoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ArrayEncoder::Plain => f.write_str("Plain"),
+            ArrayEncoder::Zlib(_) => f.write_str("Zlib"),
+            #[cfg(feature = "zstd")]
+            ArrayEncoder::Zstd(_) => f.write_str("Zstd"),
+        }
+    }
+}
+
+/// An array property being written element by element.
+#[derive(Debug)]
+struct OpenArray {
+    /// Type code of the array.
+    type_code: u8,
+    /// Position of `array_length` field of the property array header.
+    header_pos: u64,
+    /// Number of elements written so far.
+    array_length: u64,
+    encoder: ArrayEncoder,
+}
+
+/// Positions and values of the property list fields of the last started node.
+///
+/// These are necessary to append properties to the node after its `StartNode` is emitted.
+#[derive(Debug, Clone, Copy)]
+struct PropertyList {
+    /// Position of `num_properties` field of the node record header.
+    num_properties_pos: u64,
+    num_properties: u64,
+    property_list_len: u64,
+}
+
+/// Maximum byte length of a node name.
+///
+/// `name_len` field of a node record header is an 8 bit integer, so longer names cannot be
+/// represented.
+const MAX_NODE_NAME_LEN: usize = u8::MAX as usize;
+
 /// A writer for Binary FBX.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct BinaryEmitter {
     version: u32,
-    pos: u64,
     end_offset_pos_stack: Vec<u64>,
     null_record_necessities: Vec<bool>,
+    /// Names of the currently open nodes.
+    name_stack: Vec<String>,
+    /// Property list of the last started node, if properties can still be appended to it.
+    appendable_properties: Option<PropertyList>,
+    /// Array property being written element by element.
+    open_array: Option<OpenArray>,
 }
 
 impl BinaryEmitter {
@@ -23,17 +195,41 @@ impl BinaryEmitter {
     pub fn new(version: u32) -> Self {
         BinaryEmitter {
             version,
-            pos: 0,
             end_offset_pos_stack: vec![],
             null_record_necessities: vec![],
+            name_stack: vec![],
+            appendable_properties: None,
+            open_array: None,
         }
     }
 
-    pub fn emit_start_fbx<W: Write + Seek>(&mut self, sink: &mut W, ver: u32) -> Result<()> {
-        if (ver < 7000) || (ver >= 8000) {
+    /// Returns the FBX version being written.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Checks whether the given FBX version is supported.
+    pub fn check_version(ver: u32) -> Result<()> {
+        if !(7000..8000).contains(&ver) {
             error!("Unsupported version: {}", ver);
             return Err(Error::UnsupportedFbxVersion(ver));
         }
+        Ok(())
+    }
+
+    /// Returns byte size of a null record (node record header filled with zero).
+    pub fn null_record_len(&self) -> u64 {
+        if self.version < 7500 {
+            // 4+4+4+1.
+            13
+        } else {
+            // 8+8+8+1.
+            25
+        }
+    }
+
+    pub fn emit_start_fbx<W: Write + Seek>(&mut self, sink: &mut W, ver: u32) -> Result<()> {
+        Self::check_version(ver)?;
         // Write magic binary for Binary FBX.
         sink.write_all(b"Kaydara FBX Binary  \x00")?;
         // Meaning is unknown, but value seems to be always `[0x1A, 0x00]`.
@@ -44,14 +240,25 @@ impl BinaryEmitter {
         Ok(())
     }
 
-    pub fn emit_end_fbx<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
-        // Write null record header.
-        if self.version < 7500 {
-            // 13: size of a node record header (4+4+4+1).
-            sink.write_all(&[0; 13])?;
-        } else {
-            // 25: size of a node record header (8+8+8+1).
-            sink.write_all(&[0; 25])?;
+    pub fn emit_end_fbx<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        config: &EmitterConfig,
+    ) -> Result<()> {
+        self.check_no_open_array()?;
+        if !self.null_record_necessities.is_empty() {
+            return Err(Error::UnclosedNodes(self.null_record_necessities.len()));
+        }
+        // Write null record header of the implicit root node.
+        // Without it, the footer terminates the root node implicitly.
+        if config.root_null_record {
+            if self.version < 7500 {
+                // 13: size of a node record header (4+4+4+1).
+                sink.write_all(&[0; 13])?;
+            } else {
+                // 25: size of a node record header (8+8+8+1).
+                sink.write_all(&[0; 25])?;
+            }
         }
 
         // Write footer.
@@ -60,24 +267,29 @@ impl BinaryEmitter {
         // NOTE: This footer is `fa bc ax 0x dx cx dx 6x bx 7x fx 8x 1x fx 2x 7x`,
         //       but detail is unknown.
         sink.write_all(&[
-            0xfa as u8, 0xbc, 0xaf, 0x0f, 0xdf, 0xcf, 0xdf, 0x6f, 0xbf, 0x7f, 0xff, 0x8f, 0x1f,
-            0xff, 0x2f, 0x7f,
+            0xfa, 0xbc, 0xaf, 0x0f, 0xdf, 0xcf, 0xdf, 0x6f, 0xbf, 0x7f, 0xff, 0x8f, 0x1f, 0xff,
+            0x2f, 0x7f,
         ])?;
         // Write padding.
-        {
-            let current_off = sink.seek(SeekFrom::Current(0))? & 0x0f;
-            if current_off != 0 {
-                sink.write_all(&(current_off..16).map(|_| 0).collect::<Vec<u8>>())?;
-            }
+        // The rest of the footer is 144 bytes (a multiple of 16), so this makes the file size
+        // aligned. Official exporters write 16 bytes of padding even if already aligned.
+        if config.pad_to_16 {
+            let current_off = sink.stream_position()? & 0x0f;
+            sink.write_all(&[0; 16][current_off as usize..])?;
         }
-        // Write `0u32`, FBX version, and [0; 120].
+        // Write `0u32`, FBX version, and reserved area (usually `[0; 120]`).
         sink.write_all(&[0; 4])?;
         sink.write_u32::<LittleEndian>(self.version)?;
-        sink.write_all(&[0; 120])?;
+        sink.write_all(
+            config
+                .footer_reserved
+                .as_ref()
+                .map_or(&[0; FOOTER_RESERVED_LEN][..], |v| &v[..]),
+        )?;
         // Write unknown but fixed magic.
         sink.write_all(&[
-            0xf8 as u8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c, 0xe3, 0x75,
-            0x8f, 0x29, 0x0b,
+            0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c, 0xe3, 0x75, 0x8f,
+            0x29, 0x0b,
         ])?;
 
         // All done.
@@ -89,23 +301,34 @@ impl BinaryEmitter {
         sink: &mut W,
         name: &str,
         properties: &[Property<'_>],
+        config: &EmitterConfig,
     ) -> Result<()> {
+        if name.len() > MAX_NODE_NAME_LEN {
+            return Err(Error::DataTooLarge(format!(
+                "Node name length ({} bytes) exceeds the limit ({} bytes)",
+                name.len(),
+                MAX_NODE_NAME_LEN
+            )));
+        }
+        self.check_no_open_array()?;
         if let Some(top) = self.null_record_necessities.last_mut() {
             // Parent node requires null record, because it has child node (the current node!).
             *top = true;
         }
         self.null_record_necessities.push(properties.is_empty());
+        self.name_stack.push(name.to_string());
 
         // Write node record header.
         // For detail of node record header, see `reader::parser::binary::NodeRecordHeader` struct.
+        let num_properties_pos;
         let prop_list_len_offset;
         if self.version < 7500 {
             // Write a placeholder for `end_offset` and remember current offset.
-            self.end_offset_pos_stack
-                .push(sink.seek(SeekFrom::Current(0))?);
+            self.end_offset_pos_stack.push(sink.stream_position()?);
             sink.write_u32::<LittleEndian>(0xef_be_ad_de)?;
+            num_properties_pos = sink.stream_position()?;
             // Write `num_properties`.
-            if properties.len() > u32::max_value() as usize {
+            if properties.len() > u32::MAX as usize {
                 return Err(Error::DataTooLarge(format!(
                     "Number of node properties ({}) is too large for FBX {}",
                     properties.len(),
@@ -114,15 +337,15 @@ impl BinaryEmitter {
             }
             sink.write_u32::<LittleEndian>(properties.len() as u32)?;
             // Write a default value of `property_list_len`.
-            prop_list_len_offset = sink.seek(SeekFrom::Current(0))?;
+            prop_list_len_offset = sink.stream_position()?;
             sink.write_u32::<LittleEndian>(0)?;
         } else {
             // Write a placeholder for `end_offset` and remember current offset.
-            self.end_offset_pos_stack
-                .push(sink.seek(SeekFrom::Current(0))?);
+            self.end_offset_pos_stack.push(sink.stream_position()?);
             sink.write_u64::<LittleEndian>(0xef_be_ad_de_ef_be_ad_de)?;
+            num_properties_pos = sink.stream_position()?;
             // Write `num_properties`.
-            if properties.len() > u64::max_value() as usize {
+            if properties.len() > u64::MAX as usize {
                 return Err(Error::DataTooLarge(format!(
                     "Number of node properties ({}) is too large for FBX {}",
                     properties.len(),
@@ -131,7 +354,7 @@ impl BinaryEmitter {
             }
             sink.write_u64::<LittleEndian>(properties.len() as u64)?;
             // Write a default value of `property_list_len`.
-            prop_list_len_offset = sink.seek(SeekFrom::Current(0))?;
+            prop_list_len_offset = sink.stream_position()?;
             sink.write_u64::<LittleEndian>(0)?;
         }
         // Write length of the node name.
@@ -141,108 +364,151 @@ impl BinaryEmitter {
         sink.write_all(name.as_bytes())?;
 
         // Write properties.
+        let mut props_byte_size = 0_u64;
         if !properties.is_empty() {
-            let mut props_byte_size = 0_u64;
             for prop in properties {
                 macro_rules! read_array_value {
-                    ($vec:ident, $type_code:expr, $elem_type_writer:ident) => {{
-                        sink.write_u8($type_code as u8)?;
+                    ($vec:ident, $elem_type_writer:ident) => {{
+                        read_array_value!($vec, |writer, v| {
+                            writer.$elem_type_writer::<LittleEndian>(v)
+                        })
+                    }};
+                    ($vec:ident, |$writer:ident, $v:ident| $write:expr) => {{
+                        let array_length =
+                            check_u32_field($vec.len() as u64, "Number of array elements")?;
+                        // Empty arrays are written as plain data, because compression of no
+                        // data only adds bytes.
+                        let encoding = if array_length == 0 {
+                            0
+                        } else {
+                            array_encoding(config)?
+                        };
 
                         // Write a property array header.
                         // Write array length (element numbers, not byte size).
-                        sink.write_u32::<LittleEndian>($vec.len() as u32)?;
+                        sink.write_u32::<LittleEndian>(array_length)?;
                         // Write encoding.
                         // 0 for plain data, 1 for zlib-compressed data.
-                        sink.write_u32::<LittleEndian>(1)?;
+                        sink.write_u32::<LittleEndian>(encoding)?;
                         // Write a placeholder for byte size of properties.
-                        let byte_size_pos = sink.seek(SeekFrom::Current(0))?;
+                        let byte_size_pos = sink.stream_position()?;
                         sink.write_u32::<LittleEndian>(0)?;
 
-                        let vec_start_pos = sink.seek(SeekFrom::Current(0))?;
-                        {
-                            let mut encoder = flate2::write::ZlibEncoder::new(
+                        let vec_start_pos = sink.stream_position()?;
+                        if encoding == NONSTANDARD_ZSTD_ARRAY_ENCODING {
+                            write_zstd_array!(sink, |$writer, $v| $write, $vec);
+                        } else if encoding == 1 {
+                            let mut $writer = flate2::write::ZlibEncoder::new(
                                 sink.by_ref(),
                                 flate2::Compression::fast(),
                             );
-                            for &v in $vec {
-                                //encoder.write_i32::<LittleEndian>(v)?;
-                                encoder.$elem_type_writer::<LittleEndian>(v)?;
+                            for &$v in $vec {
+                                $write?;
+                            }
+                            $writer.finish()?;
+                        } else {
+                            let $writer = sink.by_ref();
+                            for &$v in $vec {
+                                $write?;
                             }
-                            encoder.finish()?;
                         }
-                        let last_pos = sink.seek(SeekFrom::Current(0))?;
+                        let last_pos = sink.stream_position()?;
 
                         // Update byte size of properties.
-                        let byte_size = last_pos - vec_start_pos;
+                        let byte_size =
+                            check_u32_field(last_pos - vec_start_pos, "Array property size")?;
                         sink.seek(SeekFrom::Start(byte_size_pos))?;
-                        sink.write_u32::<LittleEndian>(byte_size as u32)?;
+                        sink.write_u32::<LittleEndian>(byte_size)?;
                         sink.seek(SeekFrom::Start(last_pos))?;
                         // 12: property array header.
                         12 + byte_size as u64
                     }};
-                };
+                }
+                sink.write_u8(prop.fbx_type_code())?;
                 props_byte_size += 1 + match *prop {
                     Property::Bool(v) => {
-                        sink.write_u8(b'C')?;
                         // `'Y'` is `0x59`,  `'T'` is `0x54`.
-                        sink.write_u8(if v { 'Y' } else { 'T' } as u8)?;
+                        sink.write_u8(if v { b'Y' } else { b'T' })?;
                         1
                     }
                     Property::I16(v) => {
-                        sink.write_u8(b'Y')?;
                         sink.write_i16::<LittleEndian>(v)?;
                         2
                     }
                     Property::I32(v) => {
-                        sink.write_u8(b'I')?;
                         sink.write_i32::<LittleEndian>(v)?;
                         4
                     }
                     Property::I64(v) => {
-                        sink.write_u8(b'L')?;
                         sink.write_i64::<LittleEndian>(v)?;
                         8
                     }
                     Property::F32(v) => {
-                        sink.write_u8(b'F')?;
                         sink.write_f32::<LittleEndian>(v)?;
                         4
                     }
                     Property::F64(v) => {
-                        sink.write_u8(b'D')?;
                         sink.write_f64::<LittleEndian>(v)?;
                         8
                     }
+                    // The reader checks only the LSB of each element, so write `1` or `0` (unlike
+                    // `'Y'` or `'T'` for a single boolean).
                     Property::VecBool(vec) => {
-                        sink.write_u8(b'b')?;
-                        for v in vec.iter().map(|&v| if v { 'Y' } else { 'T' } as u8) {
-                            sink.write_u8(v)?;
-                        }
-                        vec.len() as u64
+                        read_array_value!(vec, |writer, v| writer.write_u8(u8::from(v)))
                     }
-                    Property::VecI32(vec) => read_array_value!(vec, 'i', write_i32),
-                    Property::VecI64(vec) => read_array_value!(vec, 'l', write_i64),
-                    Property::VecF32(vec) => read_array_value!(vec, 'f', write_f32),
-                    Property::VecF64(vec) => read_array_value!(vec, 'd', write_f64),
+                    Property::VecI32(vec) => read_array_value!(vec, write_i32),
+                    Property::VecI64(vec) => read_array_value!(vec, write_i64),
+                    Property::VecF32(vec) => read_array_value!(vec, write_f32),
+                    Property::VecF64(vec) => read_array_value!(vec, write_f64),
                     Property::String(s) => {
-                        sink.write_u8(b'S')?;
-                        sink.write_u32::<LittleEndian>(s.len() as u32)?;
+                        let len = check_u32_field(s.len() as u64, "String property length")?;
+                        sink.write_u32::<LittleEndian>(len)?;
                         sink.write_all(s.as_bytes())?;
                         4 + s.len() as u64
                     }
+                    Property::RawString(b) => {
+                        let len = check_u32_field(b.len() as u64, "String property length")?;
+                        sink.write_u32::<LittleEndian>(len)?;
+                        sink.write_all(b)?;
+                        4 + b.len() as u64
+                    }
+                    Property::CompressedArray {
+                        type_code,
+                        array_length,
+                        data,
+                    } => {
+                        match type_code {
+                            b'b' | b'i' | b'l' | b'f' | b'd' => {}
+                            _ => {
+                                return Err(Error::InvalidArrayProperty(format!(
+                                    "{:?} is not a type code of array properties",
+                                    type_code as char
+                                )))
+                            }
+                        }
+                        let byte_size =
+                            check_u32_field(data.len() as u64, "Compressed array size")?;
+                        sink.write_u32::<LittleEndian>(array_length)?;
+                        // 1 for zlib-compressed data.
+                        sink.write_u32::<LittleEndian>(1)?;
+                        sink.write_u32::<LittleEndian>(byte_size)?;
+                        sink.write_all(data)?;
+                        // 12: property array header.
+                        12 + data.len() as u64
+                    }
                     Property::Binary(b) => {
-                        sink.write_u8(b'R')?;
-                        sink.write_u32::<LittleEndian>(b.len() as u32)?;
+                        let len = check_u32_field(b.len() as u64, "Binary property length")?;
+                        sink.write_u32::<LittleEndian>(len)?;
                         sink.write_all(b)?;
                         4 + b.len() as u64
                     }
                 };
             }
             // Update `property_list_len`
-            let last_pos = sink.seek(SeekFrom::Current(0))?;
+            let last_pos = sink.stream_position()?;
             sink.seek(SeekFrom::Start(prop_list_len_offset))?;
             if self.version < 7500 {
-                if props_byte_size > u64::from(u32::max_value()) {
+                if props_byte_size > u64::from(u32::MAX) {
                     return Err(Error::DataTooLarge(format!(
                         "Properties size ({} bytes) is too large for FBX {}",
                         props_byte_size, self.version
@@ -254,11 +520,22 @@ impl BinaryEmitter {
             }
             sink.seek(SeekFrom::Start(last_pos))?;
         }
+        self.appendable_properties = Some(PropertyList {
+            num_properties_pos,
+            num_properties: properties.len() as u64,
+            property_list_len: props_byte_size,
+        });
 
         Ok(())
     }
 
-    pub fn emit_end_node<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+    pub fn emit_end_node<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        on_node_written: Option<&NodeWrittenCallback>,
+    ) -> Result<()> {
+        self.check_no_open_array()?;
+        self.appendable_properties = None;
         // Write a null record header if necessary.
         if let Some(required) = self.null_record_necessities.pop() {
             if required {
@@ -275,10 +552,12 @@ impl BinaryEmitter {
         }
 
         // Update `end_offset`.
-        let last_pos = sink.seek(SeekFrom::Current(0))?;
-        sink.seek(SeekFrom::Start(self.end_offset_pos_stack.pop().unwrap()))?;
+        let last_pos = sink.stream_position()?;
+        let start_pos = self.end_offset_pos_stack.pop().unwrap();
+        let name = self.name_stack.pop().unwrap();
+        sink.seek(SeekFrom::Start(start_pos))?;
         if self.version < 7500 {
-            if last_pos > u64::from(u32::max_value()) {
+            if last_pos > u64::from(u32::MAX) {
                 return Err(Error::DataTooLarge(format!(
                     "File size (currently {} bytes) is too large for FBX {}",
                     last_pos, self.version
@@ -290,6 +569,280 @@ impl BinaryEmitter {
         }
         sink.seek(SeekFrom::Start(last_pos))?;
 
+        if let Some(callback) = on_node_written {
+            callback(&name, start_pos, last_pos);
+        }
+
+        Ok(())
+    }
+
+    /// Writes a node captured from Binary FBX, with its descendants.
+    ///
+    /// `end_offset` fields in the node are updated for the position in the sink.
+    /// Returns the number of nodes and the number of properties written.
+    pub fn emit_raw_node<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        node: &RawNode,
+        on_node_written: Option<&NodeWrittenCallback>,
+    ) -> Result<(u64, u64)> {
+        self.check_no_open_array()?;
+        if node.version != self.version {
+            return Err(Error::DataError(format!(
+                "Raw node of FBX {} cannot be written to FBX {}",
+                node.version, self.version
+            )));
+        }
+        let start_pos = sink.stream_position()?;
+        let mut bytes = node.bytes.clone();
+        let counts = relocate_raw_node(&mut bytes, self.version, start_pos)?;
+        if let Some(top) = self.null_record_necessities.last_mut() {
+            // Parent node requires null record, because it has child node.
+            *top = true;
+        }
+        self.appendable_properties = None;
+        sink.write_all(&bytes)?;
+
+        if let Some(callback) = on_node_written {
+            let header_len = (self.null_record_len() - 1) as usize;
+            let name_len = usize::from(bytes[header_len]);
+            let name = String::from_utf8_lossy(&bytes[header_len + 1..][..name_len]);
+            callback(&name, start_pos, start_pos + bytes.len() as u64);
+        }
+
+        Ok(counts)
+    }
+
+    /// Returns an error if an array property is being written.
+    fn check_no_open_array(&self) -> Result<()> {
+        match self.open_array {
+            Some(ref array) => Err(Error::InvalidArrayProperty(format!(
+                "array property of type {:?} is not ended",
+                array.type_code as char
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts an array property appended to the last started node.
+    pub fn begin_array_property<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        type_code: u8,
+        config: &EmitterConfig,
+    ) -> Result<()> {
+        self.check_no_open_array()?;
+        match type_code {
+            b'b' | b'i' | b'l' | b'f' | b'd' => {}
+            _ => {
+                return Err(Error::InvalidArrayProperty(format!(
+                    "{:?} is not a type code of array properties",
+                    type_code as char
+                )))
+            }
+        }
+        if self.appendable_properties.is_none() {
+            return Err(Error::InvalidArrayProperty(
+                "properties can be appended only to the last started node before its children"
+                    .to_string(),
+            ));
+        }
+        let encoding = array_encoding(config)?;
+        let encoder = ArrayEncoder::new(encoding)?;
+        sink.write_u8(type_code)?;
+        let header_pos = sink.stream_position()?;
+        // Write placeholders for array length and byte size, which are updated on the end.
+        sink.write_u32::<LittleEndian>(0)?;
+        sink.write_u32::<LittleEndian>(encoding)?;
+        sink.write_u32::<LittleEndian>(0)?;
+        self.open_array = Some(OpenArray {
+            type_code,
+            header_pos,
+            array_length: 0,
+            encoder,
+        });
+        Ok(())
+    }
+
+    /// Writes an element of the array property being written.
+    ///
+    /// `bytes` is a little endian representation of the element.
+    pub fn push_array_element<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        type_code: u8,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let array = match self.open_array {
+            Some(ref mut array) => array,
+            None => {
+                return Err(Error::InvalidArrayProperty(
+                    "array property is not started".to_string(),
+                ))
+            }
+        };
+        if array.type_code != type_code {
+            return Err(Error::InvalidArrayProperty(format!(
+                "element of type {:?} is given for array property of type {:?}",
+                type_code as char, array.type_code as char
+            )));
+        }
+        check_u32_field(array.array_length + 1, "Number of array elements")?;
+        array.encoder.write(sink, bytes)?;
+        array.array_length += 1;
+        Ok(())
+    }
+
+    /// Ends the array property being written, and updates the node record header.
+    pub fn end_array_property<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+        let array = match self.open_array.take() {
+            Some(array) => array,
+            None => {
+                return Err(Error::InvalidArrayProperty(
+                    "array property is not started".to_string(),
+                ))
+            }
+        };
+        // Empty arrays are written as plain data, as `emit_start_node` does.
+        let is_empty = array.array_length == 0;
+        if !is_empty {
+            array.encoder.finish(sink)?;
+        }
+        let last_pos = sink.stream_position()?;
+
+        // Update the property array header.
+        // 12: property array header.
+        let byte_size = check_u32_field(last_pos - array.header_pos - 12, "Array property size")?;
+        sink.seek(SeekFrom::Start(array.header_pos))?;
+        sink.write_u32::<LittleEndian>(array.array_length as u32)?;
+        if is_empty {
+            sink.write_u32::<LittleEndian>(0)?;
+        } else {
+            sink.seek(SeekFrom::Current(4))?;
+        }
+        sink.write_u32::<LittleEndian>(byte_size)?;
+
+        // Update `num_properties` and `property_list_len`.
+        let props = self
+            .appendable_properties
+            .as_mut()
+            .expect("array property should be started only for an appendable node");
+        props.num_properties += 1;
+        // 1: type code.
+        props.property_list_len += 1 + 12 + u64::from(byte_size);
+        sink.seek(SeekFrom::Start(props.num_properties_pos))?;
+        if self.version < 7500 {
+            if props.property_list_len > u64::from(u32::MAX) {
+                return Err(Error::DataTooLarge(format!(
+                    "Properties size ({} bytes) is too large for FBX {}",
+                    props.property_list_len, self.version
+                )));
+            }
+            sink.write_u32::<LittleEndian>(props.num_properties as u32)?;
+            sink.write_u32::<LittleEndian>(props.property_list_len as u32)?;
+        } else {
+            sink.write_u64::<LittleEndian>(props.num_properties)?;
+            sink.write_u64::<LittleEndian>(props.property_list_len)?;
+        }
+        sink.seek(SeekFrom::Start(last_pos))?;
+        // The node has a property now, so a null record is not required unless it has children.
+        if let Some(top) = self.null_record_necessities.last_mut() {
+            *top = false;
+        }
+
         Ok(())
     }
 }
+
+/// Rewrites `end_offset` fields of the node records in `bytes` for the node moved to `start_pos`.
+///
+/// Returns the number of nodes and the number of properties in `bytes`.
+fn relocate_raw_node(bytes: &mut [u8], version: u32, start_pos: u64) -> Result<(u64, u64)> {
+    let broken = || Error::DataError("Broken raw node record".to_string());
+    let is_64bit = version >= 7500;
+    let field_len = if is_64bit { 8 } else { 4 };
+    let read_field = |bytes: &[u8], pos: usize| -> u64 {
+        if is_64bit {
+            LittleEndian::read_u64(&bytes[pos..])
+        } else {
+            u64::from(LittleEndian::read_u32(&bytes[pos..]))
+        }
+    };
+    // 1: `name_len`.
+    let header_len = field_len * 3 + 1;
+    if bytes.len() < header_len {
+        return Err(broken());
+    }
+    // Position of the node in the source data.
+    let orig_start = read_field(bytes, 0)
+        .checked_sub(bytes.len() as u64)
+        .ok_or_else(broken)?;
+    let mut num_nodes = 0;
+    let mut num_properties = 0;
+    // End positions (in `bytes`) of the open nodes.
+    let mut end_stack: Vec<usize> = vec![];
+    let mut pos = 0;
+    loop {
+        while end_stack.last() == Some(&pos) {
+            end_stack.pop();
+        }
+        if pos != 0 && end_stack.is_empty() {
+            break;
+        }
+        let parent_end = end_stack.last().cloned().unwrap_or(bytes.len());
+        if pos + header_len > parent_end {
+            return Err(broken());
+        }
+        if bytes[pos..pos + header_len].iter().all(|&b| b == 0) {
+            // Null record.
+            pos += header_len;
+            continue;
+        }
+        let end = read_field(bytes, pos)
+            .checked_sub(orig_start)
+            .filter(|&end| end > pos as u64 && end <= parent_end as u64)
+            .ok_or_else(broken)? as usize;
+        let new_end = start_pos + end as u64;
+        if is_64bit {
+            LittleEndian::write_u64(&mut bytes[pos..], new_end);
+        } else {
+            if new_end > u64::from(u32::MAX) {
+                return Err(Error::DataTooLarge(format!(
+                    "File size (currently {} bytes) is too large for FBX {}",
+                    new_end, version
+                )));
+            }
+            LittleEndian::write_u32(&mut bytes[pos..], new_end as u32);
+        }
+        num_nodes += 1;
+        num_properties += read_field(bytes, pos + field_len);
+        let property_list_len = read_field(bytes, pos + field_len * 2);
+        let name_len = u64::from(bytes[pos + header_len - 1]);
+        let children_start = ((pos + header_len) as u64 + name_len)
+            .checked_add(property_list_len)
+            .filter(|&children_start| children_start <= end as u64)
+            .ok_or_else(broken)?;
+        end_stack.push(end);
+        pos = children_start as usize;
+    }
+    Ok((num_nodes, num_properties))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_u32_field;
+    use crate::writer::error::Error;
+
+    #[test]
+    fn u32_field_limit() {
+        assert_eq!(check_u32_field(12, "size").unwrap(), 12);
+        assert_eq!(
+            check_u32_field(u64::from(u32::MAX), "size").unwrap(),
+            u32::MAX
+        );
+        assert!(matches!(
+            check_u32_field(u64::from(u32::MAX) + 1, "size"),
+            Err(Error::DataTooLarge(_))
+        ));
+    }
+}
@@ -2,16 +2,17 @@
 
 use self::ascii::AsciiEmitter;
 use self::binary::BinaryEmitter;
-use crate::common::FbxFormatType;
+use crate::common::{FbxFormatType, Property, RawNode};
 use crate::writer::error::{Error, Result};
-use crate::writer::{EmitterConfig, FbxEvent};
+use crate::writer::{EmitterConfig, FbxEvent, NodeWrittenCallback};
+use byteorder::{ByteOrder, LittleEndian};
 use log::{error, warn};
-use std::io::{Seek, Write};
+use std::io::{Seek, SeekFrom, Write};
 
 mod ascii;
 mod binary;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 enum EmitterState {
     /// Emitter is initialized but not used yet.
     Initial,
@@ -28,23 +29,300 @@ struct CommonState {
 
 pub struct Emitter {
     config: EmitterConfig,
+    /// A callback invoked when a node is written to Binary FBX.
+    on_node_written: Option<NodeWrittenCallback>,
     common: CommonState,
     state: EmitterState,
+    /// Name of the node without properties, whose `StartNode` is not emitted yet.
+    ///
+    /// This is used only when `EmitterConfig::skip_empty_nodes` is enabled.
+    pending_empty_node: Option<String>,
+    /// Whether `EndFbx` is successfully written.
+    ended: bool,
+    /// Number of nodes written.
+    nodes_written: u64,
+    /// Number of properties written.
+    properties_written: u64,
+    /// Position of the sink where the emitter started writing.
+    start_pos: Option<u64>,
+    /// Number of bytes from `start_pos` to the end of the written data.
+    bytes_written: u64,
 }
 
 impl Emitter {
     pub fn new(config: EmitterConfig) -> Self {
         Emitter {
             config,
+            on_node_written: None,
             common: CommonState { final_result: None },
             state: EmitterState::Initial,
+            pending_empty_node: None,
+            ended: false,
+            nodes_written: 0,
+            properties_written: 0,
+            start_pos: None,
+            bytes_written: 0,
         }
     }
 
+    /// Creates an emitter to append nodes to existing Binary FBX.
+    ///
+    /// `root_end` is the end position of the null record of the implicit root node.
+    /// The sink is rewound to the beginning of the null record, so that new nodes overwrite it
+    /// and the footer.
+    pub fn new_binary_appending<W: Write + Seek>(
+        sink: &mut W,
+        config: EmitterConfig,
+        version: u32,
+        root_end: u64,
+    ) -> Result<Self> {
+        if let Some(config_fbx_ver) = config.fbx_version {
+            if version != config_fbx_ver {
+                return Err(Error::InvalidOption(format!(
+                    "FBX version {} specified by emitter config, but the existing data is FBX {}",
+                    config_fbx_ver, version
+                )));
+            }
+        }
+        BinaryEmitter::check_version(version)?;
+        let binary_emitter = BinaryEmitter::new(version);
+        sink.seek(SeekFrom::Start(root_end - binary_emitter.null_record_len()))?;
+        let mut emitter = Emitter::new(config);
+        emitter.state = EmitterState::Binary(binary_emitter);
+        emitter.start_pos = Some(sink.stream_position()?);
+        Ok(emitter)
+    }
+
     pub fn write<'a, W: Write + Seek>(&mut self, sink: &mut W, event: FbxEvent<'a>) -> Result<()> {
         if let Some(ref result) = self.common.final_result {
             return result.clone();
         }
+        if !self.config.skip_empty_nodes {
+            return self.write_event(sink, event);
+        }
+        if let FbxEvent::EndNode = event {
+            if self.pending_empty_node.take().is_some() {
+                // `StartNode` is immediately followed by `EndNode`: skip the node.
+                return Ok(());
+            }
+        }
+        if let Some(name) = self.pending_empty_node.take() {
+            // The pending node has some content.
+            self.write_event(
+                sink,
+                FbxEvent::StartNode {
+                    name: &name,
+                    properties: Default::default(),
+                },
+            )?;
+        }
+        match event {
+            FbxEvent::StartNode {
+                name,
+                ref properties,
+            } if properties.is_empty() => {
+                if let EmitterState::Initial = self.state {
+                    return self.write_event(sink, event);
+                }
+                self.pending_empty_node = Some(name.to_string());
+                Ok(())
+            }
+            event => self.write_event(sink, event),
+        }
+    }
+
+    /// Sets a callback invoked when a node is written to Binary FBX.
+    pub fn set_on_node_written(&mut self, callback: Option<NodeWrittenCallback>) {
+        self.on_node_written = callback;
+    }
+
+    /// Returns whether `EndFbx` is successfully written.
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Returns the format being written, if started.
+    pub fn format_type(&self) -> Option<FbxFormatType> {
+        match self.state {
+            EmitterState::Initial => None,
+            EmitterState::Binary(ref emitter) => Some(FbxFormatType::Binary(emitter.version())),
+            EmitterState::Ascii(_) => Some(FbxFormatType::Ascii),
+        }
+    }
+
+    /// Returns the number of nodes written.
+    pub fn nodes_written(&self) -> u64 {
+        self.nodes_written
+    }
+
+    /// Returns the number of properties written.
+    pub fn properties_written(&self) -> u64 {
+        self.properties_written
+    }
+
+    /// Returns the number of bytes written.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Updates `bytes_written` to the current position of the sink.
+    fn update_bytes_written<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+        if let Some(start_pos) = self.start_pos {
+            self.bytes_written = sink.stream_position()? - start_pos;
+        }
+        Ok(())
+    }
+
+    /// Starts an array property appended to the last started node.
+    pub fn begin_array_property<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        type_code: u8,
+    ) -> Result<()> {
+        if let Some(ref result) = self.common.final_result {
+            return result.clone();
+        }
+        if let Some(name) = self.pending_empty_node.take() {
+            // The pending node has a property now.
+            self.write_event(
+                sink,
+                FbxEvent::StartNode {
+                    name: &name,
+                    properties: Default::default(),
+                },
+            )?;
+        }
+        let result = match self.state {
+            EmitterState::Initial => Err(Error::FbxNotStarted),
+            EmitterState::Binary(ref mut emitter) => {
+                emitter.begin_array_property(sink, type_code, &self.config)
+            }
+            EmitterState::Ascii(_) => Err(Error::Unimplemented(
+                "Streamed array properties for ASCII FBX".to_string(),
+            )),
+        };
+        if result.is_ok() {
+            self.properties_written += 1;
+        }
+        let result = result.and_then(|()| self.update_bytes_written(sink));
+        self.latch_error(result)
+    }
+
+    /// Writes a node captured from Binary FBX, with its descendants.
+    pub fn write_raw_node<W: Write + Seek>(&mut self, sink: &mut W, node: &RawNode) -> Result<()> {
+        if let Some(ref result) = self.common.final_result {
+            return result.clone();
+        }
+        if let Some(name) = self.pending_empty_node.take() {
+            // The pending node has a child now.
+            self.write_event(
+                sink,
+                FbxEvent::StartNode {
+                    name: &name,
+                    properties: Default::default(),
+                },
+            )?;
+        }
+        let result = match self.state {
+            EmitterState::Initial => Err(Error::FbxNotStarted),
+            EmitterState::Binary(ref mut emitter) => {
+                emitter.emit_raw_node(sink, node, self.on_node_written.as_ref())
+            }
+            EmitterState::Ascii(_) => Err(Error::UnwritableEvent),
+        };
+        let result = result.map(|(num_nodes, num_properties)| {
+            self.nodes_written += num_nodes;
+            self.properties_written += num_properties;
+        });
+        let result = result.and_then(|()| self.update_bytes_written(sink));
+        self.latch_error(result)
+    }
+
+    /// Writes an element of the array property being written.
+    pub fn push_array_element<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        type_code: u8,
+        bytes: &[u8],
+    ) -> Result<()> {
+        if let Some(ref result) = self.common.final_result {
+            return result.clone();
+        }
+        if self.config.reject_non_finite {
+            let finite = match type_code {
+                b'f' => LittleEndian::read_f32(bytes).is_finite(),
+                b'd' => LittleEndian::read_f64(bytes).is_finite(),
+                _ => true,
+            };
+            if !finite {
+                return self.latch_error(Err(Error::DataError(
+                    "Non-finite float value in an array property".to_string(),
+                )));
+            }
+        }
+        let result = match self.state {
+            EmitterState::Binary(ref mut emitter) => {
+                emitter.push_array_element(sink, type_code, bytes)
+            }
+            _ => Err(Error::InvalidArrayProperty(
+                "array property is not started".to_string(),
+            )),
+        };
+        let result = result.and_then(|()| self.update_bytes_written(sink));
+        self.latch_error(result)
+    }
+
+    /// Ends the array property being written.
+    pub fn end_array_property<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+        if let Some(ref result) = self.common.final_result {
+            return result.clone();
+        }
+        let result = match self.state {
+            EmitterState::Binary(ref mut emitter) => emitter.end_array_property(sink),
+            _ => Err(Error::InvalidArrayProperty(
+                "array property is not started".to_string(),
+            )),
+        };
+        let result = result.and_then(|()| self.update_bytes_written(sink));
+        self.latch_error(result)
+    }
+
+    /// Remembers the error as the final result, so that the emitter refuses further events.
+    fn latch_error(&mut self, result: Result<()>) -> Result<()> {
+        if let Err(ref err) = result {
+            self.common.final_result = Some(Err(err.clone()));
+        }
+        result
+    }
+
+    fn write_event<'a, W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        event: FbxEvent<'a>,
+    ) -> Result<()> {
+        let is_end = matches!(event, FbxEvent::EndFbx);
+        let num_properties = match event {
+            FbxEvent::StartNode { ref properties, .. } => Some(properties.len()),
+            _ => None,
+        };
+        if self.config.reject_non_finite {
+            if let FbxEvent::StartNode {
+                name,
+                ref properties,
+            } = event
+            {
+                if let Err(err) = check_finite(name, properties) {
+                    return self.latch_error(Err(err));
+                }
+            }
+        }
+        if self.start_pos.is_none() {
+            // Remember the start position before writing anything.
+            match sink.stream_position() {
+                Ok(pos) => self.start_pos = Some(pos),
+                Err(err) => return self.latch_error(Err(err.into())),
+            }
+        }
         let result = match self.state {
             EmitterState::Initial => match event {
                 FbxEvent::StartFbx(FbxFormatType::Binary(ver)) => {
@@ -59,7 +337,7 @@ impl Emitter {
                     result
                 }
                 FbxEvent::StartFbx(FbxFormatType::Ascii) => {
-                    let mut emitter = AsciiEmitter::new();
+                    let mut emitter = AsciiEmitter::new(&self.config);
                     let result = if let Some(ver) = self.config.fbx_version {
                         emitter.emit_start_fbx(sink, ver)
                     } else {
@@ -74,12 +352,12 @@ impl Emitter {
             },
             EmitterState::Binary(ref mut emitter) => match event {
                 FbxEvent::StartFbx(_) => Err(Error::FbxAlreadyStarted),
-                FbxEvent::EndFbx => emitter.emit_end_fbx(sink),
+                FbxEvent::EndFbx => emitter.emit_end_fbx(sink, &self.config),
                 FbxEvent::StartNode { name, properties } => {
-                    emitter.emit_start_node(sink, name, &properties)
+                    emitter.emit_start_node(sink, name, &properties, &self.config)
                 }
-                FbxEvent::EndNode => emitter.emit_end_node(sink),
-                FbxEvent::Comment(_) => {
+                FbxEvent::EndNode => emitter.emit_end_node(sink, self.on_node_written.as_ref()),
+                FbxEvent::Comment(_) | FbxEvent::InlineComment(_) => {
                     if self.config.ignore_minor_errors {
                         warn!("Comment cannot be exported to Binary FBX");
                         Ok(())
@@ -97,11 +375,39 @@ impl Emitter {
                 }
                 FbxEvent::EndNode => emitter.emit_end_node(sink),
                 FbxEvent::Comment(comment) => emitter.emit_comment(sink, comment),
+                FbxEvent::InlineComment(comment) => emitter.emit_inline_comment(sink, comment),
             },
         };
-        if let Err(ref err) = result {
-            self.common.final_result = Some(Err(err.clone()));
+        if result.is_ok() {
+            if let Some(num_properties) = num_properties {
+                self.nodes_written += 1;
+                self.properties_written += num_properties as u64;
+            }
+            if is_end {
+                self.ended = true;
+            }
+        }
+        let result = result.and_then(|()| self.update_bytes_written(sink));
+        self.latch_error(result)
+    }
+}
+
+/// Checks that the properties have no non-finite floating point values.
+fn check_finite(name: &str, properties: &[Property<'_>]) -> Result<()> {
+    for (i, property) in properties.iter().enumerate() {
+        let finite = match *property {
+            Property::F32(v) => v.is_finite(),
+            Property::F64(v) => v.is_finite(),
+            Property::VecF32(v) => v.iter().all(|v| v.is_finite()),
+            Property::VecF64(v) => v.iter().all(|v| v.is_finite()),
+            _ => true,
+        };
+        if !finite {
+            return Err(Error::DataError(format!(
+                "Non-finite float value in the property #{} of node {:?}",
+                i, name
+            )));
         }
-        result
     }
+    Ok(())
 }
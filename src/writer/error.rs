@@ -1,21 +1,30 @@
 //! Contains result and error type for FBX reader.
 
+use crate::reader;
 use std::error;
 use std::fmt;
 use std::io;
+use std::sync::Arc;
 
 /// A specialized `std::result::Result` type for FBX exporting.
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 /// An FBX parsing error.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     /// I/O error.
-    Io(io::Error),
+    ///
+    /// `io::Error` is not `Clone`, so it is shared by `Arc` to keep the original error (including
+    /// its message) on cloning.
+    Io(Arc<io::Error>),
     /// Data size is too large.
     DataTooLarge(String),
     /// `EndNode` event is given but there's no node to close.
     ExtraEndNode,
+    /// `EndFbx` event is given but some nodes are not closed.
+    ///
+    /// The payload is the number of unclosed nodes.
+    UnclosedNodes(usize),
     /// FBX not started but an event other than `StartFbx` is given.
     FbxNotStarted,
     /// FBX is already started but `StartFbx` is given.
@@ -23,11 +32,26 @@ pub enum Error {
     /// Invalid writer option.
     InvalidOption(String),
     /// Unsupported FBX version.
+    ///
+    /// The payload is the requested FBX version.
     UnsupportedFbxVersion(u32),
     /// Given event is not writable in current format.
     UnwritableEvent,
     /// Unimplemented feature.
     Unimplemented(String),
+    /// Array property streaming is used in a wrong way.
+    ///
+    /// For example, pushing an element without starting an array property, or starting a child
+    /// node before ending the array property.
+    InvalidArrayProperty(String),
+    /// Invalid data is given.
+    ///
+    /// For example, non-finite floating point values when
+    /// [`EmitterConfig::reject_non_finite`](struct.EmitterConfig.html#structfield.reject_non_finite)
+    /// is enabled.
+    DataError(String),
+    /// Failed to read existing FBX data (to append nodes to it).
+    Reader(reader::Error),
 }
 
 impl fmt::Display for Error {
@@ -36,6 +60,9 @@ impl fmt::Display for Error {
             Error::Io(ref err) => write!(f, "I/O error: {}", err),
             Error::DataTooLarge(ref err) => write!(f, "Data size is too large: {}", err),
             Error::ExtraEndNode => write!(f, "Extra end-of-node marker detected"),
+            Error::UnclosedNodes(n) => {
+                write!(f, "End of FBX is given but {} node(s) are not closed", n)
+            }
             Error::FbxNotStarted => write!(
                 f,
                 "An writer event is given, but FBX data is not started yet"
@@ -48,49 +75,55 @@ impl fmt::Display for Error {
             Error::UnsupportedFbxVersion(ver) => write!(f, "Unsupported FBX version ({})", ver),
             Error::UnwritableEvent => write!(f, "A given event is not writable in current format"),
             Error::Unimplemented(ref err) => write!(f, "Unimplemented feature: {}", err),
+            Error::InvalidArrayProperty(ref err) => {
+                write!(f, "Invalid use of streamed array property: {}", err)
+            }
+            Error::DataError(ref err) => write!(f, "Invalid data: {}", err),
+            Error::Reader(ref err) => write!(f, "Failed to read existing FBX data: {}", err),
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Io(ref err) => err.description(),
-            Error::DataTooLarge(_) => "Data size is too large",
-            Error::ExtraEndNode => "Extra end-of-node marker detected",
-            Error::FbxNotStarted => "An writer event is given, but FBX data is not started yet",
-            Error::FbxAlreadyStarted => {
-                "Got a writer event to start FBX, but FBX data is already started"
-            }
-            Error::InvalidOption(_) => "Invalid writer option",
-            Error::UnsupportedFbxVersion(_) => "Unsupported FBX version",
-            Error::UnwritableEvent => "A given event is not writable in current format",
-            Error::Unimplemented(_) => "Attempt to use unimplemented feature",
-        }
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Error::Io(ref err) => Some(err as &dyn error::Error),
+            Error::Io(ref err) => Some(&**err),
+            Error::Reader(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
-impl Clone for Error {
-    fn clone(&self) -> Self {
-        use self::Error::*;
-        use std::error::Error;
-        match *self {
-            Io(ref e) => Io(io::Error::new(e.kind(), e.description())),
-            Unimplemented(ref e) => Unimplemented(e.clone()),
-            ref e => e.clone(),
-        }
+impl From<reader::Error> for Error {
+    fn from(err: reader::Error) -> Error {
+        Error::Reader(err)
     }
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
-        Error::Io(err)
+        Error::Io(Arc::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn clone_error() {
+        let errors = vec![
+            Error::DataTooLarge("too large".to_owned()),
+            Error::ExtraEndNode,
+            Error::FbxNotStarted,
+            Error::FbxAlreadyStarted,
+            Error::InvalidOption("invalid".to_owned()),
+            Error::UnsupportedFbxVersion(42),
+            Error::UnwritableEvent,
+            Error::Unimplemented("unimplemented".to_owned()),
+        ];
+        for err in errors {
+            assert_eq!(err.clone().to_string(), err.to_string());
+        }
     }
 }
@@ -22,6 +22,12 @@ pub enum FbxEvent<'a> {
         /// Node name.
         name: &'a str,
         /// Node properties.
+        ///
+        /// Each array property can have at most `u32::MAX` elements, because the number of
+        /// elements is stored as 32 bit integer in all FBX versions.
+        /// A slice shared among many nodes can be borrowed by `Cow::Borrowed` (or `.into()`)
+        /// without cloning (see also
+        /// [`EventWriter::write_start_node`](struct.EventWriter.html#method.write_start_node)).
         properties: Cow<'a, [Property<'a>]>,
     },
     /// Denotes end of a node.
@@ -30,4 +36,109 @@ pub enum FbxEvent<'a> {
     ///
     /// Comment only appears in ASCII FBX.
     Comment(&'a str),
+    /// Comment at the end of the line of the last started node, such as
+    /// `Version: 232 ; comment`.
+    ///
+    /// This should be written right after `StartNode`, before any child nodes.
+    /// The comment is written right after `;` as is, so it should usually start with a space
+    /// (as comments read by the reader do), and should not contain newlines.
+    /// Comment only appears in ASCII FBX.
+    InlineComment(&'a str),
+}
+
+/// Creates a builder of `StartNode` event with the given node name.
+///
+/// # Examples
+///
+/// ```
+/// use fbx_direct::common::FbxFormatType;
+/// use fbx_direct::writer::{node, EventWriter, FbxEvent};
+///
+/// let mut writer = EventWriter::to_vec();
+/// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+/// writer.write(node("Model").prop_i64(1).prop_str("Model::Cube")).unwrap();
+/// writer.write(FbxEvent::EndNode).unwrap();
+/// writer.write(FbxEvent::EndFbx).unwrap();
+/// ```
+pub fn node(name: &str) -> NodeBuilder<'_> {
+    NodeBuilder::new(name)
+}
+
+/// A builder of `StartNode` event.
+///
+/// Property values are borrowed, and no data is copied except for the list of properties.
+#[derive(Debug, Clone)]
+pub struct NodeBuilder<'a> {
+    name: &'a str,
+    properties: Vec<Property<'a>>,
+}
+
+impl<'a> NodeBuilder<'a> {
+    /// Creates a new builder with the given node name and no properties.
+    pub fn new(name: &'a str) -> Self {
+        NodeBuilder {
+            name,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Appends a property.
+    pub fn prop<P: Into<Property<'a>>>(mut self, value: P) -> Self {
+        self.properties.push(value.into());
+        self
+    }
+
+    /// Appends a boolean property.
+    pub fn prop_bool(self, value: bool) -> Self {
+        self.prop(Property::Bool(value))
+    }
+
+    /// Appends a 2 byte signed integer property.
+    pub fn prop_i16(self, value: i16) -> Self {
+        self.prop(Property::I16(value))
+    }
+
+    /// Appends a 4 byte signed integer property.
+    pub fn prop_i32(self, value: i32) -> Self {
+        self.prop(Property::I32(value))
+    }
+
+    /// Appends an 8 byte signed integer property.
+    pub fn prop_i64(self, value: i64) -> Self {
+        self.prop(Property::I64(value))
+    }
+
+    /// Appends a 4 byte floating-point number property.
+    pub fn prop_f32(self, value: f32) -> Self {
+        self.prop(Property::F32(value))
+    }
+
+    /// Appends an 8 byte floating-point number property.
+    pub fn prop_f64(self, value: f64) -> Self {
+        self.prop(Property::F64(value))
+    }
+
+    /// Appends a string property.
+    pub fn prop_str(self, value: &'a str) -> Self {
+        self.prop(Property::String(value))
+    }
+
+    /// Appends a raw binary property.
+    pub fn prop_binary(self, value: &'a [u8]) -> Self {
+        self.prop(Property::Binary(value))
+    }
+
+    /// Creates `StartNode` event.
+    pub fn build(self) -> FbxEvent<'a> {
+        FbxEvent::StartNode {
+            name: self.name,
+            properties: Cow::Owned(self.properties),
+        }
+    }
+}
+
+impl<'a> From<NodeBuilder<'a>> for FbxEvent<'a> {
+    fn from(builder: NodeBuilder<'a>) -> Self {
+        builder.build()
+    }
 }
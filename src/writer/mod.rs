@@ -1,13 +1,58 @@
 //! Contains interface for an events-based FBX emitter.
 
-use std::io::{Seek, Write};
+use crate::common::{FbxFormatType, OwnedProperty, Property, RawNode, FOOTER_RESERVED_LEN};
+use crate::reader::{self, ArrayElement, EventReader};
+use std::borrow::Cow;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
 
 pub use self::error::{Error, Result};
-pub use self::events::FbxEvent;
+pub use self::events::{node, FbxEvent, NodeBuilder};
+pub use self::sink::{BufferedSink, TempFileSink};
 
 mod emitter;
 mod error;
 mod events;
+mod sink;
+pub mod templates;
+
+/// Creates a `Vec<Property>` from values, using `From` conversions into `Property`.
+///
+/// # Examples
+///
+/// ```
+/// use fbx_direct::common::{FbxFormatType, Property};
+/// use fbx_direct::fbx_props;
+/// use fbx_direct::writer::{EventWriter, FbxEvent};
+///
+/// let properties = fbx_props!["Model::Cube", 1_i64, 2.0_f64, &[0_i32, 1, 2][..]];
+/// assert_eq!(
+///     properties,
+///     vec![
+///         Property::String("Model::Cube"),
+///         Property::I64(1),
+///         Property::F64(2.0),
+///         Property::VecI32(&[0, 1, 2]),
+///     ]
+/// );
+///
+/// let mut writer = EventWriter::to_vec();
+/// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+/// writer
+///     .write(FbxEvent::StartNode {
+///         name: "Model",
+///         properties: properties.into(),
+///     })
+///     .unwrap();
+/// writer.write(FbxEvent::EndNode).unwrap();
+/// writer.write(FbxEvent::EndFbx).unwrap();
+/// ```
+#[macro_export]
+macro_rules! fbx_props {
+    ($($value:expr),* $(,)*) => {
+        vec![$($crate::common::Property::from($value)),*]
+    };
+}
 
 /// A wrapper around an `std::io::Write` instance which emits Binary FBX.
 pub struct EventWriter<W: Write + Seek> {
@@ -39,12 +84,458 @@ impl<W: Write + Seek> EventWriter<W> {
     {
         self.emitter.write(&mut self.sink, event.into())
     }
+
+    /// Writes `StartNode` event with the given name and properties.
+    ///
+    /// This is the same as writing `FbxEvent::StartNode` with borrowed properties, but
+    /// convenient to write many nodes sharing the same property slice (such as a template of
+    /// properties), without cloning the slice or building an event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fbx_direct::common::{FbxFormatType, Property};
+    /// use fbx_direct::writer::{EventWriter, FbxEvent};
+    ///
+    /// let template = [Property::String("Lcl Translation"), Property::F64(0.0)];
+    /// let mut writer = EventWriter::to_vec();
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// for _ in 0..3 {
+    ///     writer.write_start_node("P", &template).unwrap();
+    ///     writer.write(FbxEvent::EndNode).unwrap();
+    /// }
+    /// writer.write(FbxEvent::EndFbx).unwrap();
+    /// ```
+    pub fn write_start_node(&mut self, name: &str, properties: &[Property<'_>]) -> Result<()> {
+        self.write(FbxEvent::StartNode {
+            name,
+            properties: Cow::Borrowed(properties),
+        })
+    }
+
+    /// Writes the node at the given index of the arena, with all its descendants.
+    ///
+    /// If the index is of the implicit root node, only its children (i.e. top-level nodes) are
+    /// written.
+    /// Properties are borrowed from the arena, and the tree is traversed without recursion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fbx_direct::common::FbxFormatType;
+    /// use fbx_direct::reader::{Arena, EventReader};
+    /// use fbx_direct::writer::{EventWriter, FbxEvent};
+    /// use std::io::Cursor;
+    ///
+    /// let source = "; FBX 7.4.0 project file\nParent: 1 {\n\tChild: \"value\"\n}\n";
+    /// let arena = Arena::read(&mut EventReader::new(Cursor::new(source))).unwrap();
+    ///
+    /// let mut writer = EventWriter::to_vec();
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// writer.write_node(&arena, arena.root()).unwrap();
+    /// writer.write(FbxEvent::EndFbx).unwrap();
+    /// ```
+    pub fn write_node(&mut self, arena: &reader::Arena, index: usize) -> Result<()> {
+        let is_root = index == arena.root();
+        if !is_root {
+            self.start_arena_node(arena.node(index))?;
+        }
+        let mut stack = vec![arena.children(index)];
+        while let Some(children) = stack.last_mut() {
+            if let Some(child) = children.next() {
+                self.start_arena_node(arena.node(child))?;
+                stack.push(arena.children(child));
+            } else {
+                stack.pop();
+                if !stack.is_empty() || !is_root {
+                    self.write(FbxEvent::EndNode)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `StartNode` event of the given arena node.
+    fn start_arena_node(&mut self, node: &reader::ArenaNode) -> Result<()> {
+        let properties = node
+            .properties
+            .iter()
+            .map(OwnedProperty::borrow)
+            .collect::<Vec<_>>();
+        self.write_start_node(&node.name, &properties)
+    }
+
+    /// Writes a node captured by
+    /// [`EventReader::capture_node`](../reader/struct.EventReader.html#method.capture_node) as
+    /// is, with all its descendants.
+    ///
+    /// This is lossless passthrough of nodes: the bytes are identical to the source, except for
+    /// `end_offset` fields of node record headers, which are updated for the position in the
+    /// output.
+    /// Only Binary FBX of the same version as the source can be written, and
+    /// `Error::DataError` is returned for other versions (`Error::UnwritableEvent` for ASCII
+    /// FBX).
+    /// Note that nodes in the raw node are not affected by the emitter config (such as
+    /// `compress_arrays` and `skip_empty_nodes`), and `on_node_written` is called only for the
+    /// raw node itself, not for its descendants.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fbx_direct::common::FbxFormatType;
+    /// use fbx_direct::reader::{EventReader, FbxEvent as ReaderEvent};
+    /// use fbx_direct::writer::{EventWriter, FbxEvent};
+    /// use std::io::Cursor;
+    ///
+    /// let mut writer = EventWriter::to_vec();
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// writer.write_start_node("Unknown", &[]).unwrap();
+    /// writer.write_start_node("Child", &[42_i32.into()]).unwrap();
+    /// writer.write(FbxEvent::EndNode).unwrap();
+    /// writer.write(FbxEvent::EndNode).unwrap();
+    /// writer.write(FbxEvent::EndFbx).unwrap();
+    /// let source = writer.into_vec();
+    ///
+    /// let mut reader = EventReader::new(Cursor::new(&source));
+    /// assert!(matches!(reader.next(), Ok(ReaderEvent::StartFbx(_))));
+    /// assert!(matches!(reader.next(), Ok(ReaderEvent::StartNode { .. })));
+    /// let raw = reader.capture_node().unwrap().unwrap();
+    ///
+    /// let mut writer = EventWriter::to_vec();
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// writer.write_raw_node(&raw).unwrap();
+    /// writer.write(FbxEvent::EndFbx).unwrap();
+    /// assert_eq!(writer.into_vec(), source);
+    /// ```
+    pub fn write_raw_node(&mut self, node: &RawNode) -> Result<()> {
+        self.emitter.write_raw_node(&mut self.sink, node)
+    }
+
+    /// Sets a callback invoked when a node is written to Binary FBX.
+    ///
+    /// The callback is called at the end of each node with the node name and the byte range
+    /// `[start, end)` of the node record (which the `end_offset` field of the node header points
+    /// to as `end`).
+    /// This is useful to build an index of node offsets while writing.
+    /// The callback is not called for ASCII FBX.
+    pub fn set_on_node_written(&mut self, callback: Option<NodeWrittenCallback>) {
+        self.emitter.set_on_node_written(callback);
+    }
+
+    /// Returns the number of nodes written by this writer.
+    ///
+    /// Counters are per writer instance: they start from zero when the writer is created (also
+    /// for [`append`](#method.append), which counts only appended nodes) and are never reset.
+    /// Nodes omitted by `EmitterConfig::skip_empty_nodes` are not counted.
+    pub fn nodes_written(&self) -> u64 {
+        self.emitter.nodes_written()
+    }
+
+    /// Returns the number of properties written by this writer.
+    ///
+    /// Properties of all nodes are counted, including array properties written by
+    /// `begin_array_property()`. See [`nodes_written`](#method.nodes_written) for the reset
+    /// semantics.
+    pub fn properties_written(&self) -> u64 {
+        self.emitter.properties_written()
+    }
+
+    /// Returns the number of bytes written by this writer.
+    ///
+    /// This is the distance from the position of the sink when the writer started writing to
+    /// the end of the data written so far, so bytes overwritten later (such as node record
+    /// headers of Binary FBX) are counted only once.
+    /// See [`nodes_written`](#method.nodes_written) for the reset semantics.
+    pub fn bytes_written(&self) -> u64 {
+        self.emitter.bytes_written()
+    }
+
+    /// Consumes the writer and returns the underlying sink.
+    ///
+    /// Data is not finalized by this method, so `EndFbx` should be written in advance to get
+    /// complete FBX data.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    /// Flushes the underlying sink.
+    pub fn flush(&mut self) -> Result<()> {
+        self.sink.flush()?;
+        Ok(())
+    }
+
+    /// Writes `EndFbx` if not written yet, flushes the sink, and returns it.
+    ///
+    /// An error is returned if FBX is not started, some nodes are not closed, or a previous
+    /// write has failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fbx_direct::common::FbxFormatType;
+    /// use fbx_direct::writer::{EventWriter, FbxEvent};
+    /// use std::io::Cursor;
+    ///
+    /// let mut writer = EventWriter::new(Cursor::new(Vec::new()));
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// // `EndFbx` is written by `finish()`.
+    /// let data = writer.finish().unwrap().into_inner();
+    /// assert_eq!(&data[..20], b"Kaydara FBX Binary  ");
+    /// ```
+    pub fn finish(mut self) -> Result<W> {
+        if !self.emitter.is_ended() {
+            self.write(FbxEvent::EndFbx)?;
+        }
+        self.flush()?;
+        Ok(self.sink)
+    }
+
+    /// Starts an array property with elements of type `T`, appended to the last started node.
+    ///
+    /// Elements are written by `push_*` methods, and the property is completed by
+    /// `end_array_property()`.
+    /// This is useful to write a large array generated lazily, without collecting it into a `Vec`.
+    ///
+    /// Properties can be appended only to the last started node until its child node is started
+    /// or the node is ended, and no other events can be written until the array property is
+    /// ended.
+    /// Only Binary FBX is supported for now.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fbx_direct::common::FbxFormatType;
+    /// use fbx_direct::writer::{EventWriter, FbxEvent};
+    ///
+    /// let mut writer = EventWriter::to_vec();
+    /// writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+    /// writer
+    ///     .write(FbxEvent::StartNode {
+    ///         name: "Vertices",
+    ///         properties: Default::default(),
+    ///     })
+    ///     .unwrap();
+    /// writer.begin_array_property::<f64>().unwrap();
+    /// for i in 0..1000 {
+    ///     writer.push_f64(f64::from(i) * 0.5).unwrap();
+    /// }
+    /// writer.end_array_property().unwrap();
+    /// writer.write(FbxEvent::EndNode).unwrap();
+    /// writer.write(FbxEvent::EndFbx).unwrap();
+    /// ```
+    pub fn begin_array_property<T: ArrayElement>(&mut self) -> Result<()> {
+        self.emitter
+            .begin_array_property(&mut self.sink, T::TYPE_CODE)
+    }
+
+    /// Writes an element of the boolean array property being written.
+    pub fn push_bool(&mut self, value: bool) -> Result<()> {
+        self.emitter
+            .push_array_element(&mut self.sink, bool::TYPE_CODE, &[u8::from(value)])
+    }
+
+    /// Writes an element of the `i32` array property being written.
+    pub fn push_i32(&mut self, value: i32) -> Result<()> {
+        self.emitter
+            .push_array_element(&mut self.sink, i32::TYPE_CODE, &value.to_le_bytes())
+    }
+
+    /// Writes an element of the `i64` array property being written.
+    pub fn push_i64(&mut self, value: i64) -> Result<()> {
+        self.emitter
+            .push_array_element(&mut self.sink, i64::TYPE_CODE, &value.to_le_bytes())
+    }
+
+    /// Writes an element of the `f32` array property being written.
+    pub fn push_f32(&mut self, value: f32) -> Result<()> {
+        self.emitter
+            .push_array_element(&mut self.sink, f32::TYPE_CODE, &value.to_le_bytes())
+    }
+
+    /// Writes an element of the `f64` array property being written.
+    pub fn push_f64(&mut self, value: f64) -> Result<()> {
+        self.emitter
+            .push_array_element(&mut self.sink, f64::TYPE_CODE, &value.to_le_bytes())
+    }
+
+    /// Ends the array property being written, and updates the node header.
+    pub fn end_array_property(&mut self) -> Result<()> {
+        self.emitter.end_array_property(&mut self.sink)
+    }
+}
+
+impl<W: Read + Write + Seek> EventWriter<W> {
+    /// Creates a new writer to append top-level nodes to existing Binary FBX.
+    ///
+    /// The existing data is read from the beginning of the sink, and the writer continues
+    /// emitting right after the last existing top-level node.
+    /// The null record of the implicit root node and the footer are overwritten, and they are
+    /// written again by `EndFbx` event.
+    /// `StartFbx` event should not be given to the returned writer.
+    ///
+    /// If the sink is a file, it may have stale data of the old footer after the new end when
+    /// no nodes are appended; truncate the file to the position after `EndFbx` if necessary.
+    ///
+    /// Returns `Error::Reader` if the existing data is not readable, and
+    /// `Error::Unimplemented` if the existing data is ASCII FBX.
+    pub fn append(mut sink: W, config: EmitterConfig) -> Result<Self> {
+        sink.seek(SeekFrom::Start(0))?;
+        let mut reader = EventReader::new(&mut sink);
+        let version = loop {
+            match reader.next()? {
+                reader::FbxEvent::StartFbx(FbxFormatType::Ascii) => {
+                    return Err(Error::Unimplemented(
+                        "Appending to ASCII FBX is not supported".to_string(),
+                    ));
+                }
+                reader::FbxEvent::EndFbx => {
+                    break reader
+                        .fbx_version()
+                        .expect("FBX version should be known after `StartFbx`");
+                }
+                _ => {}
+            }
+        };
+        let root_end = reader.position();
+        let emitter = emitter::Emitter::new_binary_appending(&mut sink, config, version, root_end)?;
+        Ok(EventWriter { sink, emitter })
+    }
+}
+
+impl EventWriter<Cursor<Vec<u8>>> {
+    /// Creates a new writer which emits FBX data into a `Vec<u8>`.
+    ///
+    /// Use [`into_vec`](#method.into_vec) to get the written data.
+    pub fn to_vec() -> Self {
+        EventWriter::new(Cursor::new(Vec::new()))
+    }
+
+    /// Consumes the writer and returns the written data.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.sink.into_inner()
+    }
+}
+
+impl<W: Write> EventWriter<BufferedSink<W>> {
+    /// Creates a new writer which emits FBX data to a non-seekable writer.
+    ///
+    /// The whole FBX data is kept in memory, and is written to the given writer when
+    /// [`into_buffered_inner`](#method.into_buffered_inner) is called.
+    pub fn new_buffered(sink: W) -> Self {
+        EventWriter::new(BufferedSink::new(sink))
+    }
+
+    /// Writes all buffered data to the inner writer, and returns the inner writer.
+    pub fn into_buffered_inner(self) -> Result<W> {
+        Ok(self.sink.into_inner()?)
+    }
+}
+
+impl<W: Write> EventWriter<TempFileSink<W>> {
+    /// Creates a new writer which emits FBX data to a non-seekable writer through a temporary
+    /// file.
+    ///
+    /// The whole FBX data is kept in a temporary file, and is written to the given writer when
+    /// [`into_temp_file_inner`](#method.into_temp_file_inner) is called.
+    /// Use this instead of [`new_buffered`](#method.new_buffered) when the data is too large to
+    /// keep in memory.
+    pub fn new_with_temp_file(sink: W) -> Result<Self> {
+        Ok(EventWriter::new(TempFileSink::new(sink)?))
+    }
+
+    /// Writes all data in the temporary file to the inner writer, and returns the inner writer.
+    pub fn into_temp_file_inner(self) -> Result<W> {
+        Ok(self.sink.into_inner()?)
+    }
 }
 
+impl EventWriter<BufferedSink<Vec<u8>>> {
+    /// Creates a new writer which emits FBX data into a `Vec<u8>` through non-seekable path.
+    ///
+    /// This produces the same data as [`to_vec`](#method.to_vec).
+    pub fn to_vec_buffered() -> Self {
+        EventWriter::new_buffered(Vec::new())
+    }
+
+    /// Consumes the writer and returns the written data.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.sink
+            .into_inner()
+            .expect("Writing to `Vec<u8>` should never fail")
+    }
+}
+
+/// A callback invoked when a node is written.
+///
+/// Arguments are the node name, the start position (inclusive), and the end position (exclusive)
+/// of the node record in the sink.
+pub type NodeWrittenCallback = Arc<dyn Fn(&str, u64, u64) + Send + Sync>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EmitterConfig {
     pub ignore_minor_errors: bool,
     pub fbx_version: Option<u32>,
+    /// Omits nodes without properties and children.
+    ///
+    /// When this is enabled, `StartNode` without properties is not emitted immediately, but
+    /// buffered until the next event is given (one-event lookahead).
+    /// If the next event is `EndNode`, the node is omitted entirely.
+    /// Note that a node whose children are all omitted is not omitted, because it is already
+    /// emitted when the first child starts.
+    pub skip_empty_nodes: bool,
+    /// Contents of the reserved area in the footer of Binary FBX.
+    ///
+    /// If `None` (default), the area is filled with zero.
+    /// To reproduce the source byte-exactly, use `reserved` of
+    /// [`reader::EventReader::footer_info`](../reader/struct.EventReader.html#method.footer_info).
+    pub footer_reserved: Option<[u8; FOOTER_RESERVED_LEN]>,
+    /// Compresses array properties of Binary FBX with zlib.
+    ///
+    /// Default is `true`. If `false`, arrays are written as plain data.
+    pub compress_arrays: bool,
+    /// Pads the footer of Binary FBX so that the file size is a multiple of 16, as the official
+    /// SDK does.
+    ///
+    /// Default is `true`. In this case, 1 to 16 zero bytes are written after the first part of
+    /// the footer (16 bytes even if the position is already aligned, as official exporters do).
+    /// If `false`, no padding is written and the file size is not aligned. Some importers may
+    /// reject such files, but this is what some third-party exporters produce.
+    pub pad_to_16: bool,
+    /// Terminates the implicit root node of Binary FBX explicitly with a null record.
+    ///
+    /// Default is `true`. In this case, a null record (13 zero bytes for FBX 7.4 and earlier, or
+    /// 25 zero bytes for FBX 7.5 and later) is written after the last top-level node, as the
+    /// official SDK does.
+    /// If `false`, the footer follows the last top-level node directly, so the output is 13 or
+    /// 25 bytes shorter (except for the padding of `pad_to_16`). This is what some third-party
+    /// exporters produce. The reader of this crate accepts both.
+    pub root_null_record: bool,
+    /// Indentation string of a nesting level in ASCII FBX.
+    ///
+    /// Default is a tab (`"\t"`). This should consist only of spaces and tabs, otherwise
+    /// `Error::InvalidOption` is returned when ASCII FBX is started.
+    pub ascii_indent: &'static str,
+    /// Line ending of ASCII FBX.
+    ///
+    /// Default is `LineEnding::Lf`.
+    pub line_ending: LineEnding,
+    /// Rejects NaN and infinity in floating point properties (including arrays).
+    ///
+    /// Such values are technically writable, but some importers crash on them.
+    /// If `true`, `Error::DataError` is returned for them. Compressed arrays given as
+    /// `Property::CompressedArray` are written as is and not checked.
+    /// Default is `false`.
+    pub reject_non_finite: bool,
+    /// Compresses array properties of Binary FBX with zstd instead of zlib.
+    ///
+    /// This uses non-standard encoding
+    /// [`NONSTANDARD_ZSTD_ARRAY_ENCODING`](../common/constant.NONSTANDARD_ZSTD_ARRAY_ENCODING.html),
+    /// so the output cannot be read by standard FBX tools.
+    /// Default is `false`. This is ignored if `compress_arrays` is `false`.
+    ///
+    /// This requires `zstd` feature. Without the feature, `Error::InvalidOption` is returned
+    /// when array properties are written with this option enabled.
+    pub nonstandard_zstd_arrays: bool,
 }
 
 impl EmitterConfig {
@@ -53,6 +544,15 @@ impl EmitterConfig {
         EmitterConfig {
             ignore_minor_errors: true,
             fbx_version: None,
+            skip_empty_nodes: false,
+            footer_reserved: None,
+            compress_arrays: true,
+            pad_to_16: true,
+            root_null_record: true,
+            ascii_indent: "\t",
+            line_ending: LineEnding::Lf,
+            reject_non_finite: false,
+            nonstandard_zstd_arrays: false,
         }
     }
 
@@ -72,6 +572,69 @@ impl EmitterConfig {
         self.fbx_version = value;
         self
     }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn skip_empty_nodes(mut self, value: bool) -> Self {
+        self.skip_empty_nodes = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn footer_reserved(mut self, value: Option<[u8; FOOTER_RESERVED_LEN]>) -> Self {
+        self.footer_reserved = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn compress_arrays(mut self, value: bool) -> Self {
+        self.compress_arrays = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn pad_to_16(mut self, value: bool) -> Self {
+        self.pad_to_16 = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn root_null_record(mut self, value: bool) -> Self {
+        self.root_null_record = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn ascii_indent(mut self, value: &'static str) -> Self {
+        self.ascii_indent = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn line_ending(mut self, value: LineEnding) -> Self {
+        self.line_ending = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn reject_non_finite(mut self, value: bool) -> Self {
+        self.reject_non_finite = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated config object.
+    pub fn nonstandard_zstd_arrays(mut self, value: bool) -> Self {
+        self.nonstandard_zstd_arrays = value;
+        self
+    }
+}
+
+/// Line ending of text output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`.
+    Lf,
+    /// `\r\n`.
+    CrLf,
 }
 
 impl Default for EmitterConfig {
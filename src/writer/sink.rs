@@ -0,0 +1,147 @@
+//! Contains sinks to emit FBX data to non-seekable destinations.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A seekable in-memory buffer which writes all data to the inner writer at once.
+///
+/// Binary FBX writer requires `Seek` to update node headers after writing node contents.
+/// This wraps a non-seekable writer (such as a pipe) to make it usable as a sink, by keeping
+/// the whole FBX data in memory until [`into_inner`](#method.into_inner) is called.
+#[derive(Debug, Clone)]
+pub struct BufferedSink<W: Write> {
+    /// Buffered data.
+    buffer: Cursor<Vec<u8>>,
+    /// Final destination.
+    inner: W,
+}
+
+impl<W: Write> BufferedSink<W> {
+    /// Creates a new buffered sink.
+    pub fn new(inner: W) -> Self {
+        BufferedSink {
+            buffer: Cursor::new(Vec::new()),
+            inner,
+        }
+    }
+
+    /// Returns data buffered so far.
+    pub fn buffered(&self) -> &[u8] {
+        self.buffer.get_ref()
+    }
+
+    /// Writes all buffered data to the inner writer, and returns the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.inner.write_all(self.buffer.get_ref())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BufferedSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.write(buf)
+    }
+
+    /// Does nothing, because buffered data can be modified until the end.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> Seek for BufferedSink<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.buffer.seek(pos)
+    }
+}
+
+/// A seekable sink backed by a temporary file, which copies all data to the inner writer at once.
+///
+/// This works as [`BufferedSink`](struct.BufferedSink.html), but keeps the data in a temporary
+/// file instead of memory, so it is suitable for very large exports to a non-seekable writer
+/// (such as a socket).
+/// The temporary file is created in `std::env::temp_dir()`, and is removed when the sink is
+/// dropped.
+#[derive(Debug)]
+pub struct TempFileSink<W: Write> {
+    /// Temporary file.
+    file: File,
+    /// Path to the temporary file.
+    path: PathBuf,
+    /// Final destination.
+    ///
+    /// This is `None` only after the sink is consumed.
+    inner: Option<W>,
+}
+
+impl<W: Write> TempFileSink<W> {
+    /// Creates a new sink with a new temporary file.
+    pub fn new(inner: W) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir();
+        loop {
+            let path = dir.join(format!(
+                "fbx_direct-{}-{}.tmp",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => {
+                    return Ok(TempFileSink {
+                        file,
+                        path,
+                        inner: Some(inner),
+                    })
+                }
+                // Probably left by another process with the same id. Try another name.
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Copies all data in the temporary file to the inner writer, and returns the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        let mut inner = self
+            .inner
+            .take()
+            .expect("Inner writer should exist until the sink is consumed");
+        self.file.seek(SeekFrom::Start(0))?;
+        io::copy(&mut self.file, &mut inner)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for TempFileSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    /// Does nothing, because written data can be modified until the end.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> Seek for TempFileSink<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl<W: Write> Drop for TempFileSink<W> {
+    fn drop(&mut self) {
+        // The file may be already removed by others, and there is nothing to do for failure.
+        let _ = fs::remove_file(&self.path);
+    }
+}
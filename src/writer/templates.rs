@@ -0,0 +1,243 @@
+//! Contains templates of common FBX nodes.
+//!
+//! This is an optional convenience for authoring FBX data procedurally. Templates write nodes
+//! and properties in the structure the official SDK and common exporters produce, using the
+//! usual `StartNode` and `EndNode` events, so they can be freely mixed with other events.
+//! Templates do not validate data (such as uniqueness of object IDs), and they do not write
+//! other nodes required by importers (such as `Connections`).
+//!
+//! # Examples
+//!
+//! ```
+//! use fbx_direct::common::FbxFormatType;
+//! use fbx_direct::writer::templates::{Geometry, Model};
+//! use fbx_direct::writer::{node, EventWriter, FbxEvent};
+//!
+//! let vertices = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+//! let polygon_vertex_index = [0, 1, -3];
+//!
+//! let mut writer = EventWriter::to_vec();
+//! writer.write(FbxEvent::StartFbx(FbxFormatType::Binary(7400))).unwrap();
+//! writer.write(node("Objects")).unwrap();
+//! Geometry::new(1, "Triangle", &vertices, &polygon_vertex_index)
+//!     .write(&mut writer)
+//!     .unwrap();
+//! Model::new(2, "Triangle", "Mesh")
+//!     .translation([0.0, 0.0, 10.0])
+//!     .write(&mut writer)
+//!     .unwrap();
+//! writer.write(FbxEvent::EndNode).unwrap();
+//! writer.write(FbxEvent::EndFbx).unwrap();
+//! ```
+
+use crate::common::{FbxFormatType, Property};
+use crate::writer::error::Result;
+use crate::writer::{EventWriter, FbxEvent};
+use std::io::{Seek, Write};
+
+/// Writes a `P` node, which is a child of `Properties70` node.
+///
+/// `P` node has the property name, the type name (such as `"double"` and `"Lcl Translation"`),
+/// the label (such as `"Number"`), the flags (such as `"A"` for animatable), and the values.
+pub fn write_p<W: Write + Seek>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    type_name: &str,
+    label: &str,
+    flags: &str,
+    values: &[Property<'_>],
+) -> Result<()> {
+    let mut properties = vec![
+        Property::String(name),
+        Property::String(type_name),
+        Property::String(label),
+        Property::String(flags),
+    ];
+    properties.extend_from_slice(values);
+    writer.write(FbxEvent::StartNode {
+        name: "P",
+        properties: properties.into(),
+    })?;
+    writer.write(FbxEvent::EndNode)
+}
+
+/// Encodes polygons into polygon vertex indices, such as `PolygonVertexIndex` of `Geometry`.
+///
+/// The last index of each polygon is stored as the bitwise NOT of the index (i.e.
+/// `-index - 1`), which marks the boundary of polygons. This is the inverse of
+/// [`OwnedProperty::decode_polygons`](../../common/enum.OwnedProperty.html#method.decode_polygons).
+/// Indices should be non-negative. Empty polygons are not representable, and they are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use fbx_direct::writer::templates::encode_polygons;
+///
+/// let polygons = [vec![0, 1, 2], vec![2, 3, 4, 0]];
+/// assert_eq!(encode_polygons(&polygons), vec![0, 1, -3, 2, 3, 4, -1]);
+/// ```
+pub fn encode_polygons(polygons: &[Vec<i32>]) -> Vec<i32> {
+    let mut indices = Vec::with_capacity(polygons.iter().map(Vec::len).sum());
+    for polygon in polygons {
+        if let Some((&last, rest)) = polygon.split_last() {
+            indices.extend_from_slice(rest);
+            indices.push(!last);
+        }
+    }
+    indices
+}
+
+/// Returns the name of an object node in the format being written.
+///
+/// Binary FBX uses `"name\u{0}\u{1}class"`, and ASCII FBX uses `"class::name"`.
+fn object_name<W: Write + Seek>(writer: &EventWriter<W>, name: &str, class: &str) -> String {
+    match writer.emitter.format_type() {
+        Some(FbxFormatType::Ascii) => format!("{}::{}", class, name),
+        _ => format!("{}\u{0}\u{1}{}", name, class),
+    }
+}
+
+/// Writes a node with a single property and no children.
+fn write_leaf<W: Write + Seek>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    property: Property<'_>,
+) -> Result<()> {
+    writer.write_start_node(name, &[property])?;
+    writer.write(FbxEvent::EndNode)
+}
+
+/// Template of `Model` node, which is an object in the scene with the local transform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Object name (without the class name).
+    pub name: &'a str,
+    /// Type of the model, such as `"Mesh"`, `"Null"`, `"Camera"`, and `"Light"`.
+    pub kind: &'a str,
+    /// Local translation (`Lcl Translation`).
+    pub translation: [f64; 3],
+    /// Local rotation in degrees (`Lcl Rotation`).
+    pub rotation: [f64; 3],
+    /// Local scaling (`Lcl Scaling`).
+    pub scaling: [f64; 3],
+}
+
+impl<'a> Model<'a> {
+    /// Creates a template with the identity transform.
+    pub fn new(id: i64, name: &'a str, kind: &'a str) -> Self {
+        Model {
+            id,
+            name,
+            kind,
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scaling: [1.0; 3],
+        }
+    }
+
+    /// Sets the field to provided value and returns updated template.
+    pub fn translation(mut self, value: [f64; 3]) -> Self {
+        self.translation = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated template.
+    pub fn rotation(mut self, value: [f64; 3]) -> Self {
+        self.rotation = value;
+        self
+    }
+
+    /// Sets the field to provided value and returns updated template.
+    pub fn scaling(mut self, value: [f64; 3]) -> Self {
+        self.scaling = value;
+        self
+    }
+
+    /// Writes the `Model` node with its children.
+    pub fn write<W: Write + Seek>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        let name = object_name(writer, self.name, "Model");
+        writer.write_start_node(
+            "Model",
+            &[
+                Property::I64(self.id),
+                Property::String(&name),
+                Property::String(self.kind),
+            ],
+        )?;
+        write_leaf(writer, "Version", Property::I32(232))?;
+        writer.write_start_node("Properties70", &[])?;
+        for &(prop_name, value) in &[
+            ("Lcl Translation", self.translation),
+            ("Lcl Rotation", self.rotation),
+            ("Lcl Scaling", self.scaling),
+        ] {
+            let values = [
+                Property::F64(value[0]),
+                Property::F64(value[1]),
+                Property::F64(value[2]),
+            ];
+            write_p(writer, prop_name, prop_name, "", "A", &values)?;
+        }
+        writer.write(FbxEvent::EndNode)?;
+        write_leaf(writer, "Shading", Property::Bool(true))?;
+        write_leaf(writer, "Culling", Property::String("CullingOff"))?;
+        writer.write(FbxEvent::EndNode)
+    }
+}
+
+/// Template of `Geometry` node of a polygon mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Object name (without the class name).
+    pub name: &'a str,
+    /// Coordinates of the vertices (`Vertices`), as `[x0, y0, z0, x1, y1, z1, ...]`.
+    pub vertices: &'a [f64],
+    /// Vertex indices of the polygons (`PolygonVertexIndex`).
+    ///
+    /// The last index of each polygon is stored as the bitwise NOT of the index (i.e.
+    /// `-index - 1`). This can be created from polygons by
+    /// [`encode_polygons`](fn.encode_polygons.html).
+    pub polygon_vertex_index: &'a [i32],
+}
+
+impl<'a> Geometry<'a> {
+    /// Creates a template.
+    pub fn new(
+        id: i64,
+        name: &'a str,
+        vertices: &'a [f64],
+        polygon_vertex_index: &'a [i32],
+    ) -> Self {
+        Geometry {
+            id,
+            name,
+            vertices,
+            polygon_vertex_index,
+        }
+    }
+
+    /// Writes the `Geometry` node with its children.
+    pub fn write<W: Write + Seek>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        let name = object_name(writer, self.name, "Geometry");
+        writer.write_start_node(
+            "Geometry",
+            &[
+                Property::I64(self.id),
+                Property::String(&name),
+                Property::String("Mesh"),
+            ],
+        )?;
+        write_leaf(writer, "Vertices", Property::VecF64(self.vertices))?;
+        write_leaf(
+            writer,
+            "PolygonVertexIndex",
+            Property::VecI32(self.polygon_vertex_index),
+        )?;
+        write_leaf(writer, "GeometryVersion", Property::I32(124))?;
+        writer.write(FbxEvent::EndNode)
+    }
+}
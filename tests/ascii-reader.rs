@@ -0,0 +1,468 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Seek, SeekFrom};
+use std::rc::Rc;
+
+use fbx_direct::common::{FbxFormatType, OwnedProperty};
+use fbx_direct::reader::{AnimationTake, Arena, ErrorKind, EventReader, FbxEvent, ParserConfig};
+
+mod common;
+
+use common::{FlakyReader, SYNTHETIC_ASCII_FBX7400};
+
+fn read_events<R: std::io::Read>(reader: EventReader<R>) -> Vec<FbxEvent> {
+    reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+fn comment(event: &FbxEvent) -> &str {
+    match *event {
+        FbxEvent::Comment(ref comment) => comment,
+        ref e => panic!("expected comment but got {:?}", e),
+    }
+}
+
+const COMMENTS_ONLY: &str = "; FBX 7.4.0 project file\n\
+                             \n\
+                             ; first\n\
+                             ; second\r\n\
+                             ;third\n";
+
+#[test]
+fn read_comments() {
+    let events = read_events(EventReader::new(Cursor::new(COMMENTS_ONLY)));
+    assert_eq!(events.len(), 6);
+    assert!(matches!(
+        events[0],
+        FbxEvent::StartFbx(FbxFormatType::Ascii)
+    ));
+    assert_eq!(comment(&events[1]), " FBX 7.4.0 project file");
+    assert_eq!(comment(&events[2]), " first");
+    assert_eq!(comment(&events[3]), " second");
+    assert_eq!(comment(&events[4]), "third");
+    assert!(matches!(events[5], FbxEvent::EndFbx));
+}
+
+#[test]
+fn merge_comments() {
+    let reader = ParserConfig::new()
+        .merge_comments(true)
+        .create_reader(Cursor::new(COMMENTS_ONLY));
+    let events = read_events(reader);
+    assert_eq!(events.len(), 4);
+    assert_eq!(comment(&events[1]), " FBX 7.4.0 project file");
+    assert_eq!(comment(&events[2]), " first\n second\nthird");
+    assert!(matches!(events[3], FbxEvent::EndFbx));
+}
+
+#[test]
+fn fbx_version() {
+    let mut reader = EventReader::new(Cursor::new(COMMENTS_ONLY));
+    assert_eq!(reader.fbx_version(), None);
+    reader.next().unwrap();
+    assert_eq!(reader.fbx_version(), Some(7400));
+    while !matches!(reader.next().unwrap(), FbxEvent::EndFbx) {}
+    assert_eq!(reader.fbx_version(), Some(7400));
+
+    let mut reader = EventReader::new(Cursor::new("; no version here\n"));
+    reader.next().unwrap();
+    assert_eq!(reader.fbx_version(), None);
+}
+
+fn start_node(event: &FbxEvent) -> (&str, &[OwnedProperty]) {
+    match *event {
+        FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        } => (name, properties),
+        ref e => panic!("expected start of node but got {:?}", e),
+    }
+}
+
+#[test]
+fn read_nodes() {
+    const SOURCE: &str = "; FBX 7.4.0 project file\n\
+                          Parent: \"Name&quot;&lf;\", -3, 4294967296, 0.5, Y, T {\n\
+                          \tEmpty:  {\n\
+                          \t}\n\
+                          \tArray: *3 {\n\
+                          \t\ta: 1,-2,3\n\
+                          \t}\n\
+                          \tFloats: *2 {\n\
+                          \t\ta: 1,2.5\n\
+                          \t} ; floats\n\
+                          \tBools: *3 {\n\
+                          \t\ta: Y,T,Y\n\
+                          \t}\n\
+                          \tNone: *0 {\n\
+                          \t\ta: \n\
+                          \t}\n\
+                          }\n";
+    let reader = ParserConfig::new()
+        .ignore_comments(true)
+        .create_reader(Cursor::new(SOURCE));
+    let events = read_events(reader);
+    assert_eq!(events.len(), 14);
+    assert_eq!(
+        start_node(&events[1]),
+        (
+            "Parent",
+            &[
+                OwnedProperty::String("Name\"\n".to_string()),
+                OwnedProperty::I32(-3),
+                OwnedProperty::I64(4_294_967_296),
+                OwnedProperty::F64(0.5),
+                OwnedProperty::Bool(true),
+                OwnedProperty::Bool(false),
+            ][..]
+        )
+    );
+    assert_eq!(start_node(&events[2]), ("Empty", &[][..]));
+    assert!(matches!(events[3], FbxEvent::EndNode));
+    assert_eq!(
+        start_node(&events[4]),
+        ("Array", &[OwnedProperty::VecI32(vec![1, -2, 3])][..])
+    );
+    assert_eq!(
+        start_node(&events[6]),
+        ("Floats", &[OwnedProperty::VecF64(vec![1.0, 2.5])][..])
+    );
+    assert_eq!(
+        start_node(&events[8]),
+        (
+            "Bools",
+            &[OwnedProperty::VecBool(vec![true, false, true])][..]
+        )
+    );
+    assert_eq!(
+        start_node(&events[10]),
+        ("None", &[OwnedProperty::VecI32(vec![])][..])
+    );
+    assert!(matches!(events[11], FbxEvent::EndNode));
+    assert!(matches!(events[12], FbxEvent::EndNode));
+    assert!(matches!(events[13], FbxEvent::EndFbx));
+}
+
+#[test]
+fn synthetic_file() {
+    // Hand-written in the layout of ASCII FBX exported by FBX SDK, including arrays and
+    // properties continued on the next line.
+    let file = std::fs::File::open(SYNTHETIC_ASCII_FBX7400).unwrap();
+    let mut reader = ParserConfig::new().strict(true).create_reader(file);
+    let arena = Arena::read(&mut reader).unwrap();
+    let path = |names: &[&str]| {
+        names.iter().fold(arena.root(), |index, name| {
+            arena.child_by_name(index, name).unwrap()
+        })
+    };
+    let properties = |names: &[&str]| &arena.node(path(names)).properties;
+
+    assert_eq!(reader.fbx_version(), Some(7400));
+    assert_eq!(arena.creation_time_stamp().unwrap().millisecond, 412);
+    match properties(&["Objects", "Geometry", "Vertices"])[..] {
+        [OwnedProperty::VecF64(ref v)] => {
+            assert_eq!(v.len(), 24);
+            assert_eq!(v[15..17], [0.5, 0.5]);
+        }
+        ref props => panic!("unexpected properties: {:?}", props),
+    }
+    match properties(&["Objects", "Geometry", "PolygonVertexIndex"])[..] {
+        [OwnedProperty::VecI32(ref v)] => {
+            assert_eq!(v.len(), 24);
+            assert_eq!(v[22..], [2, -5]);
+        }
+        ref props => panic!("unexpected properties: {:?}", props),
+    }
+    let model_props = path(&["Objects", "Model", "Properties70"]);
+    let scaling = arena.children(model_props).nth(2).unwrap();
+    assert_eq!(arena.node(scaling).properties.len(), 7);
+    assert_eq!(arena.node(scaling).properties[6], OwnedProperty::I32(1));
+    assert_eq!(
+        properties(&["Objects", "Video", "Content"])[..],
+        [OwnedProperty::String("iVBORw0KGgo=".to_string())]
+    );
+    assert_eq!(
+        properties(&["Objects", "Model", "Shading"])[..],
+        [OwnedProperty::Bool(false)]
+    );
+    assert_eq!(arena.children(path(&["Connections"])).count(), 2);
+}
+
+#[test]
+fn find_node() {
+    let open = || EventReader::new(std::fs::File::open(SYNTHETIC_ASCII_FBX7400).unwrap());
+    let vertices = open()
+        .find_node(&["Objects", "Geometry", "Vertices"])
+        .unwrap()
+        .unwrap();
+    assert!(matches!(vertices[..], [OwnedProperty::VecF64(ref v)] if v.len() == 24));
+    assert_eq!(open().find_node(&["Objects", "NoSuchNode"]).unwrap(), None);
+}
+
+#[test]
+fn current_node_has_children() {
+    const SOURCE: &str = "; FBX 7.4.0 project file\n\
+                          Parent:  {\n\
+                          \tLeaf: 1\n\
+                          }\n";
+    let mut reader = EventReader::new(Cursor::new(SOURCE));
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert_eq!(reader.current_node_has_children(), None);
+    assert_eq!(start_node(&reader.next().unwrap()).0, "Parent");
+    // Children of nodes with braces are unknown until the following lines are read.
+    assert_eq!(reader.current_node_has_children(), None);
+    assert_eq!(start_node(&reader.next().unwrap()).0, "Leaf");
+    assert_eq!(reader.current_node_has_children(), Some(false));
+    assert!(matches!(reader.next().unwrap(), FbxEvent::EndNode));
+    assert_eq!(reader.current_node_has_children(), None);
+}
+
+#[test]
+fn max_depth() {
+    let source = format!(
+        "; FBX 7.4.0 project file\n{}{}",
+        "Nested:  {\n".repeat(3),
+        "}\n".repeat(3)
+    );
+    let read_with = |limit| {
+        ParserConfig::new()
+            .max_depth(limit)
+            .create_reader(Cursor::new(source.as_bytes()))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+    };
+    assert!(read_with(Some(3)).is_ok());
+    let err = read_with(Some(2)).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::DataError(_)));
+    assert_eq!(
+        err.pos(),
+        source.match_indices("Nested").nth(2).unwrap().0 as u64
+    );
+}
+
+#[test]
+fn reject_invalid_nodes() {
+    for source in &[
+        "Node: 1\n}\n",
+        "Node: *2 {\n\ta: 1\n}\n",
+        "Node: \"unterminated\n",
+        "Node: 1 2\n",
+        "Node:  {\n",
+    ] {
+        let source = format!("; FBX 7.4.0 project file\n{}", source);
+        let result = EventReader::new(Cursor::new(source.as_bytes()))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>();
+        assert!(result.is_err(), "source: {:?}", source);
+    }
+}
+
+#[test]
+fn positions_with_ignored_comments() {
+    const SOURCE: &str = "; FBX 7.4.0 project file\n\
+                          ; comment\n\
+                          \n\
+                          Node 1\n";
+    let node_pos = SOURCE.find("Node").unwrap() as u64;
+
+    // Positions after comments.
+    let mut reader = EventReader::new(Cursor::new(SOURCE));
+    reader.next().unwrap();
+    assert_eq!(reader.position(), 0);
+    assert_eq!(comment(&reader.next().unwrap()), " FBX 7.4.0 project file");
+    assert_eq!(reader.position(), SOURCE.find("; comment").unwrap() as u64);
+    assert_eq!(comment(&reader.next().unwrap()), " comment");
+    assert_eq!(reader.position(), SOURCE.find("\n\n").unwrap() as u64 + 1);
+
+    // The following line should be reported at the same position, whether comments are ignored
+    // or merged.
+    for &(ignore, merge) in &[(false, false), (true, false), (false, true), (true, true)] {
+        let mut reader = ParserConfig::new()
+            .ignore_comments(ignore)
+            .merge_comments(merge)
+            .create_reader(Cursor::new(SOURCE));
+        let mut last_pos = 0;
+        let err = loop {
+            match reader.next() {
+                Ok(_) => {
+                    assert!(reader.position() >= last_pos);
+                    last_pos = reader.position();
+                }
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(
+            err.pos(),
+            node_pos,
+            "ignore_comments = {}, merge_comments = {}",
+            ignore,
+            merge
+        );
+        assert!(last_pos <= node_pos);
+    }
+}
+
+#[test]
+fn syntax_error_shows_line() {
+    let source = format!(
+        "; FBX 7.4.0 project file\n  FBXHeaderExtension  {{\n{} 1\n",
+        "X".repeat(100)
+    );
+    for (src, expected) in &[
+        (&source[..], "\"FBXHeaderExtension  {\""),
+        (
+            &source[source.find("XXX").unwrap()..],
+            &format!("\"{}...\"", "X".repeat(64))[..],
+        ),
+    ] {
+        let mut reader = ParserConfig::new()
+            .ignore_comments(true)
+            .create_reader(Cursor::new(src.as_bytes()));
+        assert!(matches!(
+            reader.next().unwrap(),
+            FbxEvent::StartFbx(FbxFormatType::Ascii)
+        ));
+        let err = reader.next().unwrap_err();
+        match *err.kind() {
+            ErrorKind::DataError(ref msg) => {
+                assert!(msg.contains(expected), "message: {:?}", msg)
+            }
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+}
+
+#[test]
+fn binary_after_ascii_header() {
+    let mut source = b"; FBX 7.4.0 project file\n".to_vec();
+    let binary_pos = source.len() as u64;
+    source.extend_from_slice(b"\x1b\x00\x00\x00\x01\x00\x00\x00\x05\x00\x00\x00\x04Node\n");
+    let mut reader = EventReader::new(Cursor::new(source));
+    reader.next().unwrap();
+    reader.next().unwrap();
+    let err = reader.next().unwrap_err();
+    assert_eq!(err.pos(), binary_pos);
+    match *err.kind() {
+        ErrorKind::DataError(ref msg) => assert!(msg.contains("Binary FBX"), "{}", msg),
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn reject_too_long_line() {
+    let mut source = b"; FBX 7.4.0 project file\n".to_vec();
+    let line_pos = source.len() as u64;
+    source.resize(source.len() + 16 * 1024 * 1024 + 1, b';');
+    source.push(b'\n');
+    let mut reader = EventReader::new(Cursor::new(source));
+    reader.next().unwrap();
+    reader.next().unwrap();
+    let err = reader.next().unwrap_err();
+    assert_eq!(err.pos(), line_pos);
+    assert!(matches!(*err.kind(), ErrorKind::DataError(_)));
+}
+
+#[test]
+fn animation_takes() {
+    let source = "; FBX 7.4.0 project file\n\
+                  Objects:  {\n\
+                  \tAnimationStack: 1, \"AnimStack::Walk\", \"\" {\n\
+                  \t\tProperties70:  {\n\
+                  \t\t\tP: \"LocalStart\", \"KTime\", \"Time\", \"\",1924423250\n\
+                  \t\t\tP: \"LocalStop\", \"KTime\", \"Time\", \"\",46186158000\n\
+                  \t\t}\n\
+                  \t}\n\
+                  \tAnimationStack: 2, \"AnimStack::Idle\", \"\" {\n\
+                  \t}\n\
+                  }\n\
+                  Takes:  {\n\
+                  \tCurrent: \"Walk\"\n\
+                  \tTake: \"Walk\" {\n\
+                  \t\tLocalTime: 0,1\n\
+                  \t}\n\
+                  }\n";
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(source))).unwrap();
+    assert_eq!(
+        arena.animation_takes(),
+        [
+            AnimationTake {
+                name: "Walk".to_string(),
+                local_time: Some((1_924_423_250, 46_186_158_000)),
+                reference_time: None,
+            },
+            AnimationTake {
+                name: "Idle".to_string(),
+                local_time: None,
+                reference_time: None,
+            },
+        ]
+    );
+
+    // Takes are used if there are no animation stacks.
+    let source = "; FBX 6.1.0 project file\n\
+                  Takes:  {\n\
+                  \tCurrent: \"Take 001\"\n\
+                  \tTake: \"Take 001\" {\n\
+                  \t\tFileName: \"Take_001.tak\"\n\
+                  \t\tLocalTime: 0,46186158000\n\
+                  \t\tReferenceTime: 0,46186158000\n\
+                  \t}\n\
+                  }\n";
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(source))).unwrap();
+    assert_eq!(
+        arena.animation_takes(),
+        [AnimationTake {
+            name: "Take 001".to_string(),
+            local_time: Some((0, 46_186_158_000)),
+            reference_time: Some((0, 46_186_158_000)),
+        }]
+    );
+
+    let source = "; FBX 7.4.0 project file\nTakes:  {\n\tCurrent: \"\"\n}\n";
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(source))).unwrap();
+    assert!(arena.animation_takes().is_empty());
+}
+
+#[test]
+fn try_next_after_transient_error() {
+    let data = std::fs::read(SYNTHETIC_ASCII_FBX7400).unwrap();
+    let expected = EventReader::new(Cursor::new(&data)).read_all(None).unwrap();
+
+    for fail_at in 1..data.len() as u64 {
+        let inner = Rc::new(RefCell::new(Cursor::new(data.clone())));
+        // Small buffer to fail in the middle of the data.
+        let mut reader = ParserConfig::new()
+            .read_buffer_size(16)
+            .create_reader(FlakyReader {
+                inner: inner.clone(),
+                fail_at,
+                failed: false,
+            });
+        let mut events = Vec::new();
+        let mut retried = false;
+        loop {
+            match reader.try_next() {
+                Ok(FbxEvent::EndFbx) => {
+                    events.push(FbxEvent::EndFbx);
+                    break;
+                }
+                Ok(event) => events.push(event),
+                Err(err) => {
+                    assert!(err.is_retryable());
+                    assert!(!retried);
+                    retried = true;
+                    inner
+                        .borrow_mut()
+                        .seek(SeekFrom::Start(reader.position()))
+                        .unwrap();
+                }
+            }
+        }
+        assert!(retried, "fail_at: {}", fail_at);
+        assert_eq!(
+            format!("{:?}", events),
+            format!("{:?}", expected),
+            "fail_at: {}",
+            fail_at
+        );
+    }
+}
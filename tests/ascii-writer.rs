@@ -0,0 +1,206 @@
+use std::io::Cursor;
+
+use fbx_direct::common::{FbxFormatType, OwnedProperty, Property};
+use fbx_direct::reader::{EventReader, FbxEvent as ReaderEvent};
+use fbx_direct::writer::{templates, EmitterConfig, Error, EventWriter, FbxEvent, LineEnding};
+
+mod common;
+
+use common::start_node;
+
+fn ascii_writer() -> EventWriter<Cursor<Vec<u8>>> {
+    let mut writer = EmitterConfig::new()
+        .fbx_version(Some(7400))
+        .create_writer(Cursor::new(Vec::new()));
+    writer
+        .write(FbxEvent::StartFbx(FbxFormatType::Ascii))
+        .unwrap();
+    writer
+}
+
+#[test]
+fn inline_comments() {
+    let mut writer = ascii_writer();
+    writer.write(start_node("Parent", &[])).unwrap();
+    writer.write(FbxEvent::InlineComment(" parent")).unwrap();
+    writer
+        .write(start_node("Version", &[Property::I32(232)]))
+        .unwrap();
+    writer.write(FbxEvent::InlineComment(" version")).unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(start_node("Empty", &[])).unwrap();
+    writer.write(FbxEvent::InlineComment(" empty")).unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(FbxEvent::EndFbx).unwrap();
+    let data = String::from_utf8(writer.into_vec()).unwrap();
+    assert_eq!(
+        data,
+        "; FBX 7.4.0 project file\n\
+         Parent:  { ; parent\n\
+         \tVersion: 232 ; version\n\
+         \tEmpty:  { ; empty\n\
+         \t}\n\
+         }\n"
+    );
+}
+
+#[test]
+fn reject_misplaced_inline_comments() {
+    let mut writer = ascii_writer();
+    assert!(matches!(
+        writer.write(FbxEvent::InlineComment(" top level")),
+        Err(Error::UnwritableEvent)
+    ));
+
+    let mut writer = ascii_writer();
+    writer.write(start_node("Parent", &[])).unwrap();
+    writer.write(start_node("Child", &[])).unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    assert!(matches!(
+        writer.write(FbxEvent::InlineComment(" after child")),
+        Err(Error::UnwritableEvent)
+    ));
+
+    let mut writer = ascii_writer();
+    writer.write(start_node("Node", &[])).unwrap();
+    assert!(matches!(
+        writer.write(FbxEvent::InlineComment(" multiple\nlines")),
+        Err(Error::UnwritableEvent)
+    ));
+}
+
+#[test]
+fn custom_indent() {
+    let mut writer = EmitterConfig::new()
+        .fbx_version(Some(7400))
+        .ascii_indent("  ")
+        .create_writer(Cursor::new(Vec::new()));
+    writer
+        .write(FbxEvent::StartFbx(FbxFormatType::Ascii))
+        .unwrap();
+    writer.write(start_node("Parent", &[])).unwrap();
+    writer
+        .write(start_node("Array", &[Property::VecI32(&[1, 2])]))
+        .unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(FbxEvent::EndFbx).unwrap();
+    let data = String::from_utf8(writer.into_vec()).unwrap();
+    assert_eq!(
+        data,
+        "; FBX 7.4.0 project file\n\
+         Parent:  {\n  \
+         Array: *2 {\n    \
+         a: 1,2\n  \
+         }\n\
+         }\n"
+    );
+
+    let mut writer = EmitterConfig::new()
+        .fbx_version(Some(7400))
+        .ascii_indent("-")
+        .create_writer(Cursor::new(Vec::new()));
+    assert!(matches!(
+        writer.write(FbxEvent::StartFbx(FbxFormatType::Ascii)),
+        Err(Error::InvalidOption(_))
+    ));
+}
+
+#[test]
+fn crlf_line_ending() {
+    fn write_with(line_ending: LineEnding) -> String {
+        let mut writer = EmitterConfig::new()
+            .fbx_version(Some(7400))
+            .line_ending(line_ending)
+            .create_writer(Cursor::new(Vec::new()));
+        writer
+            .write(FbxEvent::StartFbx(FbxFormatType::Ascii))
+            .unwrap();
+        writer.write(FbxEvent::Comment("; comment")).unwrap();
+        writer.write(start_node("Parent", &[])).unwrap();
+        writer
+            .write(start_node("Array", &[Property::VecF64(&[0.5])]))
+            .unwrap();
+        writer.write(FbxEvent::EndNode).unwrap();
+        writer
+            .write(start_node("Value", &[Property::I32(1)]))
+            .unwrap();
+        writer.write(FbxEvent::InlineComment(" value")).unwrap();
+        writer.write(FbxEvent::EndNode).unwrap();
+        writer.write(FbxEvent::EndNode).unwrap();
+        writer.write(FbxEvent::EndFbx).unwrap();
+        String::from_utf8(writer.into_vec()).unwrap()
+    }
+
+    let lf = write_with(LineEnding::Lf);
+    let crlf = write_with(LineEnding::CrLf);
+    assert!(!lf.contains('\r'));
+    assert_eq!(crlf, lf.replace('\n', "\r\n"));
+
+    // The reader accepts CRLF.
+    let mut reader = EventReader::new(Cursor::new(crlf.as_bytes()));
+    reader.next().unwrap();
+    for expected in &[" FBX 7.4.0 project file", " comment"] {
+        match reader.next().unwrap() {
+            ReaderEvent::Comment(comment) => assert_eq!(comment, *expected),
+            ev => panic!("unexpected event: {:?}", ev),
+        }
+    }
+}
+
+#[test]
+fn vec_bool_round_trip() {
+    // Binary FBX.
+    let mut writer = EmitterConfig::new()
+        .fbx_version(Some(7400))
+        .create_writer(Cursor::new(Vec::new()));
+    writer
+        .write(FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+        .unwrap();
+    writer
+        .write(start_node(
+            "Bools",
+            &[Property::VecBool(&[true, false, true])],
+        ))
+        .unwrap();
+    writer.write(FbxEvent::EndNode).unwrap();
+    writer.write(FbxEvent::EndFbx).unwrap();
+    let binary = writer.into_vec();
+
+    // Binary to ASCII.
+    let mut writer = ascii_writer();
+    for event in EventReader::new(Cursor::new(binary)).into_iter().skip(1) {
+        writer.write(event.unwrap().as_writer_event()).unwrap();
+    }
+    let ascii = String::from_utf8(writer.into_vec()).unwrap();
+    assert!(ascii.contains("a: Y,T,Y\n"), "{}", ascii);
+
+    // Parse ASCII.
+    let properties = EventReader::new(Cursor::new(ascii.as_bytes()))
+        .into_iter()
+        .find_map(|event| match event.unwrap() {
+            ReaderEvent::StartNode { name, properties } if name == "Bools" => Some(properties),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        properties,
+        vec![OwnedProperty::VecBool(vec![true, false, true])]
+    );
+}
+
+#[test]
+fn templates_use_ascii_object_names() {
+    let mut writer = ascii_writer();
+    templates::Model::new(1, "Cube", "Mesh")
+        .write(&mut writer)
+        .unwrap();
+    writer.write(FbxEvent::EndFbx).unwrap();
+    let data = String::from_utf8(writer.into_vec()).unwrap();
+    assert!(
+        data.contains("Model: 1, \"Model::Cube\", \"Mesh\" {"),
+        "{}",
+        data
+    );
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "async")]
+
+use std::fs::File;
+
+use fbx_direct::reader::{AsyncEventReader, EventReader, FbxEvent, ParserConfig};
+
+mod common;
+
+use common::BLENDER_FBX7400;
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn same_events_as_sync_reader() {
+    let data = std::fs::read(BLENDER_FBX7400).unwrap();
+
+    let mut sync_reader = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    let mut async_reader = AsyncEventReader::new(&data[..]);
+    block_on(async {
+        loop {
+            let expected = sync_reader.next().unwrap();
+            let event = async_reader.next().await.unwrap();
+            assert_eq!(format!("{:?}", event), format!("{:?}", expected));
+            assert_eq!(async_reader.position(), sync_reader.position());
+            if let FbxEvent::EndFbx = event {
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn config() {
+    let source = "; FBX 7.4.0 project file\nNode: 1\n";
+    let mut reader = ParserConfig::new()
+        .ignore_comments(true)
+        .create_async_reader(source.as_bytes());
+    block_on(async {
+        assert!(matches!(
+            reader.next().await.unwrap(),
+            FbxEvent::StartFbx(_)
+        ));
+        assert!(matches!(
+            reader.next().await.unwrap(),
+            FbxEvent::StartNode { .. }
+        ));
+    });
+}
+
+#[test]
+fn cancel_pending_next() {
+    use std::future::Future;
+    use std::task::Poll;
+    use tokio::io::AsyncWriteExt;
+
+    let data = std::fs::read(BLENDER_FBX7400).unwrap();
+
+    let (client, mut server) = tokio::io::duplex(data.len());
+    let mut reader = AsyncEventReader::new(client);
+    block_on(async {
+        let (head, rest) = data.split_at(data.len() / 2);
+        server.write_all(head).await.unwrap();
+        {
+            // Reads the first half and waits for the rest, then the future is dropped.
+            let mut next = Box::pin(reader.next());
+            let poll = std::future::poll_fn(|cx| Poll::Ready(next.as_mut().poll(cx))).await;
+            assert!(poll.is_pending());
+        }
+        server.write_all(rest).await.unwrap();
+        drop(server);
+
+        let mut sync_reader = EventReader::new(&data[..]);
+        loop {
+            let expected = sync_reader.next().unwrap();
+            let event = reader.next().await.unwrap();
+            assert_eq!(format!("{:?}", event), format!("{:?}", expected));
+            if let FbxEvent::EndFbx = event {
+                break;
+            }
+        }
+    });
+}
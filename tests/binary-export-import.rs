@@ -1,6 +1,5 @@
 #[macro_use]
 extern crate log;
-use env_logger;
 
 use std::fs::File;
 use std::io::BufReader;
@@ -10,7 +9,7 @@ use fbx_direct::reader::EventReader;
 use fbx_direct::writer::EventWriter;
 
 fn indent(size: usize) -> String {
-    const INDENT: &'static str = "    ";
+    const INDENT: &str = "    ";
     (0..size)
         .map(|_| INDENT)
         .fold(String::with_capacity(size * INDENT.len()), |r, s| r + s)
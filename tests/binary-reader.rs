@@ -0,0 +1,1518 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use fbx_direct::common::{FbxFormatType, OwnedProperty};
+use fbx_direct::reader::{
+    Arena, CreationTimeStamp, ErrorKind, EventReader, FbxEvent, GlobalSettings, NodeFilter,
+    ParserConfig, UnexpectedValuePolicy,
+};
+
+mod common;
+
+use common::{
+    fbx7400_header, fbx_header, node_doc, prop_i32, push_node32, read_all, read_doc, FlakyReader,
+    BLENDER_FBX7400, FBXSDK_FBX7500,
+};
+
+#[test]
+fn strict_accepts_footer() {
+    for filename in &[BLENDER_FBX7400, FBXSDK_FBX7500] {
+        let file = File::open(filename).unwrap();
+        let events = read_all(ParserConfig::new().strict(true).create_reader(file));
+        assert!(
+            events.iter().all(|e| e.is_ok()),
+            "{}: {:?}",
+            filename,
+            events.last()
+        );
+        assert!(matches!(events.last(), Some(Ok(FbxEvent::EndFbx))));
+    }
+}
+
+#[test]
+fn strict_rejects_trailing_data() {
+    let mut data = node_doc(&|_| {});
+    data.extend_from_slice(b"trailing garbage, not a footer");
+
+    let events = read_all(
+        ParserConfig::new()
+            .strict(true)
+            .create_reader(Cursor::new(&data)),
+    );
+    assert!(matches!(
+        events[0],
+        Ok(FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+    ));
+    assert!(events[1].is_err());
+
+    // Trailing data is not checked by default.
+    let events = read_doc(&data);
+    assert!(matches!(events[1], Ok(FbxEvent::EndFbx)));
+}
+
+#[test]
+fn strict_accepts_missing_footer() {
+    let data = node_doc(&|_| {});
+    let events = read_all(
+        ParserConfig::new()
+            .strict(true)
+            .create_reader(Cursor::new(&data)),
+    );
+    assert!(matches!(events[1], Ok(FbxEvent::EndFbx)));
+}
+
+#[test]
+fn read_fbx6100() {
+    let mut data = fbx_header(6100);
+    push_node32(&mut data, "Parent", &[&prop_i32(42)], &|data| {
+        push_node32(data, "Child", &[&prop_i32(-1), &prop_i32(2)], &|_| {});
+    });
+    data.extend_from_slice(&[0; 13]);
+
+    let events = read_doc(&data)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(events.len(), 6);
+    assert!(matches!(
+        events[0],
+        FbxEvent::StartFbx(FbxFormatType::Binary(6100))
+    ));
+    match events[1] {
+        FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        } => {
+            assert_eq!(name, "Parent");
+            assert_eq!(properties, &[OwnedProperty::I32(42)]);
+        }
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+    match events[2] {
+        FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        } => {
+            assert_eq!(name, "Child");
+            assert_eq!(properties, &[OwnedProperty::I32(-1), OwnedProperty::I32(2)]);
+        }
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+    assert!(matches!(events[3], FbxEvent::EndNode));
+    assert!(matches!(events[4], FbxEvent::EndNode));
+    assert!(matches!(events[5], FbxEvent::EndFbx));
+}
+
+#[test]
+fn read_all_events() {
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let events = EventReader::new(file).read_all(None).unwrap();
+    assert!(matches!(events.first(), Some(FbxEvent::StartFbx(_))));
+    assert!(matches!(events.last(), Some(FbxEvent::EndFbx)));
+
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let err = EventReader::new(file)
+        .read_all(Some(events.len() - 1))
+        .unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::EventLimitExceeded(_)));
+
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let exact = EventReader::new(file).read_all(Some(events.len())).unwrap();
+    assert_eq!(exact.len(), events.len());
+}
+
+#[test]
+fn binary_fbx_version() {
+    let file = File::open(FBXSDK_FBX7500).unwrap();
+    let mut reader = EventReader::new(file);
+    assert_eq!(reader.fbx_version(), None);
+    reader.next().unwrap();
+    assert_eq!(reader.fbx_version(), Some(7500));
+    reader.next().unwrap();
+    assert_eq!(reader.fbx_version(), Some(7500));
+}
+
+/// Sets `end_offset` of a node record with 32 bit header at `header_pos`.
+fn set_end_offset32(data: &mut [u8], header_pos: usize, end_offset: u32) {
+    data[header_pos..header_pos + 4].copy_from_slice(&end_offset.to_le_bytes());
+}
+
+#[test]
+fn reject_backward_end_offset() {
+    let node_pos = fbx7400_header().len();
+    let mut data = node_doc(&|data| {
+        push_node32(data, "Node", &[&prop_i32(1)], &|_| {});
+    });
+    // Points to the beginning of the node itself.
+    set_end_offset32(&mut data, node_pos, node_pos as u32);
+
+    let events = read_doc(&data);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[1].as_ref().unwrap_err().kind(),
+        ErrorKind::DataError(_)
+    ));
+}
+
+#[test]
+fn reject_zero_end_offset() {
+    let node_pos = fbx7400_header().len();
+    let mut data = node_doc(&|data| {
+        push_node32(data, "Node", &[&prop_i32(1)], &|_| {});
+    });
+    // Not a null record, because other fields are nonzero.
+    set_end_offset32(&mut data, node_pos, 0);
+
+    let events = read_doc(&data);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[1].as_ref().unwrap_err().kind(),
+        ErrorKind::DataError(_)
+    ));
+}
+
+#[test]
+fn reject_end_offset_beyond_parent() {
+    let parent_pos = fbx7400_header().len();
+    let mut data = node_doc(&|data| {
+        push_node32(data, "Parent", &[], &|data| {
+            push_node32(data, "Child", &[&prop_i32(1)], &|_| {});
+        });
+    });
+    // Child node is located right after the parent node header and its name.
+    let child_pos = parent_pos + 13 + "Parent".len();
+    let parent_end = u32::from_le_bytes([
+        data[parent_pos],
+        data[parent_pos + 1],
+        data[parent_pos + 2],
+        data[parent_pos + 3],
+    ]);
+    set_end_offset32(&mut data, child_pos, parent_end + 1);
+
+    let events = read_doc(&data);
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[1], Ok(FbxEvent::StartNode { .. })));
+    assert!(matches!(
+        events[2].as_ref().unwrap_err().kind(),
+        ErrorKind::DataError(_)
+    ));
+}
+
+#[test]
+fn read_to_end() {
+    let mut data = std::fs::read(BLENDER_FBX7400).unwrap();
+    let fbx_len = data.len() as u64;
+
+    // Without `read_to_end`, the footer is not read.
+    let mut reader = EventReader::new(Cursor::new(&data));
+    reader.read_all(None).unwrap();
+    assert!(reader.position() < fbx_len);
+
+    let mut cursor = Cursor::new(&data);
+    ParserConfig::new()
+        .read_to_end(true)
+        .create_reader(&mut cursor)
+        .read_all(None)
+        .unwrap();
+    assert_eq!(cursor.position(), fbx_len);
+
+    // Trailing garbage is tolerated in non-strict mode.
+    data.extend_from_slice(b"trailing garbage");
+    let mut cursor = Cursor::new(&data);
+    ParserConfig::new()
+        .read_to_end(true)
+        .create_reader(&mut cursor)
+        .read_all(None)
+        .unwrap();
+    assert_eq!(cursor.position(), data.len() as u64);
+
+    let mut cursor = Cursor::new(&data);
+    let result = ParserConfig::new()
+        .read_to_end(true)
+        .strict(true)
+        .create_reader(&mut cursor)
+        .read_all(None);
+    assert!(result.is_err());
+}
+
+#[test]
+fn utf8_lossy() {
+    let mut prop_string = vec![b'S'];
+    prop_string.extend_from_slice(&4u32.to_le_bytes());
+    // "caf\u{e9}" in Latin-1.
+    prop_string.extend_from_slice(b"caf\xe9");
+    let node_pos = fbx7400_header().len();
+    let mut data = node_doc(&|data| {
+        push_node32(data, "Na", &[&prop_string], &|_| {});
+    });
+    // Replace `a` in the node name with invalid UTF-8 byte.
+    data[node_pos + 13 + 1] = 0xff;
+
+    let events = read_doc(&data);
+    assert!(matches!(
+        events[1].as_ref().unwrap_err().kind(),
+        ErrorKind::Utf8Error(_)
+    ));
+
+    let events = read_all(
+        ParserConfig::new()
+            .utf8_lossy(true)
+            .create_reader(Cursor::new(&data)),
+    );
+    match events[1] {
+        Ok(FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        }) => {
+            assert_eq!(name, "N\u{fffd}");
+            assert_eq!(
+                properties,
+                &[OwnedProperty::String("caf\u{fffd}".to_string())]
+            );
+        }
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+    assert!(matches!(events[3], Ok(FbxEvent::EndFbx)));
+}
+
+#[test]
+fn compressed_array_decoded_short() {
+    // Compressed stream of two `i32` values.
+    let compressed = [
+        0x78, 0x9c, 0x63, 0x64, 0x60, 0x60, 0x60, 0x02, 0x62, 0x00, 0x00, 0x18, 0x00, 0x04,
+    ];
+    let mut prop_array = vec![b'i'];
+    // Claim 4 elements.
+    prop_array.extend_from_slice(&4u32.to_le_bytes());
+    prop_array.extend_from_slice(&1u32.to_le_bytes());
+    prop_array.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    prop_array.extend_from_slice(&compressed);
+    let data = node_doc(&|data| {
+        push_node32(data, "Geometry", &[], &|data| {
+            push_node32(data, "Vertices", &[&prop_array], &|_| {});
+        });
+    });
+
+    let events = read_doc(&data);
+    match *events.last().unwrap().as_ref().unwrap_err().kind() {
+        ErrorKind::DataError(ref msg) => assert_eq!(
+            msg,
+            "Compressed array decoded to 2 elements, expected 4 (in node `Geometry/Vertices`)"
+        ),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn strict_rejects_empty_node_name() {
+    let data = node_doc(&|data| {
+        push_node32(data, "", &[&prop_i32(1)], &|_| {});
+    });
+
+    let events = read_all(
+        ParserConfig::new()
+            .strict(true)
+            .create_reader(Cursor::new(&data)),
+    );
+    assert!(matches!(
+        events[1].as_ref().unwrap_err().kind(),
+        ErrorKind::DataError(_)
+    ));
+
+    // Tolerated by default.
+    let events = read_doc(&data);
+    match events[1] {
+        Ok(FbxEvent::StartNode { ref name, .. }) => assert_eq!(name, ""),
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+    assert!(matches!(events[3], Ok(FbxEvent::EndFbx)));
+
+    // A nameless node without contents is accepted even in strict mode.
+    let data = node_doc(&|data| {
+        push_node32(data, "", &[], &|_| {});
+    });
+    let events = read_all(
+        ParserConfig::new()
+            .strict(true)
+            .create_reader(Cursor::new(&data)),
+    );
+    assert!(events.iter().all(|e| e.is_ok()));
+}
+
+#[test]
+fn read_arena() {
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let arena = Arena::read(&mut EventReader::new(file)).unwrap();
+
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let events = EventReader::new(file).read_all(None).unwrap();
+    let num_nodes = events
+        .iter()
+        .filter(|e| matches!(e, FbxEvent::StartNode { .. }))
+        .count();
+    assert_eq!(arena.len(), num_nodes + 1);
+
+    // Traverse in document order and compare with events.
+    fn traverse(arena: &Arena, index: usize, names: &mut Vec<String>) {
+        for child in arena.children(index) {
+            assert_eq!(arena.node(child).parent(), Some(index));
+            names.push(arena.node(child).name.clone());
+            traverse(arena, child, names);
+        }
+    }
+    let mut names = Vec::new();
+    traverse(&arena, arena.root(), &mut names);
+    let expected = events
+        .into_iter()
+        .filter_map(|e| match e {
+            FbxEvent::StartNode { name, .. } => Some(name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, expected);
+    assert_eq!(arena.node(arena.root()).parent(), None);
+    assert!(arena
+        .children(arena.root())
+        .any(|i| arena.node(i).name == "Objects"));
+}
+
+#[test]
+fn try_next_after_transient_error() {
+    let data = std::fs::read(BLENDER_FBX7400).unwrap();
+    let mut reader = EventReader::new(Cursor::new(&data));
+    let expected = reader.read_all(None).unwrap();
+    let end = reader.position();
+
+    // Fail in the middle of node headers, names, properties and arrays.
+    for fail_at in (27 + 5..end).step_by(97) {
+        let inner = Rc::new(RefCell::new(Cursor::new(data.clone())));
+        let mut reader = ParserConfig::new()
+            .read_buffer_size(16)
+            .create_reader(FlakyReader {
+                inner: inner.clone(),
+                fail_at,
+                failed: false,
+            });
+        let mut events = Vec::new();
+        let mut retried = false;
+        loop {
+            match reader.try_next() {
+                Ok(FbxEvent::EndFbx) => {
+                    events.push(FbxEvent::EndFbx);
+                    break;
+                }
+                Ok(event) => events.push(event),
+                Err(err) => {
+                    assert!(err.is_retryable());
+                    assert!(!retried);
+                    retried = true;
+                    inner
+                        .borrow_mut()
+                        .seek(SeekFrom::Start(reader.position()))
+                        .unwrap();
+                }
+            }
+        }
+        assert!(retried, "fail_at: {}", fail_at);
+        assert_eq!(
+            format!("{:?}", events),
+            format!("{:?}", expected),
+            "fail_at: {}",
+            fail_at
+        );
+    }
+}
+
+#[test]
+fn dump_outline() {
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let mut out = Vec::new();
+    fbx_direct::reader::dump(file, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some("Binary FBX (version 7400)"));
+    assert_eq!(lines.next(), Some("FBXHeaderExtension:"));
+    assert_eq!(lines.next(), Some("    FBXHeaderVersion: 1003"));
+    assert!(out.lines().any(|line| line == "Objects:"));
+}
+
+#[test]
+fn reject_type_code_uppercase_b() {
+    let data = node_doc(&|data| {
+        push_node32(data, "Node", &[b"B\x01"], &|_| {});
+    });
+
+    let events = read_doc(&data);
+    match *events[1].as_ref().unwrap_err().kind() {
+        ErrorKind::UnexpectedValue(ref msg) => {
+            assert!(msg.contains("type_code=B"), "{}", msg);
+            assert!(msg.contains("boolean array is 'b'"), "{}", msg);
+        }
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn unexpected_bool() {
+    let data = node_doc(&|data| {
+        // Boolean property `'X'` (LSB is 0).
+        push_node32(data, "Bool", &[b"CX"], &|_| {});
+        // Plain boolean array `[1, 2]`.
+        push_node32(
+            data,
+            "BoolArray",
+            &[b"b\x02\x00\x00\x00\x00\x00\x00\x00\x02\x00\x00\x00\x01\x02"],
+            &|_| {},
+        );
+    });
+
+    for &policy in &[UnexpectedValuePolicy::Ignore, UnexpectedValuePolicy::Warn] {
+        let events = read_all(
+            ParserConfig::new()
+                .unexpected_bool(policy)
+                .create_reader(Cursor::new(&data)),
+        );
+        let properties = events
+            .iter()
+            .filter_map(|ev| match *ev.as_ref().unwrap() {
+                FbxEvent::StartNode { ref properties, .. } => Some(properties.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            properties,
+            [
+                vec![OwnedProperty::Bool(false)],
+                vec![OwnedProperty::VecBool(vec![true, false])]
+            ]
+        );
+    }
+
+    // Each of them is rejected.
+    for skip in 0..2 {
+        let mut data = data.clone();
+        if skip == 0 {
+            // Make the boolean property valid.
+            let pos = data.iter().rposition(|&b| b == b'X').unwrap();
+            data[pos] = b'T';
+        }
+        let events = read_all(
+            ParserConfig::new()
+                .unexpected_bool(UnexpectedValuePolicy::Error)
+                .create_reader(Cursor::new(&data)),
+        );
+        match *events.last().unwrap().as_ref().unwrap_err().kind() {
+            ErrorKind::UnexpectedValue(ref msg) => {
+                assert!(
+                    msg.ends_with(if skip == 0 { "0x2" } else { "0x58" }),
+                    "{}",
+                    msg
+                )
+            }
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn max_nodes() {
+    let data = node_doc(&|data| {
+        for _ in 0..3 {
+            push_node32(data, "Node", &[&prop_i32(1)], &|_| {});
+        }
+    });
+
+    let events = read_all(
+        ParserConfig::new()
+            .max_nodes(Some(2))
+            .create_reader(Cursor::new(&data)),
+    );
+    // StartFbx, (StartNode, EndNode) * 2, and an error.
+    assert_eq!(events.len(), 6);
+    assert!(matches!(
+        events[5].as_ref().unwrap_err().kind(),
+        ErrorKind::NodeLimitExceeded(2)
+    ));
+
+    let events = read_all(
+        ParserConfig::new()
+            .max_nodes(Some(3))
+            .create_reader(Cursor::new(&data)),
+    );
+    assert!(events.iter().all(|e| e.is_ok()));
+}
+
+#[test]
+fn start_at_node() {
+    use fbx_direct::writer::{node, EventWriter, FbxEvent as WriterEvent};
+    use std::sync::{Arc, Mutex};
+
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let callback_ranges = ranges.clone();
+    let mut writer = EventWriter::to_vec();
+    writer.set_on_node_written(Some(Arc::new(move |name: &str, start, end| {
+        callback_ranges
+            .lock()
+            .unwrap()
+            .push((name.to_string(), start, end));
+    })));
+    let events = vec![
+        WriterEvent::StartFbx(FbxFormatType::Binary(7400)),
+        node("A").build(),
+        node("B").build(),
+        node("C").prop_i32(1).build(),
+        WriterEvent::EndNode,
+        WriterEvent::EndNode,
+        node("D").build(),
+        WriterEvent::EndNode,
+        WriterEvent::EndNode,
+        node("E").build(),
+        WriterEvent::EndNode,
+        WriterEvent::EndFbx,
+    ];
+    for event in events {
+        writer.write(event).unwrap();
+    }
+    let data = writer.into_vec();
+    let ranges = ranges.lock().unwrap();
+    let range_of = |name: &str| {
+        let range = ranges.iter().find(|r| r.0 == name).unwrap();
+        (range.1, range.2)
+    };
+    let (a_start, a_end) = range_of("A");
+    let (b_start, _) = range_of("B");
+    assert!(a_start < b_start);
+
+    let mut source = Cursor::new(&data[..]);
+    source.seek(SeekFrom::Start(b_start)).unwrap();
+    let events =
+        EventReader::new_binary_at(source, ParserConfig::new(), 7400, b_start, vec![a_end])
+            .read_all(None)
+            .unwrap();
+    let outline = events
+        .iter()
+        .map(|ev| match *ev {
+            FbxEvent::StartNode { ref name, .. } => name.clone(),
+            FbxEvent::EndNode => "/".to_string(),
+            FbxEvent::EndFbx => "$".to_string(),
+            ref ev => panic!("unexpected event: {:?}", ev),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(outline, ["B", "C", "/", "/", "D", "/", "/", "E", "/", "$"]);
+}
+
+#[test]
+fn empty_input() {
+    let err = EventReader::new(Cursor::new(Vec::new()))
+        .next()
+        .unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::EmptyInput), "{:?}", err);
+
+    // Truncated magic is not an empty input.
+    let err = EventReader::new(Cursor::new(b"Kaydara".to_vec()))
+        .next()
+        .unwrap_err();
+    assert!(!matches!(*err.kind(), ErrorKind::EmptyInput), "{:?}", err);
+}
+
+#[test]
+fn detect_format() {
+    use fbx_direct::reader::detect_format;
+
+    let data = node_doc(&|_| {});
+    let mut reader = Cursor::new(&data[..]);
+    assert_eq!(
+        detect_format(&mut reader).unwrap(),
+        Some(FbxFormatType::Binary(7400))
+    );
+    // Only magic and version are consumed.
+    assert_eq!(reader.position(), 27);
+
+    let mut reader = Cursor::new(&b"; FBX 7.4.0 project file\nFBXHeaderExtension:  {\n"[..]);
+    assert_eq!(
+        detect_format(&mut reader).unwrap(),
+        Some(FbxFormatType::Ascii)
+    );
+    assert_eq!(reader.position(), 25);
+
+    for data in &[
+        &b""[..],
+        &b"Kaydara FBX Binary  \x00\x1a"[..],
+        &b"\x89PNG\r\n\x1a\n\x00"[..],
+    ] {
+        assert_eq!(detect_format(&mut Cursor::new(data)).unwrap(), None);
+    }
+    assert_eq!(
+        detect_format(&mut Cursor::new(&b"Not FBX\x00"[..])).unwrap(),
+        None
+    );
+
+    // Long data without newline is not read to the end.
+    let data = vec![b'a'; 1 << 20];
+    let mut reader = Cursor::new(&data[..]);
+    assert_eq!(detect_format(&mut reader).unwrap(), None);
+    assert_eq!(reader.position(), 256);
+}
+
+#[test]
+fn magic_without_1a_00() {
+    let mut data = b"Kaydara FBX Binary  \x00".to_vec();
+    data.extend_from_slice(&7400_u32.to_le_bytes());
+    push_node32(&mut data, "Node", &[&prop_i32(42)], &|_| {});
+    data.extend_from_slice(&[0; 13]);
+
+    let events = read_doc(&data);
+    let events = events.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert!(matches!(
+        events[0],
+        FbxEvent::StartFbx(FbxFormatType::Binary(7400))
+    ));
+    match events[1] {
+        FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        } => {
+            assert_eq!(name, "Node");
+            assert_eq!(properties, &[OwnedProperty::I32(42)]);
+        }
+        ref ev => panic!("unexpected event: {:?}", ev),
+    }
+    assert!(matches!(events[2], FbxEvent::EndNode));
+    assert!(matches!(events[3], FbxEvent::EndFbx));
+}
+
+#[test]
+fn headers_only() {
+    let mut full = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    let mut headers = ParserConfig::new()
+        .headers_only(true)
+        .create_reader(File::open(BLENDER_FBX7400).unwrap());
+    loop {
+        let expected = full.next().unwrap();
+        let event = headers.next().unwrap();
+        match (expected, event) {
+            (
+                FbxEvent::StartNode {
+                    name: expected_name,
+                    properties: expected_props,
+                },
+                FbxEvent::StartNode { name, properties },
+            ) => {
+                assert_eq!(name, expected_name);
+                assert!(properties.is_empty());
+                assert_eq!(headers.last_node_header(), full.last_node_header());
+                let header = headers.last_node_header().unwrap();
+                assert_eq!(header.num_properties, expected_props.len() as u64);
+            }
+            (FbxEvent::EndFbx, FbxEvent::EndFbx) => break,
+            (expected, event) => assert_eq!(format!("{:?}", event), format!("{:?}", expected)),
+        }
+        assert_eq!(headers.position(), full.position());
+    }
+}
+
+#[test]
+fn current_node_has_children() {
+    let mut reader = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    let mut has_children = None;
+    loop {
+        let event = reader.next().unwrap();
+        // The event after `StartNode` tells whether the node has children.
+        match (has_children, &event) {
+            (None, _)
+            | (Some(true), FbxEvent::StartNode { .. })
+            | (Some(false), FbxEvent::EndNode) => {}
+            (expected, event) => panic!("has_children = {:?}, but got {:?}", expected, event),
+        }
+        has_children = reader.current_node_has_children();
+        match event {
+            FbxEvent::StartNode { .. } => assert!(has_children.is_some()),
+            FbxEvent::EndFbx => break,
+            _ => assert_eq!(has_children, None),
+        }
+    }
+}
+
+#[test]
+fn last_node_span() {
+    let mut reader = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    let mut open_spans = Vec::new();
+    loop {
+        let start = reader.position();
+        match reader.next().unwrap() {
+            FbxEvent::StartNode { .. } => {
+                let span = reader.last_node_span().unwrap();
+                assert_eq!(span.start, start);
+                open_spans.push(span);
+            }
+            FbxEvent::EndNode => {
+                let span = open_spans.pop().unwrap();
+                assert_eq!(reader.position(), span.end);
+            }
+            FbxEvent::EndFbx => break,
+            _ => {}
+        }
+    }
+    assert!(open_spans.is_empty());
+}
+
+#[test]
+fn truncated_before_root_null_record() {
+    let data = std::fs::read(BLENDER_FBX7400).unwrap();
+    let mut reader = EventReader::new(Cursor::new(&data));
+    while !matches!(reader.next().unwrap(), FbxEvent::EndFbx) {}
+    let root_end = reader.position() as usize;
+
+    // Truncated mid-footer, but nodes are complete.
+    let events = read_doc(&data[..root_end + 10]);
+    assert!(matches!(events.last(), Some(Ok(FbxEvent::EndFbx))));
+
+    // Truncated before or in the root null record.
+    for &len in &[root_end - 13, root_end - 5] {
+        let events = read_doc(&data[..len]);
+        match events.last() {
+            Some(Err(err)) => {
+                assert_eq!(err.pos(), root_end as u64 - 13);
+                match *err.kind() {
+                    ErrorKind::DataError(ref msg) => assert!(msg.contains("root null record")),
+                    ref kind => panic!("unexpected error: {:?}", kind),
+                }
+            }
+            last => panic!("expected an error but got {:?}", last),
+        }
+    }
+}
+
+#[test]
+fn keep_type_codes() {
+    let mut reader = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert_eq!(reader.last_type_codes(), None);
+
+    let mut reader = ParserConfig::new()
+        .keep_type_codes(true)
+        .raw_compressed_arrays(true)
+        .create_reader(File::open(BLENDER_FBX7400).unwrap());
+    assert_eq!(reader.last_type_codes(), None);
+    let mut num_codes = 0;
+    loop {
+        match reader.next().unwrap() {
+            FbxEvent::StartNode { properties, .. } => {
+                let codes = reader.last_type_codes().unwrap();
+                let expected = properties
+                    .iter()
+                    .map(OwnedProperty::fbx_type_code)
+                    .collect::<Vec<_>>();
+                assert_eq!(codes, &expected[..]);
+                num_codes += codes.len();
+            }
+            FbxEvent::EndFbx => break,
+            _ => {}
+        }
+    }
+    assert!(num_codes > 0);
+}
+
+#[test]
+fn array_headers_only() {
+    let full = EventReader::new(File::open(BLENDER_FBX7400).unwrap())
+        .read_all(None)
+        .unwrap();
+    let mut reader = ParserConfig::new()
+        .array_headers_only(true)
+        .create_reader(File::open(BLENDER_FBX7400).unwrap());
+    let mut num_arrays = 0;
+    for full in &full {
+        let skipped = reader.next().unwrap();
+        let (full_props, properties) = match (full, &skipped) {
+            (
+                FbxEvent::StartNode {
+                    name: full_name,
+                    properties: full_props,
+                },
+                FbxEvent::StartNode { name, properties },
+            ) => {
+                assert_eq!(full_name, name);
+                (full_props, properties)
+            }
+            (full, skipped) => {
+                assert_eq!(format!("{:?}", full), format!("{:?}", skipped));
+                continue;
+            }
+        };
+        assert_eq!(full_props.len(), properties.len());
+        let mut headers = reader.last_array_headers().unwrap().iter();
+        for (full_prop, prop) in full_props.iter().zip(properties) {
+            let (full_len, is_empty) = match (full_prop, prop) {
+                (OwnedProperty::VecBool(full), OwnedProperty::VecBool(v)) => {
+                    (full.len(), v.is_empty())
+                }
+                (OwnedProperty::VecI32(full), OwnedProperty::VecI32(v)) => {
+                    (full.len(), v.is_empty())
+                }
+                (OwnedProperty::VecI64(full), OwnedProperty::VecI64(v)) => {
+                    (full.len(), v.is_empty())
+                }
+                (OwnedProperty::VecF32(full), OwnedProperty::VecF32(v)) => {
+                    (full.len(), v.is_empty())
+                }
+                (OwnedProperty::VecF64(full), OwnedProperty::VecF64(v)) => {
+                    (full.len(), v.is_empty())
+                }
+                _ => {
+                    assert_eq!(full_prop, prop);
+                    continue;
+                }
+            };
+            assert!(is_empty);
+            let header = headers.next().unwrap();
+            assert_eq!(header.type_code, full_prop.fbx_type_code());
+            assert_eq!(header.array_length as usize, full_len);
+            num_arrays += 1;
+        }
+        assert!(headers.next().is_none());
+    }
+    assert!(num_arrays > 0);
+}
+
+#[test]
+fn creation_time_stamp() {
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let arena = Arena::read(&mut EventReader::new(file)).unwrap();
+    assert_eq!(
+        arena.creation_time_stamp(),
+        Some(CreationTimeStamp {
+            year: 2015,
+            month: 12,
+            day: 26,
+            hour: 14,
+            minute: 3,
+            second: 53,
+            millisecond: 28,
+        })
+    );
+
+    let data = node_doc(&|data| {
+        push_node32(data, "FBXHeaderExtension", &[], &|_| {});
+    });
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(data))).unwrap();
+    assert_eq!(arena.creation_time_stamp(), None);
+}
+
+#[test]
+fn global_settings() {
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let arena = Arena::read(&mut EventReader::new(file)).unwrap();
+    assert_eq!(
+        arena.global_settings(),
+        Some(GlobalSettings {
+            up_axis: 1,
+            up_axis_sign: 1,
+            front_axis: 2,
+            front_axis_sign: -1,
+            coord_axis: 0,
+            coord_axis_sign: -1,
+            unit_scale_factor: 100.0,
+            original_unit_scale_factor: 100.0,
+            time_mode: 11,
+            time_span_start: 0,
+            time_span_stop: 46_186_158_000,
+            custom_frame_rate: 24.0,
+        })
+    );
+
+    // Absent fields are set to the defaults.
+    let data = node_doc(&|data| {
+        push_node32(data, "GlobalSettings", &[], &|_| {});
+    });
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(data))).unwrap();
+    assert_eq!(arena.global_settings(), Some(GlobalSettings::default()));
+
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(node_doc(&|_| {})))).unwrap();
+    assert_eq!(arena.global_settings(), None);
+}
+
+#[test]
+fn reject_string_longer_than_property_list() {
+    for &type_code in b"SR" {
+        let mut prop = vec![type_code];
+        prop.extend_from_slice(&u32::MAX.to_le_bytes());
+        prop.extend_from_slice(b"abc");
+        let data = node_doc(&|data| {
+            push_node32(data, "Node", &[&prop], &|_| {});
+        });
+
+        let events = read_doc(&data);
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(
+                events[1].as_ref().unwrap_err().kind(),
+                ErrorKind::DataError(_)
+            ),
+            "type code {:?}: {:?}",
+            type_code as char,
+            events[1]
+        );
+    }
+}
+
+#[test]
+fn reject_inconsistent_array_header() {
+    // (array_length, encoding, compressed_length, contents)
+    let headers: &[(u32, u32, u32, &[u8])] = &[
+        // Huge array claimed by a tiny property list.
+        (u32::MAX, 0, u32::MAX, &[0; 8]),
+        (u32::MAX, 1, u32::MAX, &[0; 8]),
+        // Raw array whose byte size does not match the element count.
+        (u32::MAX, 0, 8, &[0; 8]),
+        (1, 0, 8, &[0; 8]),
+        // Compressed array much longer than the compressed data can hold.
+        (
+            u32::MAX,
+            1,
+            8,
+            &[0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+        ),
+    ];
+    for &(array_length, encoding, compressed_length, contents) in headers {
+        let mut prop = vec![b'i'];
+        prop.extend_from_slice(&array_length.to_le_bytes());
+        prop.extend_from_slice(&encoding.to_le_bytes());
+        prop.extend_from_slice(&compressed_length.to_le_bytes());
+        prop.extend_from_slice(contents);
+        let data = node_doc(&|data| {
+            push_node32(data, "Node", &[&prop], &|_| {});
+        });
+
+        let events = read_doc(&data);
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(
+                events[1].as_ref().unwrap_err().kind(),
+                ErrorKind::DataError(_)
+            ),
+            "header {:?}: {:?}",
+            (array_length, encoding, compressed_length),
+            events[1]
+        );
+    }
+}
+
+#[test]
+fn max_depth() {
+    fn push_nested(data: &mut Vec<u8>, depth: usize) {
+        if depth > 0 {
+            push_node32(data, "Nested", &[], &|data| push_nested(data, depth - 1));
+        }
+    }
+    let data = node_doc(&|data| {
+        push_nested(data, 300);
+    });
+
+    // Default limit.
+    let events = read_doc(&data);
+    let num_started = events
+        .iter()
+        .filter(|e| matches!(e, Ok(FbxEvent::StartNode { .. })))
+        .count();
+    assert_eq!(num_started, 256);
+    match events.last() {
+        Some(Err(err)) => assert!(matches!(*err.kind(), ErrorKind::DataError(_))),
+        last => panic!("expected an error but got {:?}", last),
+    }
+
+    for &limit in &[Some(300), None] {
+        let events = read_all(
+            ParserConfig::new()
+                .max_depth(limit)
+                .create_reader(Cursor::new(&data)),
+        );
+        assert!(matches!(events.last(), Some(Ok(FbxEvent::EndFbx))));
+    }
+}
+
+#[test]
+fn node_filter() {
+    let filter: NodeFilter = Arc::new(|name, depth| match depth {
+        0 => name == "Objects",
+        1 => name == "Model",
+        _ => true,
+    });
+    let mut reader = EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+    reader.set_node_filter(Some(filter));
+    let mut depth = 0;
+    let mut num_models = 0;
+    for event in reader {
+        match event.unwrap() {
+            FbxEvent::StartNode { name, .. } => {
+                match depth {
+                    0 => assert_eq!(name, "Objects"),
+                    1 => {
+                        assert_eq!(name, "Model");
+                        num_models += 1;
+                    }
+                    _ => {}
+                }
+                depth += 1;
+            }
+            FbxEvent::EndNode => depth -= 1,
+            _ => {}
+        }
+    }
+    assert_eq!(depth, 0);
+    // Camera, light, and cube.
+    assert_eq!(num_models, 3);
+}
+
+#[test]
+fn scan_report() {
+    let data = node_doc(&|data| {
+        push_node32(data, "Parent", &[&prop_i32(1), &prop_i32(2)], &|data| {
+            push_node32(data, "Child", &[b"CY"], &|data| {
+                push_node32(data, "Grandchild", &[], &|_| {});
+            });
+        });
+        push_node32(data, "Sibling", &[&prop_i32(3)], &|_| {});
+    });
+
+    let report = fbx_direct::reader::scan(Cursor::new(data)).unwrap();
+    assert_eq!(report.format, Some(FbxFormatType::Binary(7400)));
+    assert_eq!(report.num_nodes, 4);
+    assert_eq!(report.max_depth, 3);
+    assert_eq!(report.num_properties, 4);
+    assert_eq!(
+        report.property_types.into_iter().collect::<Vec<_>>(),
+        vec![(b'C', 1), (b'I', 3)]
+    );
+}
+
+#[test]
+fn events_are_fused() {
+    let mut events = EventReader::new(Cursor::new(node_doc(&|_| {}))).into_iter();
+    assert_eq!(events.size_hint(), (0, None));
+    assert!(matches!(events.next(), Some(Ok(FbxEvent::StartFbx(_)))));
+    assert!(matches!(events.next(), Some(Ok(FbxEvent::EndFbx))));
+    assert_eq!(events.size_hint(), (0, Some(0)));
+    assert!(events.next().is_none());
+    assert!(events.next().is_none());
+
+    fn assert_fused<I: std::iter::FusedIterator>(_: &I) {}
+    assert_fused(&events);
+}
+
+#[test]
+fn into_inner() {
+    let mut data = node_doc(&|_| {});
+    data.extend_from_slice(b"trailing");
+    let mut reader = ParserConfig::new()
+        .read_buffer_size(0)
+        .create_reader(Cursor::new(data));
+    reader.next().unwrap();
+    let mut events = reader.into_iter();
+    assert!(matches!(events.next(), Some(Ok(FbxEvent::EndFbx))));
+    let mut rest = Vec::new();
+    events
+        .into_inner()
+        .into_inner()
+        .read_to_end(&mut rest)
+        .unwrap();
+    assert_eq!(rest, b"trailing");
+}
+
+#[test]
+fn ascii_after_binary_magic() {
+    let mut data = fbx7400_header();
+    data.extend_from_slice(b"FBXHeaderExtension:  {\n\tFBXHeaderVersion: 1003\n}\n");
+    let events = read_doc(&data);
+    assert_eq!(events.len(), 2);
+    match events[1] {
+        Err(ref err) => {
+            assert_eq!(err.pos(), fbx7400_header().len() as u64);
+            match *err.kind() {
+                ErrorKind::DataError(ref msg) => assert!(msg.contains("ASCII FBX"), "{}", msg),
+                ref kind => panic!("unexpected error: {:?}", kind),
+            }
+        }
+        ref e => panic!("expected an error but got {:?}", e),
+    }
+}
+
+#[test]
+fn read_buffer_size() {
+    /// A source which counts `read` calls.
+    struct CountingReader<R> {
+        inner: R,
+        count: Rc<RefCell<usize>>,
+    }
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            *self.count.borrow_mut() += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    let mut data = std::fs::read(BLENDER_FBX7400).unwrap();
+    data.extend_from_slice(b"trailing");
+    let read_with = |buffer_size| {
+        let count = Rc::new(RefCell::new(0));
+        let source = CountingReader {
+            inner: Cursor::new(&data),
+            count: count.clone(),
+        };
+        let mut reader = ParserConfig::new()
+            .read_buffer_size(buffer_size)
+            .create_reader(source);
+        let events = reader.read_all(None).unwrap();
+        let position = reader.position();
+        let mut rest = reader.buffer().to_vec();
+        reader.into_inner().read_to_end(&mut rest).unwrap();
+        let count = *count.borrow();
+        (events, position, rest, count)
+    };
+
+    let (events, position, rest, unbuffered_reads) = read_with(0);
+    let (buffered_events, buffered_position, buffered_rest, buffered_reads) = read_with(8192);
+    assert_eq!(format!("{:?}", events), format!("{:?}", buffered_events));
+    assert_eq!(position, buffered_position);
+    assert_eq!(rest, buffered_rest);
+    assert!(rest.ends_with(b"trailing"));
+    assert!(buffered_reads * 100 < unbuffered_reads);
+}
+
+#[test]
+fn multiple_documents() {
+    let blender = std::fs::read(BLENDER_FBX7400).unwrap();
+    let fbxsdk = std::fs::read(FBXSDK_FBX7500).unwrap();
+    let without_footer = node_doc(&|data| {
+        push_node32(data, "Node", &[&prop_i32(42)], &|_| {});
+    });
+    let ascii = b"; FBX 7.4.0 project file\nNode: 1\n";
+    // Documents and whether they have footers.
+    let documents: Vec<(&[u8], bool)> = vec![
+        (&blender, true),
+        (&without_footer, false),
+        (&fbxsdk, true),
+        (&blender, true),
+        (ascii, false),
+    ];
+    let data = documents
+        .iter()
+        .map(|&(doc, _)| doc)
+        .collect::<Vec<_>>()
+        .concat();
+
+    // Each document is read as if it is read alone.
+    let mut reader = ParserConfig::new()
+        .strict(true)
+        .allow_multiple_documents(true)
+        .create_reader(Cursor::new(&data));
+    let mut doc_start = 0;
+    for &(document, has_footer) in &documents {
+        let arena = Arena::read(&mut reader).unwrap();
+        let expected = Arena::read(&mut EventReader::new(Cursor::new(document))).unwrap();
+        assert_eq!(arena, expected);
+        assert_eq!(reader.footer_info().is_some(), has_footer);
+        doc_start += document.len();
+        assert!(reader.position() <= doc_start as u64);
+    }
+    assert_eq!(reader.position(), data.len() as u64);
+    // `EndFbx` is repeated at the end of the stream.
+    assert!(matches!(reader.next(), Ok(FbxEvent::EndFbx)));
+    assert!(matches!(reader.next(), Ok(FbxEvent::EndFbx)));
+
+    // The iterator stops after the last document.
+    let reader = ParserConfig::new()
+        .allow_multiple_documents(true)
+        .create_reader(Cursor::new(&data));
+    let events = read_all(reader);
+    let count =
+        |f: &dyn Fn(&FbxEvent) -> bool| events.iter().filter(|ev| f(ev.as_ref().unwrap())).count();
+    assert_eq!(count(&|ev| matches!(ev, FbxEvent::StartFbx(_))), 5);
+    assert_eq!(count(&|ev| matches!(ev, FbxEvent::EndFbx)), 5);
+    assert!(matches!(events.last(), Some(Ok(FbxEvent::EndFbx))));
+
+    // Only the first document is read by default.
+    let events = read_doc(&data);
+    assert_eq!(events.len(), read_doc(&blender).len());
+}
+
+#[test]
+fn empty_compressed_array_without_data() {
+    let data = node_doc(&|data| {
+        // Empty array with zlib encoding but without compressed data.
+        let mut prop = vec![b'd'];
+        prop.extend_from_slice(&0_u32.to_le_bytes());
+        prop.extend_from_slice(&1_u32.to_le_bytes());
+        prop.extend_from_slice(&0_u32.to_le_bytes());
+        push_node32(data, "Empty", &[&prop], &|_| {});
+    });
+    let events = ParserConfig::new()
+        .strict(true)
+        .create_reader(Cursor::new(data))
+        .read_all(None)
+        .unwrap();
+    match events[1] {
+        FbxEvent::StartNode { ref properties, .. } => {
+            assert_eq!(properties, &[OwnedProperty::VecF64(vec![])]);
+        }
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+}
+
+#[test]
+fn find_node() {
+    let open = || EventReader::new(File::open(BLENDER_FBX7400).unwrap());
+
+    let mut reader = open();
+    assert_eq!(
+        reader
+            .find_node(&["FBXHeaderExtension", "FBXVersion"])
+            .unwrap(),
+        Some(vec![OwnedProperty::I32(7400)])
+    );
+    // Children of the found node can be read next, and the path is relative to the position.
+    let mut reader = open();
+    reader.find_node(&["FBXHeaderExtension"]).unwrap().unwrap();
+    assert_eq!(
+        reader.find_node(&["FBXVersion"]).unwrap(),
+        Some(vec![OwnedProperty::I32(7400)])
+    );
+    // Not found in the open node (`FBXVersion`), and then in its parent.
+    assert_eq!(reader.find_node(&["Objects"]).unwrap(), None);
+    assert_eq!(reader.find_node(&["Objects"]).unwrap(), None);
+    assert!(reader.find_node(&["Objects"]).unwrap().is_some());
+
+    let mut reader = open();
+    assert_eq!(reader.find_node(&["Objects", "NoSuchNode"]).unwrap(), None);
+    assert!(matches!(reader.next(), Ok(FbxEvent::EndFbx)));
+    assert_eq!(open().find_node(&[]).unwrap(), None);
+
+    // Skipped subtrees are not parsed.
+    let data = node_doc(&|data| {
+        push_node32(data, "Skipped", &[], &|data| {
+            push_node32(data, "Broken", &[b"X"], &|_| {});
+        });
+        push_node32(data, "Target", &[&prop_i32(42)], &|_| {});
+    });
+    assert!(read_doc(&data).last().unwrap().is_err());
+    assert_eq!(
+        EventReader::new(Cursor::new(&data))
+            .find_node(&["Target"])
+            .unwrap(),
+        Some(vec![OwnedProperty::I32(42)])
+    );
+}
+
+#[test]
+fn read_tree_lenient() {
+    // Broken compressed array.
+    let mut prop_array = vec![b'i'];
+    prop_array.extend_from_slice(&4u32.to_le_bytes());
+    prop_array.extend_from_slice(&1u32.to_le_bytes());
+    prop_array.extend_from_slice(&6u32.to_le_bytes());
+    prop_array.extend_from_slice(&[0x78, 0x9c, 0xff, 0xff, 0xff, 0xff]);
+    let mut data = node_doc(&|data| {
+        push_node32(data, "Objects", &[], &|data| {
+            push_node32(data, "Broken", &[&prop_array], &|_| {});
+            push_node32(data, "Good", &[&prop_i32(1)], &|_| {});
+        });
+        push_node32(data, "Settings", &[], &|data| {
+            push_node32(data, "Inner", &[&prop_i32(2)], &|_| {});
+            push_node32(data, "Lost", &[&prop_i32(3)], &|_| {});
+        });
+        push_node32(data, "Tail", &[&prop_i32(4)], &|_| {});
+    });
+    // Make the end offset of `Inner` beyond its parent.
+    let inner_pos = data.windows(5).position(|w| w == b"Inner").unwrap() - 13;
+    set_end_offset32(&mut data, inner_pos, u32::MAX);
+
+    assert!(Arena::read(&mut EventReader::new(Cursor::new(&data))).is_err());
+
+    let mut reader = EventReader::new(Cursor::new(&data));
+    let (arena, errors) = Arena::read_tree_lenient(&mut reader);
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0].kind(),
+        ErrorKind::Decompression(_) | ErrorKind::DataError(_)
+    ));
+    assert!(matches!(errors[1].kind(), ErrorKind::DataError(_)));
+    let names = |index| {
+        arena
+            .children(index)
+            .map(|child| arena.node(child).name.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(arena.root()), ["Objects", "Settings", "Tail"]);
+    let objects = arena.child_by_name(arena.root(), "Objects").unwrap();
+    assert_eq!(names(objects), ["Good"]);
+    let settings = arena.child_by_name(arena.root(), "Settings").unwrap();
+    assert!(names(settings).is_empty());
+    let tail = arena.child_by_name(arena.root(), "Tail").unwrap();
+    assert_eq!(arena.node(tail).properties, [OwnedProperty::I32(4)]);
+    assert!(matches!(reader.next(), Ok(FbxEvent::EndFbx)));
+
+    // Valid data is read as `Arena::read` does.
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let expected = Arena::read(&mut EventReader::new(file)).unwrap();
+    let file = File::open(BLENDER_FBX7400).unwrap();
+    let (arena, errors) = Arena::read_tree_lenient(&mut EventReader::new(file));
+    assert!(errors.is_empty());
+    assert_eq!(arena, expected);
+}
+
+#[test]
+fn reject_overflowing_property_list_len() {
+    // The node name ends at 56, so the second one makes the properties end just before
+    // `u64::MAX` and the following null record overflow.
+    for &property_list_len in &[u64::MAX, u64::MAX - 66] {
+        let mut data = fbx_header(7500);
+        // Node record header with 64 bit fields.
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&property_list_len.to_le_bytes());
+        data.push(4);
+        data.extend_from_slice(b"Node");
+
+        let events = read_doc(&data);
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(
+                events[1].as_ref().unwrap_err().kind(),
+                ErrorKind::DataError(_)
+            ),
+            "property_list_len = {}: {:?}",
+            property_list_len,
+            events[1]
+        );
+    }
+}
+
+#[test]
+fn capture_node_with_huge_end_offset() {
+    let mut data = fbx_header(7500);
+    // Node record header with 64 bit fields.
+    data.extend_from_slice(&(1u64 << 62).to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(4);
+    data.extend_from_slice(b"Node");
+    data.extend_from_slice(&[0; 25]);
+
+    let mut reader = EventReader::new(Cursor::new(&data));
+    reader.next().unwrap();
+    assert!(matches!(reader.next().unwrap(), FbxEvent::StartNode { .. }));
+    // The data ends before the end offset.
+    assert!(reader.capture_node().is_err());
+}
+
+/// Measures decoding speed of uncompressed arrays.
+///
+/// Run with `cargo test --release --test binary-reader -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn plain_array_decoding_speed() {
+    const LEN: usize = 1 << 20;
+    let mut props = Vec::new();
+    for &(type_code, elem_size) in &[(b'd', 8), (b'f', 4), (b'l', 8), (b'i', 4)] {
+        let mut prop = vec![type_code];
+        prop.extend_from_slice(&(LEN as u32).to_le_bytes());
+        prop.extend_from_slice(&0u32.to_le_bytes());
+        prop.extend_from_slice(&((LEN * elem_size) as u32).to_le_bytes());
+        prop.extend((0..LEN * elem_size).map(|i| i as u8));
+        props.push(prop);
+    }
+    let data = node_doc(&|data| {
+        for _ in 0..4 {
+            let props: Vec<&[u8]> = props.iter().map(|prop| &prop[..]).collect();
+            push_node32(data, "Arrays", &props, &|_| {});
+        }
+    });
+
+    let start = std::time::Instant::now();
+    for _ in 0..4 {
+        let events = read_doc(&data);
+        assert!(events.iter().all(Result::is_ok));
+    }
+    println!(
+        "decoded {} MB of arrays in {:?}",
+        data.len() * 4 / 1_000_000,
+        start.elapsed()
+    );
+}
+
+/// Checks that values are read as little endian regardless of the native endianness.
+///
+/// Bytes and expected values are written as literals, so this fails if the reader wrongly
+/// depends on the native endianness (for example, on big endian targets).
+#[test]
+fn read_little_endian_values() {
+    let props: &[&[u8]] = &[
+        &[b'Y', 0xfe, 0xff],
+        &[b'I', 0x01, 0x02, 0x03, 0x04],
+        &[b'L', 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+        &[b'F', 0x00, 0x00, 0x80, 0x3f],
+        &[b'D', 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xc0],
+        &[
+            b'i', 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01,
+            0x02, 0x03, 0x04, 0xff, 0xff, 0xff, 0xff,
+        ],
+        &[
+            b'f', 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xc0,
+        ],
+    ];
+    let data = node_doc(&|data| {
+        push_node32(data, "Values", props, &|_| {});
+    });
+
+    let events = read_doc(&data);
+    match *events[1].as_ref().unwrap() {
+        FbxEvent::StartNode { ref properties, .. } => assert_eq!(
+            properties,
+            &[
+                OwnedProperty::I16(-2),
+                OwnedProperty::I32(0x0403_0201),
+                OwnedProperty::I64(0x0807_0605_0403_0201),
+                OwnedProperty::F32(1.0),
+                OwnedProperty::F64(-2.5),
+                OwnedProperty::VecI32(vec![0x0403_0201, -1]),
+                OwnedProperty::VecF32(vec![1.0, -2.0]),
+            ]
+        ),
+        ref ev => panic!("unexpected event: {:?}", ev),
+    }
+}
+
+/// Measures reading speed of an unbuffered file with and without `read_buffer_size`.
+///
+/// Run with `cargo test --release --test binary-reader -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn read_buffer_size_speed() {
+    let data = node_doc(&|data| {
+        for i in 0..100_000 {
+            push_node32(data, "Node", &[&prop_i32(i), &prop_i32(-i)], &|_| {});
+        }
+    });
+    let path = std::env::temp_dir().join("fbx_direct-read_buffer_size_speed.fbx");
+    std::fs::write(&path, &data).unwrap();
+
+    for &buffer_size in &[0, 8192] {
+        let start = std::time::Instant::now();
+        let mut reader = ParserConfig::new()
+            .read_buffer_size(buffer_size)
+            .create_reader(File::open(&path).unwrap());
+        reader.read_all(None).unwrap();
+        println!(
+            "read {} bytes with read_buffer_size = {} in {:?}",
+            data.len(),
+            buffer_size,
+            start.elapsed()
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
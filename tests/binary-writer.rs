@@ -0,0 +1,977 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use fbx_direct::common::{FbxFormatType, OwnedProperty, Property};
+use fbx_direct::reader::{Arena, EventReader, FbxEvent, ParserConfig};
+use fbx_direct::writer::{self, templates, EmitterConfig, Error, EventWriter};
+
+mod common;
+
+use common::{read_events, start_doc, start_node, write_doc, write_doc_with, BLENDER_FBX7400};
+
+#[test]
+fn in_memory_sinks() {
+    let events = read_events(BLENDER_FBX7400);
+
+    let mut cursor = EventWriter::new(Cursor::new(Vec::new()));
+    let mut vec = EventWriter::to_vec();
+    let mut buffered = EventWriter::to_vec_buffered();
+    let mut temp_file = EventWriter::new_with_temp_file(Vec::new()).unwrap();
+    for event in &events {
+        cursor.write(event.as_writer_event()).unwrap();
+        vec.write(event.as_writer_event()).unwrap();
+        buffered.write(event.as_writer_event()).unwrap();
+        temp_file.write(event.as_writer_event()).unwrap();
+    }
+
+    let cursor = cursor.into_vec();
+    let vec = vec.into_vec();
+    let buffered = buffered.into_vec();
+    let temp_file = temp_file.into_temp_file_inner().unwrap();
+    assert!(!cursor.is_empty());
+    assert_eq!(cursor, vec);
+    assert_eq!(cursor, buffered);
+    assert_eq!(cursor, temp_file);
+}
+
+#[test]
+fn skip_empty_nodes() {
+    let events = vec![
+        start_node("Empty", &[]),
+        writer::FbxEvent::EndNode,
+        start_node("Parent", &[]),
+        start_node("EmptyChild", &[]),
+        writer::FbxEvent::EndNode,
+        start_node("Child", &[Property::I32(1)]),
+        writer::FbxEvent::EndNode,
+        writer::FbxEvent::EndNode,
+        start_node("Leaf", &[Property::I32(2)]),
+        writer::FbxEvent::EndNode,
+    ];
+    let data = write_doc_with(EmitterConfig::new().skip_empty_nodes(true), |writer| {
+        for event in events {
+            writer.write(event).unwrap();
+        }
+    });
+
+    let names = EventReader::new(Cursor::new(data))
+        .read_all(None)
+        .unwrap()
+        .into_iter()
+        .filter_map(|event| match event {
+            FbxEvent::StartNode { name, .. } => Some(name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Parent", "Child", "Leaf"]);
+}
+
+#[test]
+fn raw_strings_round_trip() {
+    let raw = b"Name\x00\x01Class\xff\xfe";
+    let properties = [Property::RawString(raw)];
+    let exported1 = write_doc(vec![
+        start_node("Node", &properties),
+        writer::FbxEvent::EndNode,
+    ]);
+
+    let events = ParserConfig::new()
+        .raw_strings(true)
+        .create_reader(Cursor::new(&exported1))
+        .read_all(None)
+        .unwrap();
+    match events[1] {
+        FbxEvent::StartNode { ref properties, .. } => {
+            assert_eq!(properties, &[OwnedProperty::RawString(raw.to_vec())]);
+        }
+        ref e => panic!("unexpected event: {:?}", e),
+    }
+    let mut writer = EventWriter::to_vec();
+    for event in &events {
+        writer.write(event.as_writer_event()).unwrap();
+    }
+    assert_eq!(exported1, writer.into_vec());
+}
+
+#[test]
+fn reject_too_long_node_name() {
+    let mut writer = start_doc(EmitterConfig::new());
+    let name = "a".repeat(255);
+    writer.write(start_node(&name, &[])).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    let name = "a".repeat(256);
+    assert!(matches!(
+        writer.write(start_node(&name, &[])),
+        Err(Error::DataTooLarge(_))
+    ));
+}
+
+#[test]
+fn on_node_written() {
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let callback_ranges = ranges.clone();
+    let mut writer = EventWriter::to_vec();
+    writer.set_on_node_written(Some(Arc::new(move |name: &str, start, end| {
+        callback_ranges
+            .lock()
+            .unwrap()
+            .push((name.to_string(), start, end));
+    })));
+    for event in read_events(BLENDER_FBX7400) {
+        writer.write(event.as_writer_event()).unwrap();
+    }
+    let data = writer.into_vec();
+    let ranges = ranges.lock().unwrap();
+
+    // Every node is reported once, in the order of their ends.
+    let events = EventReader::new(Cursor::new(&data)).read_all(None).unwrap();
+    let mut open_names = Vec::new();
+    let mut expected_names = Vec::new();
+    for event in events {
+        match event {
+            FbxEvent::StartNode { name, .. } => open_names.push(name),
+            FbxEvent::EndNode => expected_names.push(open_names.pop().unwrap()),
+            _ => {}
+        }
+    }
+    let names = ranges.iter().map(|r| r.0.clone()).collect::<Vec<_>>();
+    assert_eq!(names, expected_names);
+
+    // Ranges point to node records.
+    for &(ref name, start, end) in ranges.iter() {
+        let start = start as usize;
+        let end_offset = u32::from_le_bytes([
+            data[start],
+            data[start + 1],
+            data[start + 2],
+            data[start + 3],
+        ]);
+        assert_eq!(u64::from(end_offset), end);
+        let name_len = data[start + 12] as usize;
+        assert_eq!(&data[start + 13..start + 13 + name_len], name.as_bytes());
+    }
+}
+
+#[test]
+fn unsupported_fbx_version() {
+    let mut writer = EventWriter::to_vec();
+    let err = writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(6100)))
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedFbxVersion(6100)));
+    assert_eq!(err.to_string(), "Unsupported FBX version (6100)");
+}
+
+#[test]
+fn reject_unclosed_nodes() {
+    let mut writer = start_doc(EmitterConfig::new());
+    writer.write(start_node("Parent", &[])).unwrap();
+    writer.write(start_node("Child", &[])).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    assert!(matches!(
+        writer.write(writer::FbxEvent::EndFbx),
+        Err(Error::UnclosedNodes(1))
+    ));
+}
+
+#[test]
+fn append_nodes() {
+    let original = write_doc(vec![
+        start_node("Original", &[Property::I32(1)]),
+        writer::FbxEvent::EndNode,
+    ]);
+
+    let mut writer = EventWriter::append(Cursor::new(original), EmitterConfig::new()).unwrap();
+    writer
+        .write(start_node("Appended", &[Property::I32(2)]))
+        .unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    let appended = writer.into_vec();
+
+    // The result is the same as writing all nodes at once.
+    let expected = write_doc(vec![
+        start_node("Original", &[Property::I32(1)]),
+        writer::FbxEvent::EndNode,
+        start_node("Appended", &[Property::I32(2)]),
+        writer::FbxEvent::EndNode,
+    ]);
+    assert_eq!(appended, expected);
+
+    let events = ParserConfig::new()
+        .strict(true)
+        .create_reader(Cursor::new(&appended))
+        .read_all(None)
+        .unwrap();
+    assert_eq!(events.len(), 6);
+}
+
+#[test]
+fn write_node() {
+    let read_arena = |data: &[u8]| Arena::read(&mut EventReader::new(Cursor::new(data))).unwrap();
+    let original = std::fs::read(BLENDER_FBX7400).unwrap();
+    let arena = read_arena(&original);
+
+    // Writing the implicit root node writes the whole tree.
+    let data = write_doc_with(EmitterConfig::new(), |writer| {
+        writer.write_node(&arena, arena.root()).unwrap();
+    });
+    assert_eq!(read_arena(&data), arena);
+
+    // Writing a node writes the node itself and its descendants.
+    let objects = arena.child_by_name(arena.root(), "Objects").unwrap();
+    let data = write_doc_with(EmitterConfig::new(), |writer| {
+        writer.write_node(&arena, objects).unwrap();
+    });
+    let written = read_arena(&data);
+    let top_level = written.children(written.root()).collect::<Vec<_>>();
+    assert_eq!(top_level.len(), 1);
+    assert_eq!(written.node(top_level[0]).name, "Objects");
+    assert_eq!(
+        written.children(top_level[0]).count(),
+        arena.children(objects).count()
+    );
+}
+
+#[test]
+fn write_start_node() {
+    let template = [
+        Property::String("Model::Cube"),
+        Property::VecI32(&[0, 1, 2]),
+    ];
+    let write_with = |shared: bool| {
+        write_doc_with(EmitterConfig::new(), |writer| {
+            for _ in 0..3 {
+                if shared {
+                    writer.write_start_node("Model", &template).unwrap();
+                } else {
+                    writer.write(start_node("Model", &template)).unwrap();
+                }
+                writer.write(writer::FbxEvent::EndNode).unwrap();
+            }
+        })
+    };
+    assert_eq!(write_with(true), write_with(false));
+}
+
+#[test]
+fn preserve_footer_reserved() {
+    fn write_with_reserved(reserved: Option<[u8; 120]>) -> Vec<u8> {
+        write_doc_with(EmitterConfig::new().footer_reserved(reserved), |writer| {
+            writer
+                .write(start_node("Node", &[Property::I32(1)]))
+                .unwrap();
+            writer.write(writer::FbxEvent::EndNode).unwrap();
+        })
+    }
+
+    let mut reserved = [0; 120];
+    for (i, byte) in reserved.iter_mut().enumerate() {
+        *byte = i as u8 + 1;
+    }
+    let original = write_with_reserved(Some(reserved));
+
+    let mut reader = ParserConfig::new()
+        .read_to_end(true)
+        .create_reader(Cursor::new(&original[..]));
+    reader.read_all(None).unwrap();
+    let footer = reader.footer_info().expect("Footer should be read").clone();
+    assert_eq!(footer.version, 7400);
+    assert_eq!(&footer.reserved[..], &reserved[..]);
+
+    assert_eq!(write_with_reserved(Some(footer.reserved)), original);
+    assert_ne!(write_with_reserved(None), original);
+}
+
+#[test]
+fn reject_non_finite() {
+    fn writer(reject: bool) -> EventWriter<Cursor<Vec<u8>>> {
+        start_doc(EmitterConfig::new().reject_non_finite(reject))
+    }
+
+    let nan = [f64::NAN];
+    let inf = [f32::INFINITY];
+    let cases = [
+        Property::F64(f64::NAN),
+        Property::F32(f32::NEG_INFINITY),
+        Property::VecF64(&nan),
+        Property::VecF32(&inf),
+    ];
+    for property in &cases {
+        let properties = [Property::I32(0), property.clone()];
+        assert!(writer(false).write(start_node("Node", &properties)).is_ok());
+        let mut writer = writer(true);
+        assert!(matches!(
+            writer.write(start_node("Node", &properties)),
+            Err(Error::DataError(_))
+        ));
+        // The error is latched.
+        assert!(writer.write(writer::FbxEvent::EndNode).is_err());
+    }
+    assert!(writer(true)
+        .write(start_node(
+            "Node",
+            &[Property::F64(1.0), Property::VecF32(&[0.5])]
+        ))
+        .is_ok());
+
+    // Streamed arrays.
+    let mut writer = writer(true);
+    writer.write(start_node("Node", &[])).unwrap();
+    writer.begin_array_property::<f32>().unwrap();
+    writer.push_f32(1.0).unwrap();
+    assert!(matches!(
+        writer.push_f32(f32::NAN),
+        Err(Error::DataError(_))
+    ));
+}
+
+#[test]
+fn pad_to_16() {
+    fn write_with_padding(name: &str, pad: bool) -> Vec<u8> {
+        write_doc_with(EmitterConfig::new().pad_to_16(pad), |writer| {
+            writer.write(start_node(name, &[Property::I32(1)])).unwrap();
+            writer.write(writer::FbxEvent::EndNode).unwrap();
+        })
+    }
+
+    // Try all alignments of the end of the document.
+    for len in 0..16 {
+        let name = "N".repeat(len + 1);
+        let padded = write_with_padding(&name, true);
+        let unpadded = write_with_padding(&name, false);
+        assert_eq!(padded.len() % 16, 0);
+        let pad_len = padded.len() - unpadded.len();
+        assert!((1..=16).contains(&pad_len), "padding: {} bytes", pad_len);
+        for data in &[padded, unpadded] {
+            let mut reader = ParserConfig::new()
+                .strict(true)
+                .read_to_end(true)
+                .create_reader(Cursor::new(&data[..]));
+            reader.read_all(None).unwrap();
+            assert!(reader.footer_info().is_some());
+        }
+    }
+}
+
+#[test]
+fn root_null_record() {
+    fn write(version: u32, with_node: bool, null_record: bool) -> Vec<u8> {
+        let mut writer = EmitterConfig::new()
+            .pad_to_16(false)
+            .root_null_record(null_record)
+            .create_writer(Cursor::new(Vec::new()));
+        writer
+            .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(version)))
+            .unwrap();
+        if with_node {
+            writer
+                .write(start_node("Node", &[Property::I32(1)]))
+                .unwrap();
+            writer.write(writer::FbxEvent::EndNode).unwrap();
+        }
+        writer.write(writer::FbxEvent::EndFbx).unwrap();
+        writer.into_vec()
+    }
+
+    for &(version, null_record_len) in &[(7400, 13), (7500, 25)] {
+        for &with_node in &[false, true] {
+            let explicit = write(version, with_node, true);
+            let implicit = write(version, with_node, false);
+            assert_eq!(explicit.len() - implicit.len(), null_record_len);
+            let read = |data: &[u8]| {
+                let events = EventReader::new(Cursor::new(data)).read_all(None).unwrap();
+                format!("{:?}", events)
+            };
+            let expected = read(&explicit);
+            for data in &[explicit, implicit] {
+                assert_eq!(read(data), expected);
+                let mut reader = ParserConfig::new()
+                    .strict(true)
+                    .read_to_end(true)
+                    .create_reader(Cursor::new(&data[..]));
+                reader.read_all(None).unwrap();
+                assert_eq!(reader.footer_info().unwrap().version, version);
+
+                let mut concatenated = data.clone();
+                concatenated.extend_from_slice(data);
+                let events = ParserConfig::new()
+                    .allow_multiple_documents(true)
+                    .create_reader(Cursor::new(concatenated))
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                let num_start_fbx = events
+                    .iter()
+                    .filter(|event| matches!(event, FbxEvent::StartFbx(_)))
+                    .count();
+                assert_eq!(num_start_fbx, 2);
+            }
+        }
+    }
+}
+
+#[test]
+fn written_counters() {
+    let mut writer = EventWriter::to_vec();
+    assert_eq!(writer.nodes_written(), 0);
+    assert_eq!(writer.bytes_written(), 0);
+    writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+        .unwrap();
+    writer
+        .write_start_node("A", &[Property::I32(1), Property::String("a")])
+        .unwrap();
+    writer.write_start_node("B", &[]).unwrap();
+    writer.begin_array_property::<f64>().unwrap();
+    writer.push_f64(1.0).unwrap();
+    writer.end_array_property().unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    assert_eq!(writer.nodes_written(), 2);
+    assert_eq!(writer.properties_written(), 3);
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    let bytes_written = writer.bytes_written();
+    assert_eq!(bytes_written, writer.into_vec().len() as u64);
+
+    // Counters start from zero for appending writers.
+    let mut sink = Cursor::new(Vec::new());
+    let mut writer = EventWriter::new(&mut sink);
+    writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+        .unwrap();
+    writer.write_start_node("A", &[Property::I32(1)]).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    // The appending writer starts writing at the null record of the root node.
+    let mut reader = EventReader::new(Cursor::new(sink.get_ref().clone()));
+    reader.read_all(None).unwrap();
+    let start_pos = reader.position() - 13;
+    let mut writer = EventWriter::append(&mut sink, EmitterConfig::new()).unwrap();
+    assert_eq!(writer.nodes_written(), 0);
+    writer.write_start_node("B", &[]).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    assert_eq!(writer.nodes_written(), 1);
+    assert_eq!(writer.properties_written(), 0);
+    let bytes_written = writer.bytes_written();
+    assert_eq!(start_pos + bytes_written, sink.get_ref().len() as u64);
+}
+
+#[test]
+fn into_inner() {
+    let mut writer = EventWriter::new(Cursor::new(Vec::new()));
+    writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+        .unwrap();
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    let cursor = writer.into_inner();
+    assert_eq!(cursor.position(), cursor.get_ref().len() as u64);
+    let events = EventReader::new(Cursor::new(cursor.into_inner()))
+        .read_all(None)
+        .unwrap();
+    assert_eq!(events.len(), 2);
+}
+
+#[test]
+fn finish() {
+    let write_node = |writer: &mut EventWriter<Cursor<Vec<u8>>>| {
+        writer
+            .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+            .unwrap();
+        writer
+            .write(start_node("Node", &[Property::I32(1)]))
+            .unwrap();
+        writer.write(writer::FbxEvent::EndNode).unwrap();
+    };
+
+    // `EndFbx` is written by `finish()`.
+    let mut writer = EventWriter::to_vec();
+    write_node(&mut writer);
+    let finished = writer.finish().unwrap().into_inner();
+    // `EndFbx` is not written twice.
+    let mut writer = EventWriter::to_vec();
+    write_node(&mut writer);
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    assert_eq!(writer.finish().unwrap().into_inner(), finished);
+
+    let mut reader = ParserConfig::new()
+        .strict(true)
+        .read_to_end(true)
+        .create_reader(Cursor::new(finished));
+    reader.read_all(None).unwrap();
+    assert!(reader.footer_info().is_some());
+
+    let mut writer = start_doc(EmitterConfig::new());
+    writer.write(start_node("Unclosed", &[])).unwrap();
+    assert!(matches!(writer.finish(), Err(Error::UnclosedNodes(1))));
+
+    assert!(matches!(
+        EventWriter::to_vec().finish(),
+        Err(Error::FbxNotStarted)
+    ));
+}
+
+#[test]
+fn transcode_start_node() {
+    let event = FbxEvent::StartNode {
+        name: "Node".to_string(),
+        properties: vec![
+            OwnedProperty::I32(42),
+            OwnedProperty::String("str".to_string()),
+            OwnedProperty::VecF64(vec![1.0, 2.0]),
+        ],
+    };
+
+    let data = write_doc(vec![event.as_writer_event(), writer::FbxEvent::EndNode]);
+
+    let events = EventReader::new(Cursor::new(data)).read_all(None).unwrap();
+    match events[1] {
+        FbxEvent::StartNode {
+            ref name,
+            ref properties,
+        } => {
+            assert_eq!(name, "Node");
+            assert_eq!(
+                properties,
+                &[
+                    OwnedProperty::I32(42),
+                    OwnedProperty::String("str".to_string()),
+                    OwnedProperty::VecF64(vec![1.0, 2.0]),
+                ]
+            );
+        }
+        ref ev => panic!("unexpected event: {:?}", ev),
+    }
+}
+
+#[test]
+fn raw_compressed_arrays_round_trip() {
+    let original = write_doc(vec![
+        start_node(
+            "Vertices",
+            &[
+                Property::VecF64(&[0.0, 1.0, 2.0]),
+                Property::VecI32(&[0, 1, 2]),
+            ],
+        ),
+        writer::FbxEvent::EndNode,
+    ]);
+
+    let events = ParserConfig::new()
+        .raw_compressed_arrays(true)
+        .create_reader(Cursor::new(&original[..]))
+        .read_all(None)
+        .unwrap();
+    match events[1] {
+        FbxEvent::StartNode { ref properties, .. } => {
+            assert!(properties.iter().all(|prop| matches!(
+                *prop,
+                OwnedProperty::CompressedArray {
+                    array_length: 3,
+                    ..
+                }
+            )));
+            assert_eq!(properties[0].get_vec_f64(), None);
+        }
+        ref ev => panic!("unexpected event: {:?}", ev),
+    }
+
+    let mut writer = EventWriter::to_vec();
+    for event in &events {
+        writer.write(event.as_writer_event()).unwrap();
+    }
+    assert_eq!(writer.into_vec(), original);
+}
+
+#[test]
+fn empty_arrays_round_trip() {
+    let properties = [
+        Property::VecBool(&[]),
+        Property::VecI32(&[]),
+        Property::VecI64(&[]),
+        Property::VecF32(&[]),
+        Property::VecF64(&[]),
+    ];
+    for &compress in &[true, false] {
+        for &streamed in &[true, false] {
+            let config = EmitterConfig::new().compress_arrays(compress);
+            let data = write_doc_with(config, |writer| {
+                if streamed {
+                    writer.write(start_node("Arrays", &[])).unwrap();
+                    writer.begin_array_property::<bool>().unwrap();
+                    writer.end_array_property().unwrap();
+                    writer.begin_array_property::<i32>().unwrap();
+                    writer.end_array_property().unwrap();
+                    writer.begin_array_property::<i64>().unwrap();
+                    writer.end_array_property().unwrap();
+                    writer.begin_array_property::<f32>().unwrap();
+                    writer.end_array_property().unwrap();
+                    writer.begin_array_property::<f64>().unwrap();
+                    writer.end_array_property().unwrap();
+                } else {
+                    writer.write(start_node("Arrays", &properties)).unwrap();
+                }
+                writer.write(writer::FbxEvent::EndNode).unwrap();
+            });
+
+            // Each array is written as a type code and a header of zero length, plain encoding,
+            // and no data.
+            let name_pos = data.windows(6).position(|w| w == b"Arrays").unwrap();
+            let props = &data[name_pos + 6..name_pos + 6 + properties.len() * 13];
+            for (prop, bytes) in properties.iter().zip(props.chunks(13)) {
+                assert_eq!(bytes[0], prop.fbx_type_code());
+                assert_eq!(bytes[1..], [0; 12]);
+            }
+
+            let events = ParserConfig::new()
+                .strict(true)
+                .create_reader(Cursor::new(data))
+                .read_all(None)
+                .unwrap();
+            match events[1] {
+                FbxEvent::StartNode { ref properties, .. } => assert_eq!(
+                    properties,
+                    &[
+                        OwnedProperty::VecBool(vec![]),
+                        OwnedProperty::VecI32(vec![]),
+                        OwnedProperty::VecI64(vec![]),
+                        OwnedProperty::VecF32(vec![]),
+                        OwnedProperty::VecF64(vec![]),
+                    ],
+                    "compress_arrays = {}, streamed = {}",
+                    compress,
+                    streamed
+                ),
+                ref ev => panic!("unexpected event: {:?}", ev),
+            }
+        }
+    }
+}
+
+#[test]
+fn bool_array_round_trip() {
+    for &compress in &[true, false] {
+        let data = write_doc_with(EmitterConfig::new().compress_arrays(compress), |writer| {
+            writer
+                .write(start_node(
+                    "Bools",
+                    &[Property::VecBool(&[true, false, true]), Property::I32(1)],
+                ))
+                .unwrap();
+            writer.write(writer::FbxEvent::EndNode).unwrap();
+        });
+
+        let events = EventReader::new(Cursor::new(data)).read_all(None).unwrap();
+        match events[1] {
+            FbxEvent::StartNode { ref properties, .. } => assert_eq!(
+                properties,
+                &[
+                    OwnedProperty::VecBool(vec![true, false, true]),
+                    OwnedProperty::I32(1)
+                ],
+                "compress_arrays = {}",
+                compress
+            ),
+            ref ev => panic!("unexpected event: {:?}", ev),
+        }
+    }
+}
+
+#[test]
+fn streamed_array_properties() {
+    let values = (0..100_000)
+        .map(|i| f64::from(i) * 0.25)
+        .collect::<Vec<_>>();
+    for &(version, compress) in &[(7400, true), (7400, false), (7500, true), (7500, false)] {
+        let mut writer = EmitterConfig::new()
+            .compress_arrays(compress)
+            .create_writer(Cursor::new(Vec::new()));
+        writer
+            .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(version)))
+            .unwrap();
+        writer
+            .write(start_node("Geometry", &[Property::I32(1)]))
+            .unwrap();
+        writer.begin_array_property::<f64>().unwrap();
+        for &v in &values {
+            writer.push_f64(v).unwrap();
+        }
+        writer.end_array_property().unwrap();
+        writer.begin_array_property::<bool>().unwrap();
+        writer.push_bool(true).unwrap();
+        writer.push_bool(false).unwrap();
+        writer.end_array_property().unwrap();
+        writer.write(start_node("Empty", &[])).unwrap();
+        writer.begin_array_property::<i32>().unwrap();
+        writer.end_array_property().unwrap();
+        writer.write(writer::FbxEvent::EndNode).unwrap();
+        writer.write(writer::FbxEvent::EndNode).unwrap();
+        writer.write(writer::FbxEvent::EndFbx).unwrap();
+        let data = writer.into_vec();
+
+        let events = EventReader::new(Cursor::new(data)).read_all(None).unwrap();
+        match events[1] {
+            FbxEvent::StartNode { ref properties, .. } => assert_eq!(
+                properties,
+                &[
+                    OwnedProperty::I32(1),
+                    OwnedProperty::VecF64(values.clone()),
+                    OwnedProperty::VecBool(vec![true, false]),
+                ],
+                "version = {}, compress_arrays = {}",
+                version,
+                compress
+            ),
+            ref ev => panic!("unexpected event: {:?}", ev),
+        }
+        match events[2] {
+            FbxEvent::StartNode {
+                ref name,
+                ref properties,
+            } => {
+                assert_eq!(name, "Empty");
+                assert_eq!(properties, &[OwnedProperty::VecI32(vec![])]);
+            }
+            ref ev => panic!("unexpected event: {:?}", ev),
+        }
+    }
+}
+
+#[test]
+fn reject_misused_array_properties() {
+    let mut writer = start_doc(EmitterConfig::new());
+    assert!(matches!(
+        writer.push_f64(1.0),
+        Err(Error::InvalidArrayProperty(_))
+    ));
+
+    let mut writer = start_doc(EmitterConfig::new());
+    writer.write(start_node("Node", &[])).unwrap();
+    writer.begin_array_property::<f64>().unwrap();
+    assert!(matches!(
+        writer.push_i32(1),
+        Err(Error::InvalidArrayProperty(_))
+    ));
+
+    let mut writer = start_doc(EmitterConfig::new());
+    writer.write(start_node("Node", &[])).unwrap();
+    writer.begin_array_property::<f64>().unwrap();
+    assert!(matches!(
+        writer.write(start_node("Child", &[])),
+        Err(Error::InvalidArrayProperty(_))
+    ));
+
+    // Properties cannot be appended after a child node is started.
+    let mut writer = start_doc(EmitterConfig::new());
+    writer.write(start_node("Node", &[])).unwrap();
+    writer.write(start_node("Child", &[])).unwrap();
+    writer.write(writer::FbxEvent::EndNode).unwrap();
+    assert!(matches!(
+        writer.begin_array_property::<f64>(),
+        Err(Error::InvalidArrayProperty(_))
+    ));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn nonstandard_zstd_arrays() {
+    let config = EmitterConfig::new().nonstandard_zstd_arrays(true);
+    let data = write_doc_with(config, |writer| {
+        writer
+            .write(start_node(
+                "Arrays",
+                &[Property::VecI32(&[1, 2, 3]), Property::VecF64(&[0.5, 1.5])],
+            ))
+            .unwrap();
+        writer.write(writer::FbxEvent::EndNode).unwrap();
+    });
+
+    let events = ParserConfig::new()
+        .strict(true)
+        .create_reader(Cursor::new(data))
+        .read_all(None)
+        .unwrap();
+    match events[1] {
+        FbxEvent::StartNode { ref properties, .. } => assert_eq!(
+            properties,
+            &[
+                OwnedProperty::VecI32(vec![1, 2, 3]),
+                OwnedProperty::VecF64(vec![0.5, 1.5])
+            ]
+        ),
+        ref ev => panic!("unexpected event: {:?}", ev),
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn nonstandard_zstd_arrays_without_feature() {
+    let mut writer = start_doc(EmitterConfig::new().nonstandard_zstd_arrays(true));
+    assert!(matches!(
+        writer.write(start_node("Arrays", &[Property::VecI32(&[1, 2, 3])])),
+        Err(Error::InvalidOption(_))
+    ));
+}
+
+#[test]
+fn templates() {
+    let vertices = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let polygon_vertex_index = [0, 1, -3];
+    let data = write_doc_with(EmitterConfig::new(), |writer| {
+        writer.write(writer::node("Objects")).unwrap();
+        templates::Geometry::new(1, "Triangle", &vertices, &polygon_vertex_index)
+            .write(writer)
+            .unwrap();
+        templates::Model::new(2, "Triangle", "Mesh")
+            .translation([1.0, 2.0, 3.0])
+            .write(writer)
+            .unwrap();
+        writer.write(writer::FbxEvent::EndNode).unwrap();
+    });
+
+    let arena = Arena::read(&mut EventReader::new(Cursor::new(data))).unwrap();
+    let objects = arena.child_by_name(arena.root(), "Objects").unwrap();
+
+    let geometry = arena.child_by_name(objects, "Geometry").unwrap();
+    assert_eq!(
+        arena.node(geometry).properties,
+        vec![
+            OwnedProperty::I64(1),
+            OwnedProperty::String("Triangle\u{0}\u{1}Geometry".to_string()),
+            OwnedProperty::String("Mesh".to_string()),
+        ]
+    );
+    let child = |parent: usize, name: &str| {
+        let index = arena.child_by_name(parent, name).unwrap();
+        arena.node(index).properties.clone()
+    };
+    assert_eq!(
+        child(geometry, "Vertices"),
+        vec![OwnedProperty::VecF64(vertices.to_vec())]
+    );
+    assert_eq!(
+        child(geometry, "PolygonVertexIndex"),
+        vec![OwnedProperty::VecI32(polygon_vertex_index.to_vec())]
+    );
+
+    let model = arena.child_by_name(objects, "Model").unwrap();
+    assert_eq!(
+        arena.node(model).properties[1],
+        OwnedProperty::String("Triangle\u{0}\u{1}Model".to_string())
+    );
+    assert_eq!(child(model, "Version"), vec![OwnedProperty::I32(232)]);
+    let props70 = arena.child_by_name(model, "Properties70").unwrap();
+    let ps = arena
+        .children(props70)
+        .map(|p| arena.node(p).properties.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ps.len(), 3);
+    assert_eq!(
+        ps[0],
+        vec![
+            OwnedProperty::String("Lcl Translation".to_string()),
+            OwnedProperty::String("Lcl Translation".to_string()),
+            OwnedProperty::String("".to_string()),
+            OwnedProperty::String("A".to_string()),
+            OwnedProperty::F64(1.0),
+            OwnedProperty::F64(2.0),
+            OwnedProperty::F64(3.0),
+        ]
+    );
+    assert_eq!(ps[2][4..], vec![OwnedProperty::F64(1.0); 3][..]);
+}
+
+#[test]
+fn encode_polygons_round_trip() {
+    let polygons = vec![vec![0, 1, 2], vec![2, 3, 4, 0], vec![5], vec![0, i32::MAX]];
+    let indices = templates::encode_polygons(&polygons);
+    assert_eq!(indices, vec![0, 1, -3, 2, 3, 4, -1, -6, 0, i32::MIN]);
+    assert_eq!(
+        OwnedProperty::VecI32(indices).decode_polygons(),
+        Ok(polygons)
+    );
+    assert!(templates::encode_polygons(&[]).is_empty());
+    // Empty polygons are skipped.
+    assert_eq!(
+        templates::encode_polygons(&[vec![], vec![1, 2]]),
+        vec![1, -3]
+    );
+}
+
+#[test]
+fn raw_node_passthrough() {
+    let mut reader = EventReader::new(std::fs::File::open(BLENDER_FBX7400).unwrap());
+    let mut writer = EventWriter::to_vec();
+    let mut depth = 0;
+    let mut num_raw_nodes = 0;
+    loop {
+        let event = reader.next().unwrap();
+        match event {
+            FbxEvent::StartNode { ref name, .. } if depth == 0 && name == "Objects" => {
+                let raw = reader.capture_node().unwrap().unwrap();
+                assert_eq!(raw.version, 7400);
+                writer.write_raw_node(&raw).unwrap();
+                num_raw_nodes += 1;
+                continue;
+            }
+            FbxEvent::StartNode { .. } => depth += 1,
+            FbxEvent::EndNode => depth -= 1,
+            _ => {}
+        }
+        writer.write(event.as_writer_event()).unwrap();
+        if let FbxEvent::EndFbx = event {
+            break;
+        }
+    }
+    assert_eq!(num_raw_nodes, 1);
+    // `capture_node` is available only right after `StartNode`.
+    assert!(reader.capture_node().unwrap().is_none());
+
+    let expected = read_events(BLENDER_FBX7400);
+    let nodes_written = writer.nodes_written();
+    let events = EventReader::new(Cursor::new(writer.into_vec()))
+        .read_all(None)
+        .unwrap();
+    assert_eq!(format!("{:?}", events), format!("{:?}", expected));
+    let num_nodes = expected
+        .iter()
+        .filter(|event| matches!(event, FbxEvent::StartNode { .. }))
+        .count();
+    assert_eq!(nodes_written, num_nodes as u64);
+}
+
+#[test]
+fn raw_node_version_mismatch() {
+    let data = write_doc(vec![
+        start_node("Node", &[Property::I32(1)]),
+        writer::FbxEvent::EndNode,
+    ]);
+    let mut reader = EventReader::new(Cursor::new(data));
+    reader.next().unwrap();
+    reader.next().unwrap();
+    let raw = reader.capture_node().unwrap().unwrap();
+
+    let mut writer = EventWriter::to_vec();
+    writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7500)))
+        .unwrap();
+    assert!(matches!(
+        writer.write_raw_node(&raw),
+        Err(Error::DataError(_))
+    ));
+}
+
+#[test]
+fn reject_compressed_array_with_invalid_type_code() {
+    let mut writer = start_doc(EmitterConfig::new());
+    let prop = Property::CompressedArray {
+        type_code: b'S',
+        array_length: 0,
+        data: &[],
+    };
+    assert!(matches!(
+        writer.write(start_node("Node", &[prop])),
+        Err(Error::InvalidArrayProperty(_))
+    ));
+}
@@ -0,0 +1,157 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io::{self, Cursor, Read};
+use std::rc::Rc;
+
+use fbx_direct::common::{FbxFormatType, Property};
+use fbx_direct::reader::{Error, EventReader, FbxEvent};
+use fbx_direct::writer::{self, EmitterConfig, EventWriter};
+
+/// Binary FBX 7.4 exported by Blender 2.72b.
+pub const BLENDER_FBX7400: &str = "tests/assets/blender_2_72b_default-fbx7400.fbx";
+/// Empty Binary FBX 7.5 exported by FBX SDK 2016.1.2.
+pub const FBXSDK_FBX7500: &str = "tests/assets/fbxsdk-2016.1.2-empty-binary-fbx7500.fbx";
+/// ASCII FBX 7.4 written by hand.
+pub const SYNTHETIC_ASCII_FBX7400: &str = "tests/assets/synthetic-ascii-fbx7400.fbx";
+
+/// Returns magic binary and the given version of Binary FBX.
+pub fn fbx_header(version: u32) -> Vec<u8> {
+    let mut data = b"Kaydara FBX Binary  \x00\x1a\x00".to_vec();
+    data.extend_from_slice(&version.to_le_bytes());
+    data
+}
+
+/// Returns magic binary and version of Binary FBX 7.4.
+pub fn fbx7400_header() -> Vec<u8> {
+    fbx_header(7400)
+}
+
+/// Appends a node record with 32 bit header (for FBX 7.4 or earlier) to `data`.
+///
+/// `properties` is a list of encoded properties (type codes and values).
+pub fn push_node32(
+    data: &mut Vec<u8>,
+    name: &str,
+    properties: &[&[u8]],
+    children: &dyn Fn(&mut Vec<u8>),
+) {
+    let header_pos = data.len();
+    data.extend_from_slice(&[0; 12]);
+    data.push(name.len() as u8);
+    data.extend_from_slice(name.as_bytes());
+    let props_pos = data.len();
+    for prop in properties {
+        data.extend_from_slice(prop);
+    }
+    let property_list_len = (data.len() - props_pos) as u32;
+    let children_pos = data.len();
+    children(data);
+    if data.len() != children_pos || properties.is_empty() {
+        data.extend_from_slice(&[0; 13]);
+    }
+    let end_offset = data.len() as u32;
+    data[header_pos..header_pos + 4].copy_from_slice(&end_offset.to_le_bytes());
+    data[header_pos + 4..header_pos + 8].copy_from_slice(&(properties.len() as u32).to_le_bytes());
+    data[header_pos + 8..header_pos + 12].copy_from_slice(&property_list_len.to_le_bytes());
+}
+
+/// Returns an encoded `I32` property.
+pub fn prop_i32(v: i32) -> Vec<u8> {
+    let mut prop = vec![b'I'];
+    prop.extend_from_slice(&v.to_le_bytes());
+    prop
+}
+
+/// Returns an FBX 7.4 document with the nodes appended by `build`, without the footer.
+pub fn node_doc(build: &dyn Fn(&mut Vec<u8>)) -> Vec<u8> {
+    let mut data = fbx7400_header();
+    build(&mut data);
+    // Null record of the implicit root node.
+    data.extend_from_slice(&[0; 13]);
+    data
+}
+
+/// Reads events until the reader stops, including the error if any.
+pub fn read_all<R: Read>(reader: EventReader<R>) -> Vec<Result<FbxEvent, Error>> {
+    reader.into_iter().collect()
+}
+
+/// Reads events of the given data with the default config.
+pub fn read_doc(data: &[u8]) -> Vec<Result<FbxEvent, Error>> {
+    read_all(EventReader::new(Cursor::new(data)))
+}
+
+/// Reads all events of the given file.
+pub fn read_events(filename: &str) -> Vec<FbxEvent> {
+    EventReader::new(std::fs::File::open(filename).unwrap())
+        .read_all(None)
+        .unwrap()
+}
+
+/// Returns a `StartNode` event of the writer.
+pub fn start_node<'a>(name: &'a str, properties: &'a [Property<'a>]) -> writer::FbxEvent<'a> {
+    writer::FbxEvent::StartNode {
+        name,
+        properties: properties.into(),
+    }
+}
+
+/// Returns a writer to memory, with `StartFbx` of Binary FBX 7.4 already written.
+pub fn start_doc(config: EmitterConfig) -> EventWriter<Cursor<Vec<u8>>> {
+    let mut writer = config.create_writer(Cursor::new(Vec::new()));
+    writer
+        .write(writer::FbxEvent::StartFbx(FbxFormatType::Binary(7400)))
+        .unwrap();
+    writer
+}
+
+/// Writes Binary FBX 7.4 with the given config.
+///
+/// `StartFbx` and `EndFbx` are written around the contents written by `build`.
+pub fn write_doc_with(
+    config: EmitterConfig,
+    build: impl FnOnce(&mut EventWriter<Cursor<Vec<u8>>>),
+) -> Vec<u8> {
+    let mut writer = start_doc(config);
+    build(&mut writer);
+    writer.write(writer::FbxEvent::EndFbx).unwrap();
+    writer.into_vec()
+}
+
+/// Writes the given events as Binary FBX 7.4 with the default config.
+///
+/// `StartFbx` and `EndFbx` are written around the events.
+pub fn write_doc<'a>(events: impl IntoIterator<Item = writer::FbxEvent<'a>>) -> Vec<u8> {
+    write_doc_with(EmitterConfig::new(), |writer| {
+        for event in events {
+            writer.write(event).unwrap();
+        }
+    })
+}
+
+/// A reader which fails with `WouldBlock` once when reaching the given position.
+pub struct FlakyReader {
+    pub inner: Rc<RefCell<Cursor<Vec<u8>>>>,
+    pub fail_at: u64,
+    pub failed: bool,
+}
+
+impl Read for FlakyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inner = self.inner.borrow_mut();
+        if self.failed {
+            return inner.read(buf);
+        }
+        let rest = self.fail_at - inner.position();
+        if rest == 0 {
+            self.failed = true;
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "not ready"));
+        }
+        let len = buf.len().min(rest as usize);
+        inner.read(&mut buf[..len])
+    }
+}